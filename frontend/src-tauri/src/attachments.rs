@@ -0,0 +1,191 @@
+//! ====== ATTACHMENTS ======
+//!
+//! Files attached to notes (pictures, recordings, PDFs) are kept in
+//! attachments/, named by the SHA-256 of their contents:
+//!
+//!   attachments/
+//!   └── {sha256}.{ext}    ← ext from the name of the imported file
+//!
+//! Importing the same bytes twice stores them once. Notes and canvases
+//! refer to an attachment by URI, messy://attachment/{sha256}: in a
+//! Markdown link or image, or in a media card's mediaUrl.
+//!
+//! An attachment is in use while some note or canvas refers to it,
+//! including notes in the trash, since they can be restored. Unused ones
+//! are only removed when garbage is collected. Old revisions don't keep an
+//! attachment: restoring one whose attachment was collected leaves a
+//! broken link.
+//!
+//! =========================
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::note_id::NoteId;
+use crate::store::{Item, VaultStore};
+
+pub const URI_PREFIX: &str = "messy://attachment/";
+
+/// Larger files are refused
+pub const MAX_SIZE: u64 = 256 * 1024 * 1024;
+
+const HASH_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Attachment {
+    /// SHA-256 of the contents, lowercase hex
+    pub hash: String,
+    /// Name under attachments/
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+    pub size: u64,
+    /// What notes and canvases put in a link to it
+    pub uri: String,
+    /// Notes whose text or canvas refers to it, trashed ones included
+    pub notes: Vec<NoteId>,
+}
+
+impl Attachment {
+    pub fn new(hash: &str, file_name: String, size: u64, notes: Vec<NoteId>) -> Self {
+        Attachment { hash: hash.to_string(), file_name, size, uri: uri(hash), notes }
+    }
+}
+
+pub fn hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn uri(hash: &str) -> String {
+    format!("{}{}", URI_PREFIX, hash)
+}
+
+pub fn is_hash(text: &str) -> bool {
+    text.len() == HASH_LEN && text.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// The hash an attachment's file name starts with, if it is one
+pub fn hash_of(file_name: &str) -> Option<&str> {
+    let stem = file_name.split('.').next()?;
+    is_hash(stem).then_some(stem)
+}
+
+/// The name to store `bytes` under: the hash, plus the original name's
+/// extension if it has a sensible one
+pub fn file_name(hash: &str, original_name: &str) -> String {
+    let extension = Path::new(original_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .filter(|e| !e.is_empty() && e.len() <= 10 && e.bytes().all(|b| b.is_ascii_alphanumeric()));
+
+    match extension {
+        Some(ext) => format!("{}.{}", hash, ext),
+        None => hash.to_string(),
+    }
+}
+
+/// Hashes of the attachments `text` links to, in order of first mention
+pub fn references(text: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+
+    for (start, _) in text.match_indices(URI_PREFIX) {
+        let rest = &text[start + URI_PREFIX.len()..];
+        if let Some(hash) = rest.get(..HASH_LEN).filter(|h| is_hash(h)) {
+            // Longer runs of hex aren't one of ours
+            let ends = rest[HASH_LEN..].chars().next().is_none_or(|c| !c.is_ascii_hexdigit());
+            if ends && !found.iter().any(|f| f == hash) {
+                found.push(hash.to_string());
+            }
+        }
+    }
+
+    found
+}
+
+/// The file in the store holding the attachment with this hash
+pub fn find(hash: &str, store: &dyn VaultStore) -> Result<Option<String>, String> {
+    if !is_hash(hash) {
+        return Err(format!("\"{}\" is not an attachment hash", hash));
+    }
+    Ok(store.attachments()?.into_iter().find(|name| hash_of(name) == Some(hash)))
+}
+
+/// Stores `bytes`, imported from a file called `original_name`, unless the
+/// same contents are stored already. Returns the hash and the file name.
+pub fn import(bytes: &[u8], original_name: &str, store: &dyn VaultStore) -> Result<(String, String), String> {
+    if bytes.len() as u64 > MAX_SIZE {
+        return Err(format!("Attachments can be at most {} MB", MAX_SIZE / 1024 / 1024));
+    }
+
+    let hash = hash(bytes);
+    if let Some(existing) = find(&hash, store)? {
+        return Ok((hash, existing));
+    }
+
+    let name = file_name(&hash, original_name);
+    store.write(&Item::Attachment(name.clone()), bytes)?;
+    Ok((hash, name))
+}
+
+/// Which notes refer to which attachments, given each note's texts (note
+/// file, canvas file)
+pub fn count_references<'a>(texts: impl IntoIterator<Item = (&'a NoteId, &'a str)>) -> BTreeMap<String, Vec<NoteId>> {
+    let mut notes: BTreeMap<String, Vec<NoteId>> = BTreeMap::new();
+
+    for (id, text) in texts {
+        for hash in references(text) {
+            let referrers = notes.entry(hash).or_default();
+            if !referrers.contains(id) {
+                referrers.push(id.clone());
+            }
+        }
+    }
+
+    notes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[test]
+    fn imports_are_stored_once_under_their_hash() {
+        let store = MemoryStore::default();
+
+        let (hash, name) = import(b"picture", "Holiday.JPG", &store).unwrap();
+        assert!(is_hash(&hash));
+        assert_eq!(name, format!("{}.jpg", hash));
+
+        // Same bytes under another name: the stored file is reused
+        assert_eq!(import(b"picture", "copy.png", &store).unwrap(), (hash.clone(), name.clone()));
+        assert_eq!(store.attachments().unwrap(), std::slice::from_ref(&name));
+        assert_eq!(find(&hash, &store).unwrap(), Some(name));
+        assert!(find("../notes", &store).is_err());
+
+        assert_eq!(file_name(&hash, "no extension"), hash);
+        assert_eq!(file_name(&hash, "weird.ex t"), hash);
+    }
+
+    #[test]
+    fn finds_references_in_text() {
+        let a = hash(b"a");
+        let b = hash(b"b");
+        let text = format!(
+            "![cat]({}{}) and [doc]({}{}), again ![]({}{})\n\"mediaUrl\": \"{}{}ff\"",
+            URI_PREFIX, a, URI_PREFIX, b, URI_PREFIX, a, URI_PREFIX, b,
+        );
+
+        assert_eq!(references(&text), [a.clone(), b.clone()]);
+        assert!(references(&format!("{}{}", URI_PREFIX, &a[..10])).is_empty());
+
+        let one = NoteId::parse("one").unwrap();
+        let two = NoteId::parse("two").unwrap();
+        let link = uri(&a);
+        let counts = count_references([(&one, link.as_str()), (&one, link.as_str()), (&two, text.as_str())]);
+        assert_eq!(counts[&a], [one, two.clone()]);
+        assert_eq!(counts[&b], [two]);
+    }
+}
@@ -0,0 +1,478 @@
+//! ====== MESSY-CLI ======
+//!
+//! The vault from a terminal: the same storage code as the app, without a
+//! window. Handy for scripts, quick captures and checking a vault over ssh.
+//!
+//!   messy-cli [--vault PATH] <command> [args]
+//!
+//! Without --vault it works on the vault the app opened last. It can run
+//! while the app is open; the app picks up its changes like any other
+//! edit made outside it (see watcher.rs).
+//!
+//! Exit status: 0 on success, 1 if `check` leaves problems behind, 2 on
+//! errors.
+//!
+//! ======================
+
+use messy_notes::{
+    create_note, delete_note, find_note, fsck, get_folders, get_notes, import_obsidian_vault,
+    note_markdown, note_tags, prepare_vault, render_note, reorder_notes, search_notes,
+    update_note, AppState, Folder, NewNote, Note, NoteChanges,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{IsTerminal, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: messy-cli [--vault PATH] <command> [args]
+
+Commands:
+  list [--tag TAG] [--json]          List notes in sidebar order
+  show NOTE [--json]                 Print a note's Markdown
+  new TITLE [TEXT...]                Create a note; TEXT is read from stdin if piped
+      [--folder FOLDER] [--tag TAG] [--ephemeral]
+  append NOTE [TEXT...] [--create]   Add a paragraph to a note
+      [--ephemeral]
+  mv NOTE FOLDER                     Move a note into a folder, or / for the root
+  rm NOTE                            Move a note to the trash
+  search QUERY [--limit N] [--json]  Full-text search
+  export DIR                         Write every note to DIR as folder/title.md
+  import-obsidian DIR [--dry-run]    Import an Obsidian vault into a new folder
+      [--json]
+  check [--repair]                   Look for (and fix) inconsistencies
+  help                               Show this message
+
+NOTE is a note id, title or alias. FOLDER is a folder id or name.";
+
+/// Options that take a value; every other option is a flag
+const VALUE_OPTIONS: [&str; 4] = ["--vault", "--tag", "--folder", "--limit"];
+
+/// The command line, split into positional arguments and options
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Vec<String>>,
+}
+
+impl Args {
+    fn parse(raw: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = Args { positional: Vec::new(), options: HashMap::new() };
+        let mut raw = raw.into_iter();
+
+        while let Some(arg) = raw.next() {
+            if arg == "--" {
+                args.positional.extend(raw.by_ref());
+            } else if let Some((name, value)) = arg.split_once('=').filter(|_| arg.starts_with("--")) {
+                args.options.entry(name.to_string()).or_default().push(value.to_string());
+            } else if VALUE_OPTIONS.contains(&arg.as_str()) {
+                let value = raw.next().ok_or_else(|| format!("{} needs a value", arg))?;
+                args.options.entry(arg).or_default().push(value);
+            } else if arg.starts_with("--") {
+                args.options.entry(arg).or_default();
+            } else {
+                args.positional.push(arg);
+            }
+        }
+
+        Ok(args)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options.get(name).and_then(|v| v.last()).map(|v| v.as_str())
+    }
+
+    fn values(&self, name: &str) -> Vec<String> {
+        self.options.get(name).cloned().unwrap_or_default()
+    }
+
+    /// The n-th positional argument after the command
+    fn arg(&self, n: usize, what: &str) -> Result<&str, String> {
+        self.positional.get(n + 1)
+            .map(|a| a.as_str())
+            .ok_or_else(|| format!("Missing {}\n\n{}", what, USAGE))
+    }
+
+    /// Positional arguments from the n-th on, joined, or else stdin when it
+    /// isn't a terminal
+    fn text_from(&self, n: usize) -> Result<Option<String>, String> {
+        let words = self.positional.get(n + 1..).unwrap_or_default();
+        if !words.is_empty() {
+            return Ok(Some(words.join(" ")));
+        }
+        if std::io::stdin().is_terminal() {
+            return Ok(None);
+        }
+
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text).map_err(|e| e.to_string())?;
+        Ok(Some(text).filter(|t| !t.trim().is_empty()))
+    }
+}
+
+fn main() -> ExitCode {
+    let result = Args::parse(std::env::args().skip(1)).and_then(|args| run(&args));
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("messy-cli: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<ExitCode, String> {
+    let command = args.positional.first().map(|c| c.as_str()).unwrap_or("help");
+    if matches!(command, "help" | "-h") || args.flag("--help") {
+        println!("{}", USAGE);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let data_dir = match args.value("--vault") {
+        Some(dir) => PathBuf::from(dir),
+        None => messy_notes::vaults::startup_dir()?,
+    };
+    if !data_dir.is_dir() {
+        return Err(format!("No vault at {}", data_dir.display()));
+    }
+    let state = AppState::new(data_dir);
+
+    // Writes go through the same upgrade and recovery the app does at startup
+    let imports = command == "import-obsidian" && !args.flag("--dry-run");
+    if matches!(command, "new" | "append" | "mv" | "rm") || imports {
+        prepare_vault(&state)?;
+    }
+
+    match command {
+        "list" => list(args, &state)?,
+        "show" => show(args, &state)?,
+        "new" => new(args, &state)?,
+        "append" => append(args, &state)?,
+        "mv" => move_note(args, &state)?,
+        "rm" => remove(args, &state)?,
+        "search" => search(args, &state)?,
+        "export" => export(args, &state)?,
+        "import-obsidian" => import_obsidian(args, &state)?,
+        "check" => return check(args, &state),
+        other => return Err(format!("Unknown command \"{}\"\n\n{}", other, USAGE)),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+// ==================== COMMANDS ====================
+
+fn list(args: &Args, state: &AppState) -> Result<(), String> {
+    let mut notes = get_notes(state)?;
+    if let Some(tag) = args.value("--tag") {
+        let tag = messy_notes::tags::normalize(tag).ok_or_else(|| format!("\"{}\" is not a tag", tag))?;
+        notes.retain(|note| note_tags(note).contains(&tag));
+    }
+
+    if args.flag("--json") {
+        return print_json(&notes);
+    }
+
+    let folders = get_folders(state)?;
+    for note in &notes {
+        let folder = note.folder_id.as_deref()
+            .map(|id| format!("{}/", folder_path(id, &folders).join("/")))
+            .unwrap_or_default();
+        println!("{}  {}{}", note.id, folder, note.title);
+    }
+    Ok(())
+}
+
+fn show(args: &Args, state: &AppState) -> Result<(), String> {
+    let note = find_note(args.arg(0, "NOTE")?, state)?;
+
+    if args.flag("--json") {
+        return print_json(&note);
+    }
+    print!("{}", note_markdown(&note));
+    Ok(())
+}
+
+fn new(args: &Args, state: &AppState) -> Result<(), String> {
+    let title = args.arg(0, "TITLE")?.to_string();
+    let folder_id = match args.value("--folder") {
+        Some(folder) => find_folder(folder, state)?,
+        None => None,
+    };
+
+    let note = create_note(NewNote {
+        title: Some(title),
+        raw_text: args.text_from(1)?,
+        // Scripts write notes to keep, so they don't expire unless asked to
+        ephemeral: Some(args.flag("--ephemeral")),
+        folder_id,
+        tags: Some(args.values("--tag")),
+        ..NewNote::default()
+    }, state)?;
+
+    println!("{}", note.id);
+    Ok(())
+}
+
+fn append(args: &Args, state: &AppState) -> Result<(), String> {
+    let query = args.arg(0, "NOTE")?;
+    let text = args.text_from(1)?.ok_or("Nothing to append")?;
+
+    let note = match find_note(query, state) {
+        Ok(note) => note,
+        Err(_) if args.flag("--create") => create_note(NewNote {
+            title: Some(query.to_string()),
+            ephemeral: Some(args.flag("--ephemeral")),
+            ..NewNote::default()
+        }, state)?,
+        Err(e) => return Err(e),
+    };
+
+    let body = note_markdown(&note);
+    let body = match body.trim_end() {
+        "" => text.trim().to_string(),
+        existing => format!("{}\n\n{}", existing, text.trim()),
+    };
+
+    update_note(note.id.clone(), NoteChanges { raw_text: Some(body), ..NoteChanges::default() }, state)?;
+    println!("{}", note.id);
+    Ok(())
+}
+
+fn move_note(args: &Args, state: &AppState) -> Result<(), String> {
+    let note = find_note(args.arg(0, "NOTE")?, state)?;
+    let folder_id = find_folder(args.arg(1, "FOLDER")?, state)?;
+
+    // Moved to the end of the folder, as dropping it below the last note would
+    reorder_notes(note.id, folder_id, i32::MAX, state)
+}
+
+fn remove(args: &Args, state: &AppState) -> Result<(), String> {
+    let note = find_note(args.arg(0, "NOTE")?, state)?;
+    delete_note(note.id, state)?;
+
+    println!("Moved \"{}\" to the trash", note.title);
+    Ok(())
+}
+
+fn search(args: &Args, state: &AppState) -> Result<(), String> {
+    let query = args.arg(0, "QUERY")?.to_string();
+    let limit = match args.value("--limit") {
+        Some(n) => Some(n.parse::<usize>().map_err(|_| format!("--limit must be a number, not \"{}\"", n))?),
+        None => None,
+    };
+
+    let hits = search_notes(query, limit, state)?;
+    if args.flag("--json") {
+        return print_json(&hits);
+    }
+
+    for hit in &hits {
+        let snippet: String = hit.snippet.iter()
+            .map(|part| if part.highlight { format!("**{}**", part.text) } else { part.text.clone() })
+            .collect();
+        println!("{}  {}\n    {}", hit.id, hit.title, snippet.trim());
+    }
+    Ok(())
+}
+
+fn export(args: &Args, state: &AppState) -> Result<(), String> {
+    let out_dir = Path::new(args.arg(0, "DIR")?);
+    let notes = get_notes(state)?;
+    let folders = get_folders(state)?;
+
+    for (path, note) in export_paths(&notes, &folders) {
+        let path = out_dir.join(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        fs::write(&path, render_note(note)?).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+
+    println!("Exported {} note(s) to {}", notes.len(), out_dir.display());
+    Ok(())
+}
+
+fn import_obsidian(args: &Args, state: &AppState) -> Result<(), String> {
+    let dir = args.arg(0, "DIR")?.to_string();
+    let report = import_obsidian_vault(dir, Some(args.flag("--dry-run")), state)?;
+    if args.flag("--json") {
+        return print_json(&report);
+    }
+
+    for skipped in &report.skipped {
+        println!("skipped: {}: {}", skipped.path, skipped.reason);
+    }
+    for link in &report.unresolved {
+        println!("unresolved: {}", link);
+    }
+    let verb = if report.dry_run { "Would import" } else { "Imported" };
+    println!(
+        "{} {} note(s), {} folder(s), {} attachment(s) and {} link(s) into folder \"{}\"",
+        verb, report.notes.len(), report.folders.len(), report.attachments.len(), report.links, report.name,
+    );
+    Ok(())
+}
+
+fn check(args: &Args, state: &AppState) -> Result<ExitCode, String> {
+    let report = if args.flag("--repair") {
+        fsck::repair(None, state)?
+    } else {
+        fsck::Repair { fixed: Vec::new(), remaining: fsck::check(state)? }
+    };
+
+    for issue in &report.fixed {
+        println!("fixed: {}", issue);
+    }
+    for issue in &report.remaining {
+        println!("{}", issue);
+    }
+
+    if report.remaining.is_empty() {
+        println!("{}: no problems found", state.data_dir().display());
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(1))
+    }
+}
+
+// ==================== HELPERS ====================
+
+fn print_json(value: &impl serde::Serialize) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// A folder by id or name; "/" is the root
+fn find_folder(query: &str, state: &AppState) -> Result<Option<String>, String> {
+    if query == "/" {
+        return Ok(None);
+    }
+
+    let folders = get_folders(state)?;
+    if let Some(folder) = folders.iter().find(|f| f.id == query) {
+        return Ok(Some(folder.id.clone()));
+    }
+
+    let named: Vec<&Folder> = folders.iter()
+        .filter(|f| f.name.eq_ignore_ascii_case(query.trim()))
+        .collect();
+    match named.as_slice() {
+        [folder] => Ok(Some(folder.id.clone())),
+        [] => Err(format!("No folder called \"{}\"", query)),
+        _ => Err(format!("More than one folder is called \"{}\"; use its id", query)),
+    }
+}
+
+/// Folder names from the root down to the folder `id`
+fn folder_path(id: &str, folders: &[Folder]) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut next = Some(id);
+
+    // Bounded so a parent cycle (see fsck.rs) can't loop forever
+    while let Some(folder) = next.and_then(|id| folders.iter().find(|f| f.id == id)) {
+        if path.len() > folders.len() {
+            break;
+        }
+        path.push(folder.name.clone());
+        next = folder.parent_id.as_deref();
+    }
+
+    path.reverse();
+    path
+}
+
+/// Where each note goes in an export: folder/sub-folder/title.md, with a
+/// number added when two notes in a folder share a title
+fn export_paths<'a>(notes: &'a [Note], folders: &[Folder]) -> Vec<(PathBuf, &'a Note)> {
+    let mut taken = HashMap::new();
+
+    notes.iter()
+        .map(|note| {
+            let dir: PathBuf = note.folder_id.as_deref()
+                .map(|id| folder_path(id, folders).iter().map(|name| file_name(name)).collect())
+                .unwrap_or_default();
+            let title = file_name(&note.title);
+
+            let count = taken.entry((dir.clone(), title.to_lowercase())).or_insert(0);
+            *count += 1;
+            let name = match *count {
+                1 => format!("{}.md", title),
+                n => format!("{} {}.md", title, n),
+            };
+            (dir.join(name), note)
+        })
+        .collect()
+}
+
+/// A title or folder name made safe to use as a file name
+fn file_name(name: &str) -> String {
+    let cleaned: String = name.trim()
+        .chars()
+        .map(|c| if c.is_control() || "/\\:*?\"<>|".contains(c) { '-' } else { c })
+        .collect();
+
+    match cleaned.trim_matches('.').trim() {
+        "" => "Untitled".to_string(),
+        _ => cleaned,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use messy_notes::note_id::NoteId;
+
+    fn args(raw: &[&str]) -> Args {
+        Args::parse(raw.iter().map(|a| a.to_string())).unwrap()
+    }
+
+    #[test]
+    fn parses_options_around_positional_arguments() {
+        let parsed = args(&["--vault", "/tmp/v", "new", "Groceries", "--tag", "home", "eggs", "--tag=errands", "--ephemeral"]);
+
+        assert_eq!(parsed.positional, ["new", "Groceries", "eggs"]);
+        assert_eq!(parsed.value("--vault"), Some("/tmp/v"));
+        assert_eq!(parsed.values("--tag"), ["home", "errands"]);
+        assert!(parsed.flag("--ephemeral") && !parsed.flag("--json"));
+        assert!(Args::parse(["--limit".to_string()]).is_err());
+    }
+
+    #[test]
+    fn export_paths_follow_folders_and_number_clashing_titles() {
+        let folder = |id: &str, name: &str, parent: Option<&str>| Folder {
+            id: id.to_string(),
+            name: name.to_string(),
+            parent_id: parent.map(|p| p.to_string()),
+            created_at: String::new(),
+            updated_at: String::new(),
+            expanded: false,
+        };
+        let folders = vec![folder("f1", "Work", None), folder("f2", "Q3: plans", Some("f1"))];
+
+        let note = |title: &str, folder_id: Option<&str>| {
+            let mut note: Note = serde_json::from_value(serde_json::json!({
+                "id": NoteId::new(), "title": title, "rawText": null, "content": null,
+                "updatedAt": "", "createdAt": "", "sticky": false, "ephemeral": false,
+                "archived": false, "type": "text", "color": "#ffffff",
+            })).unwrap();
+            note.folder_id = folder_id.map(|f| f.to_string());
+            note
+        };
+        let notes = vec![note("Ideas", Some("f2")), note("ideas", Some("f2")), note("a/b", None), note("..", None)];
+
+        let paths: Vec<PathBuf> = export_paths(&notes, &folders).into_iter().map(|(p, _)| p).collect();
+        assert_eq!(paths, [
+            PathBuf::from("Work/Q3- plans/Ideas.md"),
+            PathBuf::from("Work/Q3- plans/ideas 2.md"),
+            PathBuf::from("a-b.md"),
+            PathBuf::from("Untitled.md"),
+        ]);
+    }
+}
@@ -0,0 +1,445 @@
+//! ====== CANVAS MODEL ======
+//!
+//! The canvas of a note (canvas/{id}.json) as the frontend draws it:
+//!
+//!   nodes: cards laid out on the canvas; groups hold child nodes, whose
+//!          x/y are relative to the group and whose parentId names it
+//!   edges: connections from one node's side to another's
+//!
+//! Reading is strict about what the app relies on (ids, geometry, edge
+//! endpoints) and lenient about everything else: node types and variants
+//! this build doesn't know, and fields it has never heard of, are kept
+//! as they are and written back unchanged.
+//!
+//! `validate` lists what is wrong with a canvas; `repair` fixes it.
+//!
+//! ==========================
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Canvas {
+    #[serde(default)]
+    pub nodes: Vec<CanvasNode>,
+    #[serde(default)]
+    pub edges: Vec<CanvasEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanvasNode {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub node_type: NodeType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<Variant>,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub color: String,
+    #[serde(rename = "mediaUrl", default, skip_serializing_if = "Option::is_none")]
+    pub media_url: Option<String>,
+    /// MIME type of an attached file in mediaUrl, so it shows as audio or video
+    #[serde(rename = "mediaType", default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<CanvasNode>,
+    #[serde(rename = "parentId", default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// Fields written by newer versions of the app
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanvasEdge {
+    pub id: String,
+    #[serde(rename = "fromNode")]
+    pub from_node: String,
+    #[serde(rename = "toNode")]
+    pub to_node: String,
+    #[serde(rename = "fromSide", default, skip_serializing_if = "Option::is_none")]
+    pub from_side: Option<Side>,
+    #[serde(rename = "toSide", default, skip_serializing_if = "Option::is_none")]
+    pub to_side: Option<Side>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Fields written by newer versions of the app
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum NodeType {
+    Group,
+    Card,
+    Note,
+    Media,
+    /// A type from a newer version, kept as written
+    Other(String),
+}
+
+impl From<String> for NodeType {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "group" => NodeType::Group,
+            "card" => NodeType::Card,
+            "note" => NodeType::Note,
+            "media" => NodeType::Media,
+            _ => NodeType::Other(name),
+        }
+    }
+}
+
+impl From<NodeType> for String {
+    fn from(node_type: NodeType) -> Self {
+        match node_type {
+            NodeType::Group => "group".to_string(),
+            NodeType::Card => "card".to_string(),
+            NodeType::Note => "note".to_string(),
+            NodeType::Media => "media".to_string(),
+            NodeType::Other(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Variant {
+    Default,
+    Definition,
+    Important,
+    Formula,
+    /// A variant from a newer version, kept as written
+    Other(String),
+}
+
+impl From<String> for Variant {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "default" => Variant::Default,
+            "definition" => Variant::Definition,
+            "important" => Variant::Important,
+            "formula" => Variant::Formula,
+            _ => Variant::Other(name),
+        }
+    }
+}
+
+impl From<Variant> for String {
+    fn from(variant: Variant) -> Self {
+        match variant {
+            Variant::Default => "default".to_string(),
+            Variant::Definition => "definition".to_string(),
+            Variant::Important => "important".to_string(),
+            Variant::Formula => "formula".to_string(),
+            Variant::Other(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProblemKind {
+    MissingId,
+    DuplicateId,
+    BadGeometry,
+    DanglingEdge,
+    /// A node's parentId doesn't name the group it sits in
+    WrongParent,
+}
+
+/// Something wrong with a canvas, and what `repair` does about it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    /// The node or edge concerned
+    pub id: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(kind: ProblemKind, id: &str, message: String) -> Self {
+        Problem { kind, id: id.to_string(), message }
+    }
+}
+
+/// Parses a canvas file or canvas sent by the webview
+pub fn parse(json: &str) -> Result<Canvas, String> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid canvas: {}", e))
+}
+
+/// Builds a canvas from the nodes and edges the webview sends
+pub fn from_values(nodes: Value, edges: Value) -> Result<Canvas, String> {
+    let nodes = serde_json::from_value(nodes).map_err(|e| format!("Invalid canvas nodes: {}", e))?;
+    let edges = serde_json::from_value(edges).map_err(|e| format!("Invalid canvas edges: {}", e))?;
+    Ok(Canvas { nodes, edges })
+}
+
+impl Canvas {
+    /// Every node, groups before the nodes inside them
+    pub fn all_nodes(&self) -> Vec<&CanvasNode> {
+        fn collect<'a>(nodes: &'a [CanvasNode], out: &mut Vec<&'a CanvasNode>) {
+            for node in nodes {
+                out.push(node);
+                collect(&node.children, out);
+            }
+        }
+
+        let mut out = Vec::new();
+        collect(&self.nodes, &mut out);
+        out
+    }
+
+    /// Calls `f` on every node, groups before the nodes inside them
+    pub fn for_each_node_mut(&mut self, f: &mut dyn FnMut(&mut CanvasNode)) {
+        fn visit(nodes: &mut [CanvasNode], f: &mut dyn FnMut(&mut CanvasNode)) {
+            for node in nodes {
+                f(node);
+                visit(&mut node.children, f);
+            }
+        }
+
+        visit(&mut self.nodes, f);
+    }
+}
+
+/// Everything wrong with a canvas, in document order
+pub fn validate(canvas: &Canvas) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    check_nodes(&canvas.nodes, None, &mut HashSet::new(), &mut problems);
+
+    let node_ids: HashSet<&str> = canvas.all_nodes().iter().map(|n| n.id.as_str()).collect();
+    let mut edge_ids = HashSet::new();
+
+    for edge in &canvas.edges {
+        if edge.id.is_empty() {
+            problems.push(Problem::new(ProblemKind::MissingId, "", "An edge has no id".to_string()));
+        } else if !edge_ids.insert(edge.id.as_str()) {
+            problems.push(Problem::new(ProblemKind::DuplicateId, &edge.id, format!("Edge id {} is used twice", edge.id)));
+        }
+
+        for end in [&edge.from_node, &edge.to_node] {
+            if !node_ids.contains(end.as_str()) {
+                problems.push(Problem::new(
+                    ProblemKind::DanglingEdge,
+                    &edge.id,
+                    format!("Edge {} points at missing node {}", edge.id, end),
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+fn check_nodes<'a>(
+    nodes: &'a [CanvasNode],
+    parent: Option<&str>,
+    seen: &mut HashSet<&'a str>,
+    problems: &mut Vec<Problem>,
+) {
+    for node in nodes {
+        if node.id.is_empty() {
+            problems.push(Problem::new(ProblemKind::MissingId, "", "A node has no id".to_string()));
+        } else if !seen.insert(node.id.as_str()) {
+            problems.push(Problem::new(ProblemKind::DuplicateId, &node.id, format!("Node id {} is used twice", node.id)));
+        }
+
+        let finite = [node.x, node.y, node.width, node.height].iter().all(|v| v.is_finite());
+        if !finite || node.width < 0.0 || node.height < 0.0 {
+            problems.push(Problem::new(
+                ProblemKind::BadGeometry,
+                &node.id,
+                format!("Node {} has an invalid position or size", node.id),
+            ));
+        }
+
+        // Nesting is what places a node; a parentId that disagrees with it
+        // (including one that loops back on itself) is wrong
+        if node.parent_id.as_deref() != parent {
+            problems.push(Problem::new(
+                ProblemKind::WrongParent,
+                &node.id,
+                match parent {
+                    Some(parent) => format!("Node {} is inside {} but its parentId says otherwise", node.id, parent),
+                    None => format!("Node {} is at the top level but has a parentId", node.id),
+                },
+            ));
+        }
+
+        check_nodes(&node.children, Some(&node.id), seen, problems);
+    }
+}
+
+/// Fixes everything `validate` finds and returns what was fixed:
+/// dangling and duplicate edges are dropped, nodes with a missing or
+/// duplicate id get a new one, parentIds are set from the nesting and
+/// unusable geometry is reset to zero.
+pub fn repair(canvas: &mut Canvas) -> Vec<Problem> {
+    let problems = validate(canvas);
+    if problems.is_empty() {
+        return problems;
+    }
+
+    let mut seen = HashSet::new();
+    repair_nodes(&mut canvas.nodes, None, &mut seen);
+
+    let mut edge_ids = HashSet::new();
+    canvas.edges.retain(|edge| {
+        seen.contains(&edge.from_node) && seen.contains(&edge.to_node) && !edge.id.is_empty() && edge_ids.insert(edge.id.clone())
+    });
+
+    problems
+}
+
+fn repair_nodes(nodes: &mut [CanvasNode], parent: Option<&str>, seen: &mut HashSet<String>) {
+    for node in nodes {
+        if node.id.is_empty() || seen.contains(&node.id) {
+            node.id = Uuid::new_v4().to_string();
+        }
+        seen.insert(node.id.clone());
+
+        for value in [&mut node.x, &mut node.y] {
+            if !value.is_finite() {
+                *value = 0.0;
+            }
+        }
+        for value in [&mut node.width, &mut node.height] {
+            if !value.is_finite() || *value < 0.0 {
+                *value = 0.0;
+            }
+        }
+
+        node.parent_id = parent.map(|p| p.to_string());
+        repair_nodes(&mut node.children, Some(&node.id), seen);
+    }
+}
+
+/// One line per problem, for an error message
+pub fn describe(problems: &[Problem]) -> String {
+    problems.iter().map(|p| p.message.as_str()).collect::<Vec<_>>().join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn canvas(value: Value) -> Canvas {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn node(id: &str, parent: Option<&str>, children: Value) -> Value {
+        json!({
+            "id": id, "type": "card", "x": 0, "y": 0, "width": 100, "height": 50,
+            "label": id, "text": "", "color": "#1e1e1e",
+            "parentId": parent, "children": children
+        })
+    }
+
+    fn edge(id: &str, from: &str, to: &str) -> Value {
+        json!({ "id": id, "fromNode": from, "toNode": to, "fromSide": "right", "toSide": "left" })
+    }
+
+    fn kinds(problems: &[Problem]) -> Vec<ProblemKind> {
+        problems.iter().map(|p| p.kind).collect()
+    }
+
+    #[test]
+    fn valid_canvas_has_no_problems() {
+        let c = canvas(json!({
+            "nodes": [node("g", None, json!([node("a", Some("g"), json!([]))])), node("b", None, json!([]))],
+            "edges": [edge("e", "a", "b")]
+        }));
+        assert!(validate(&c).is_empty());
+    }
+
+    #[test]
+    fn unknown_types_and_fields_survive_a_round_trip() {
+        let mut value = node("a", None, json!([]));
+        value["type"] = json!("embed");
+        value["variant"] = json!("quote");
+        value["rotation"] = json!(15);
+        let mut e = edge("e", "a", "a");
+        e["style"] = json!("dashed");
+        let value = json!({ "nodes": [value], "edges": [e] });
+
+        let c = canvas(value.clone());
+        assert_eq!(c.nodes[0].node_type, NodeType::Other("embed".to_string()));
+        assert_eq!(c.nodes[0].variant, Some(Variant::Other("quote".to_string())));
+
+        let written = serde_json::to_value(&c).unwrap();
+        assert_eq!(written["nodes"][0]["rotation"], 15);
+        assert_eq!(written["nodes"][0]["type"], "embed");
+        assert_eq!(written["edges"][0]["style"], "dashed");
+    }
+
+    #[test]
+    fn structural_garbage_is_rejected() {
+        assert!(parse(r#"{ "nodes": [{ "id": "a", "type": "card" }] }"#).is_err());
+        assert!(parse(r#"{ "nodes": "nope" }"#).is_err());
+        assert!(parse(r#"{ "edges": [{ "id": "e", "fromNode": "a", "toNode": "b", "toSide": "up" }] }"#).is_err());
+        assert_eq!(parse("{}").unwrap(), Canvas::default());
+    }
+
+    #[test]
+    fn finds_duplicates_dangling_edges_and_wrong_parents() {
+        let mut bad = node("a", None, json!([node("b", Some("c"), json!([]))]));
+        bad["width"] = json!(-5);
+        let c = canvas(json!({
+            "nodes": [bad, node("a", Some("b"), json!([]))],
+            "edges": [edge("e", "a", "missing"), edge("e", "a", "b")]
+        }));
+
+        assert_eq!(kinds(&validate(&c)), [
+            ProblemKind::BadGeometry,
+            ProblemKind::WrongParent,
+            ProblemKind::DuplicateId,
+            ProblemKind::WrongParent,
+            ProblemKind::DanglingEdge,
+            ProblemKind::DuplicateId,
+        ]);
+    }
+
+    #[test]
+    fn repair_leaves_a_valid_canvas() {
+        let mut c = canvas(json!({
+            "nodes": [
+                node("a", Some("z"), json!([node("b", Some("b"), json!([]))])),
+                node("a", None, json!([])),
+                node("", None, json!([]))
+            ],
+            "edges": [edge("e1", "a", "b"), edge("e2", "b", "gone"), edge("e1", "b", "a")]
+        }));
+
+        let fixed = repair(&mut c);
+        assert!(!fixed.is_empty());
+        assert!(validate(&c).is_empty());
+
+        assert_eq!(c.nodes[0].parent_id, None);
+        assert_eq!(c.nodes[0].children[0].parent_id.as_deref(), Some("a"));
+        assert_ne!(c.nodes[1].id, "a");
+        assert!(!c.nodes[2].id.is_empty());
+        assert_eq!(c.edges.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), ["e1"]);
+        assert!(repair(&mut c).is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+//! ====== LINE DIFF ======
+//!
+//! Line-by-line diff between two versions of a text (note revisions),
+//! using Myers' O(ND) algorithm: the shortest edit script, so unchanged
+//! lines are never reported as removed and re-added. Texts too different
+//! for that to be cheap (over MAX_EDITS changed lines) are shown as one
+//! replaced block.
+//!
+//! =======================
+
+use serde::Serialize;
+
+/// Past this many edits a diff is reported as the old lines replaced by the
+/// new ones: the search keeps O(D²) state for D edits
+const MAX_EDITS: isize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// Diffs `old` against `new` line by line
+pub fn lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // Most edits touch a small part of a note; only diff the middle
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let mut diff: Vec<DiffLine> = Vec::with_capacity(a.len().max(b.len()));
+    let mut push = |op: DiffOp, text: &str| diff.push(DiffLine { op, text: text.to_string() });
+
+    for line in &a[..prefix] {
+        push(DiffOp::Equal, line);
+    }
+    for (op, line) in shortest_edit(&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]) {
+        push(op, line);
+    }
+    for line in &a[a.len() - suffix..] {
+        push(DiffOp::Equal, line);
+    }
+
+    diff
+}
+
+/// Myers' greedy forward search, then a walk back through the saved
+/// frontiers to recover the edit script
+fn shortest_edit<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(DiffOp, &'a str)> {
+    if a.is_empty() || b.is_empty() {
+        return replace(a, b);
+    }
+
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let offset = n + m;
+    // One spare slot either side for the diagonals next to the window
+    let index = |k: isize| (k + offset + 1) as usize;
+
+    // v[k] = furthest x reached on diagonal k; one snapshot per edit
+    // distance d, of the diagonals -d..=d it can reach and their neighbours
+    let mut v = vec![0isize; 2 * offset as usize + 3];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=offset {
+        if d > MAX_EDITS {
+            return replace(a, b);
+        }
+        trace.push(v[index(-d - 1)..=index(d + 1)].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+
+    for (d, window) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let v = |k: isize| window[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v(k - 1) < v(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            ops.push((DiffOp::Equal, a[x as usize - 1]));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push((DiffOp::Insert, b[y as usize - 1]));
+            } else {
+                ops.push((DiffOp::Delete, a[x as usize - 1]));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    ops.reverse();
+    ops
+}
+
+/// Every line of `a` deleted, then every line of `b` inserted
+fn replace<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(DiffOp, &'a str)> {
+    a.iter().map(|line| (DiffOp::Delete, *line))
+        .chain(b.iter().map(|line| (DiffOp::Insert, *line)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(diff: &[DiffLine]) -> String {
+        diff.iter()
+            .map(|l| {
+                let sign = match l.op {
+                    DiffOp::Equal => ' ',
+                    DiffOp::Insert => '+',
+                    DiffOp::Delete => '-',
+                };
+                format!("{}{}\n", sign, l.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_texts_are_all_equal() {
+        assert_eq!(render(&lines("a\nb\n", "a\nb\n")), " a\n b\n");
+    }
+
+    #[test]
+    fn large_texts_diff_without_blowing_up() {
+        let long: String = (0..5000).map(|i| format!("line {}\n", i)).collect();
+        let wiped = lines(&long, "");
+        assert_eq!(wiped.len(), 5000);
+        assert!(wiped.iter().all(|l| l.op == DiffOp::Delete));
+
+        // Too many edits for the shortest script: replaced wholesale
+        let other: String = (0..5000).map(|i| format!("other {}\n", i)).collect();
+        let rewritten = lines(&long, &other);
+        assert_eq!(rewritten.len(), 10000);
+        assert!(rewritten[..5000].iter().all(|l| l.op == DiffOp::Delete));
+
+        // A few scattered edits still get the exact script
+        let edited = long.replace("line 100\n", "").replace("line 4000\n", "line 4000!\n");
+        let ops: Vec<DiffOp> = lines(&long, &edited).into_iter().map(|l| l.op).filter(|op| *op != DiffOp::Equal).collect();
+        assert_eq!(ops, [DiffOp::Delete, DiffOp::Delete, DiffOp::Insert]);
+    }
+
+    #[test]
+    fn empty_texts() {
+        assert!(lines("", "").is_empty());
+        assert_eq!(render(&lines("", "a\nb")), "+a\n+b\n");
+        assert_eq!(render(&lines("a\nb", "")), "-a\n-b\n");
+    }
+
+    #[test]
+    fn insertion_in_the_middle() {
+        assert_eq!(render(&lines("a\nc", "a\nb\nc")), " a\n+b\n c\n");
+    }
+
+    #[test]
+    fn replaced_line() {
+        assert_eq!(render(&lines("a\nb\nc", "a\nx\nc")), " a\n-b\n+x\n c\n");
+    }
+
+    #[test]
+    fn minimal_edit_script() {
+        // The classic example from Myers' paper: 5 edits, 4 lines kept
+        let diff = lines("a\nb\nc\na\nb\nb\na", "c\nb\na\nb\na\nc");
+        let edits = diff.iter().filter(|l| l.op != DiffOp::Equal).count();
+        let kept = diff.iter().filter(|l| l.op == DiffOp::Equal).count();
+        assert_eq!((edits, kept), (5, 4));
+    }
+
+    #[test]
+    fn applying_the_diff_gives_both_sides() {
+        let old = "title\n\nfirst\nsecond\nthird\nfourth";
+        let new = "title\n\nsecond\nthird and more\nfourth\nfifth";
+        let diff = lines(old, new);
+
+        let side = |skip: DiffOp| diff.iter()
+            .filter(|l| l.op != skip)
+            .map(|l| l.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(side(DiffOp::Insert), old);
+        assert_eq!(side(DiffOp::Delete), new);
+    }
+}
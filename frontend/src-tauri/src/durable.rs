@@ -0,0 +1,148 @@
+//! ====== DURABLE WRITES ======
+//!
+//! Every file the app persists goes through `write()`:
+//!   1. the bytes land in a hidden sibling temp file (.{name}.{uuid}.tmp)
+//!   2. the temp file is fsynced
+//!   3. it is renamed over the live file (atomic on the same filesystem)
+//!   4. the parent directory is fsynced so the rename itself is durable
+//!
+//! A crash at any point leaves either the old file or the new one, never a
+//! truncated mix. The worst case is a stray temp file, which `recover()`
+//! sweeps up on the next start.
+//!
+//! ============================
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// Atomically replaces `path` with `contents`
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let dir = parent_dir(path);
+    let temp = temp_path(path)?;
+
+    let result = write_and_sync(&temp, contents.as_ref())
+        .and_then(|_| fs::rename(&temp, path));
+
+    if let Err(e) = result {
+        // Best effort: don't leave the half-written temp file behind
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+
+    sync_dir(dir)
+}
+
+/// Removes leftover temp files from an interrupted `write()` in `dir`.
+/// The live files are untouched; returns the paths that were cleaned up.
+pub fn recover(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+
+    if !dir.exists() {
+        return Ok(removed);
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_temp_file(&path) {
+            fs::remove_file(&path)?;
+            removed.push(path);
+        }
+    }
+
+    if !removed.is_empty() {
+        sync_dir(dir)?;
+    }
+
+    Ok(removed)
+}
+
+/// True for files created by `write()` that never got renamed into place:
+/// `.{name}.{uuid}.tmp`, not any hidden file that happens to end in .tmp
+pub fn is_temp_file(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    let Some(inner) = name.strip_prefix('.').and_then(|n| n.strip_suffix(TEMP_SUFFIX)) else {
+        return false;
+    };
+
+    inner.rsplit_once('.').is_some_and(|(name, uuid)| {
+        !name.is_empty() && uuid.len() == 32 && uuid.bytes().all(|b| b.is_ascii_hexdigit())
+    })
+}
+
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let name = path.file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+
+    Ok(parent_dir(path).join(format!(".{}.{}{}", name, Uuid::new_v4().simple(), TEMP_SUFFIX)))
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    }
+}
+
+fn write_and_sync(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+/// Flushes directory entries (the rename) to disk
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+/// Windows has no way to fsync a directory handle; NTFS journals the rename
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn write_replaces_the_file_and_leaves_no_temp_file() {
+        let dir = TempDir::new("durable");
+        let file = dir.0.join("note.md");
+
+        write(&file, "first").unwrap();
+        write(&file, "second").unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "second");
+        assert_eq!(names(&dir.0), ["note.md"]);
+    }
+
+    #[test]
+    fn recover_removes_only_leftover_temp_files() {
+        let dir = TempDir::new("durable");
+        let stray = format!(".note.md.{}.tmp", Uuid::new_v4().simple());
+        for name in [stray.as_str(), "note.md", ".scratch.tmp", "notes.tmp", ".note.md.draft.tmp"] {
+            dir.write(name, b"x");
+        }
+
+        assert_eq!(recover(&dir.0).unwrap(), [dir.0.join(&stray)]);
+        assert_eq!(names(&dir.0), [".note.md.draft.tmp", ".scratch.tmp", "note.md", "notes.tmp"]);
+        assert!(recover(&dir.0.join("missing")).unwrap().is_empty());
+    }
+}
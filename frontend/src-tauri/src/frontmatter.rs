@@ -0,0 +1,241 @@
+//! ====== NOTE FRONTMATTER ======
+//!
+//! Notes are Markdown files with YAML frontmatter between `---` fences,
+//! the same layout Obsidian, Hugo and pandoc read:
+//!
+//!   ---
+//!   title: My Note
+//!   sticky: false
+//!   ---
+//!
+//!   Body text...
+//!
+//! Older versions wrote pretty-printed JSON between the fences. That is
+//! still accepted on read (`is_legacy()` tells them apart) so existing
+//! vaults keep loading until the startup migration rewrites them.
+//!
+//! ==============================
+
+use serde::Serialize;
+use serde_json::Value;
+
+const FENCE: &str = "---";
+
+/// Splits a note file into its frontmatter metadata and body.
+///
+/// Fences are only recognized as a line that is exactly `---`: the opening
+/// one must be the first line of the file, the closing one is the next such
+/// line. A `---` anywhere else (horizontal rules, table separators, text)
+/// is body content. Malformed frontmatter is an error, never `{}`.
+pub fn parse(content: &str) -> Result<(Value, String), String> {
+    match split(content)? {
+        Some((raw, body)) => Ok((parse_metadata(raw)?, body.to_string())),
+        None => Ok((serde_json::json!({}), content.to_string())),
+    }
+}
+
+/// Renders metadata as YAML frontmatter followed by the body
+pub fn render<T: Serialize>(metadata: &T, body: &str) -> Result<String, String> {
+    let yaml = serde_yaml::to_string(metadata)
+        .map_err(|e| format!("Failed to serialize frontmatter: {}", e))?;
+
+    Ok(format!("{}\n{}{}\n\n{}", FENCE, yaml, FENCE, body))
+}
+
+/// True if the file still uses the old JSON-between-fences frontmatter
+pub fn is_legacy(content: &str) -> bool {
+    matches!(split(content), Ok(Some((raw, _))) if raw.trim_start().starts_with('{'))
+}
+
+/// Returns the raw frontmatter and the body, or None if the file has no
+/// frontmatter at all
+fn split(content: &str) -> Result<Option<(&str, &str)>, String> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+
+    let (first, rest) = next_line(content);
+    if !is_fence(first) {
+        return Ok(None);
+    }
+
+    let mut offset = 0;
+    while offset < rest.len() {
+        let (line, remainder) = next_line(&rest[offset..]);
+        if is_fence(line) {
+            let raw = &rest[..offset];
+            // The writer puts one blank line between the fence and the body
+            let body = remainder.strip_prefix("\r\n")
+                .or_else(|| remainder.strip_prefix('\n'))
+                .unwrap_or(remainder);
+            return Ok(Some((raw, body)));
+        }
+        offset = rest.len() - remainder.len();
+    }
+
+    Err("Unterminated frontmatter: no closing '---' line".to_string())
+}
+
+/// Splits off the first line (without its line ending)
+fn next_line(s: &str) -> (&str, &str) {
+    match s.find('\n') {
+        Some(i) => (s[..i].strip_suffix('\r').unwrap_or(&s[..i]), &s[i + 1..]),
+        None => (s, ""),
+    }
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_end() == FENCE
+}
+
+fn parse_metadata(raw: &str) -> Result<Value, String> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+
+    let metadata: Value = if trimmed.starts_with('{') {
+        // Legacy JSON frontmatter
+        serde_json::from_str(trimmed)
+            .map_err(|e| format!("Invalid JSON frontmatter: {}", e))?
+    } else {
+        serde_yaml::from_str(raw)
+            .map_err(|e| format!("Invalid YAML frontmatter: {}", e))?
+    };
+
+    if !metadata.is_object() {
+        return Err("Frontmatter must be a key/value mapping".to_string());
+    }
+
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn note(yaml: &str, body: &str) -> String {
+        format!("---\n{}---\n\n{}", yaml, body)
+    }
+
+    #[test]
+    fn parses_yaml_frontmatter_and_body() {
+        let (meta, body) = parse(&note("title: Hello\nsticky: true\n", "Body text")).unwrap();
+        assert_eq!(meta["title"], "Hello");
+        assert_eq!(meta["sticky"], true);
+        assert_eq!(body, "Body text");
+    }
+
+    #[test]
+    fn file_without_frontmatter_is_all_body() {
+        let (meta, body) = parse("Just some text\n---\nmore").unwrap();
+        assert_eq!(meta, json!({}));
+        assert_eq!(body, "Just some text\n---\nmore");
+    }
+
+    #[test]
+    fn horizontal_rule_in_body_is_kept() {
+        let body = "Intro\n\n---\n\nAfter the rule\n\n---\n";
+        let (meta, parsed) = parse(&note("title: Rules\n", body)).unwrap();
+        assert_eq!(meta["title"], "Rules");
+        assert_eq!(parsed, body);
+    }
+
+    #[test]
+    fn body_starting_with_a_rule_is_not_a_fence() {
+        let body = "---\nstill body";
+        let (meta, parsed) = parse(&format!("---\ntitle: T\n---\n{}", body)).unwrap();
+        assert_eq!(meta["title"], "T");
+        assert_eq!(parsed, body);
+    }
+
+    #[test]
+    fn dashes_inside_lines_are_not_fences() {
+        let body = "em---dash typing and a --- b\n  ---\n--- trailing text";
+        let (meta, parsed) = parse(&note("title: Dashes\n", body)).unwrap();
+        assert_eq!(meta["title"], "Dashes");
+        assert_eq!(parsed, body);
+    }
+
+    #[test]
+    fn markdown_table_separators_are_body() {
+        let body = "| a | b |\n|---|---|\n| 1 | 2 |\n";
+        let (_, parsed) = parse(&note("title: Table\n", body)).unwrap();
+        assert_eq!(parsed, body);
+    }
+
+    #[test]
+    fn dashes_inside_metadata_values() {
+        let (meta, _) = parse(&note("title: a --- b\n", "")).unwrap();
+        assert_eq!(meta["title"], "a --- b");
+    }
+
+    #[test]
+    fn legacy_json_with_dashes_in_content() {
+        let legacy = "---\n{\n  \"title\": \"Old --- note\",\n  \"content\": {\"text\": \"---\"}\n}\n---\n\nbody --- text\n---\n";
+        assert!(is_legacy(legacy));
+        let (meta, body) = parse(legacy).unwrap();
+        assert_eq!(meta["title"], "Old --- note");
+        assert_eq!(meta["content"]["text"], "---");
+        assert_eq!(body, "body --- text\n---\n");
+    }
+
+    #[test]
+    fn yaml_is_not_legacy() {
+        assert!(!is_legacy(&note("title: New\n", "")));
+        assert!(!is_legacy("no frontmatter"));
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let (meta, body) = parse("---\r\ntitle: Windows\r\n---\r\n\r\nline one\r\n").unwrap();
+        assert_eq!(meta["title"], "Windows");
+        assert_eq!(body, "line one\r\n");
+    }
+
+    #[test]
+    fn leading_byte_order_mark() {
+        let (meta, _) = parse("\u{feff}---\ntitle: BOM\n---\n").unwrap();
+        assert_eq!(meta["title"], "BOM");
+    }
+
+    #[test]
+    fn empty_frontmatter_is_empty_mapping() {
+        let (meta, body) = parse("---\n---\n\nbody").unwrap();
+        assert_eq!(meta, json!({}));
+        assert_eq!(body, "body");
+    }
+
+    #[test]
+    fn unterminated_frontmatter_is_an_error() {
+        assert!(parse("---\ntitle: Oops\nbody without closing fence").is_err());
+    }
+
+    #[test]
+    fn invalid_yaml_is_an_error() {
+        assert!(parse(&note("title: [unclosed\n", "")).is_err());
+    }
+
+    #[test]
+    fn invalid_legacy_json_is_an_error() {
+        assert!(parse(&note("{\"title\": \n", "")).is_err());
+    }
+
+    #[test]
+    fn non_mapping_frontmatter_is_an_error() {
+        assert!(parse(&note("- just\n- a list\n", "")).is_err());
+    }
+
+    #[test]
+    fn render_round_trips() {
+        let meta = json!({
+            "title": "Round --- trip",
+            "color": "#ffffff",
+            "position": 3,
+            "sticky": false,
+        });
+        let body = "# Heading\n\n---\n\n| x |\n|---|\n";
+        let (parsed, parsed_body) = parse(&render(&meta, body).unwrap()).unwrap();
+        assert_eq!(parsed, meta);
+        assert_eq!(parsed_body, body);
+    }
+}
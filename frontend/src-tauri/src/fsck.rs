@@ -0,0 +1,607 @@
+//! ====== VAULT CHECK ======
+//!
+//! The vault keeps related data in separate stores (notes/, folders.json,
+//! canvas/, graph.json) and nothing else keeps them in step. `check` reads
+//! all of them and reports where they disagree:
+//!
+//!   orphanedCanvas     canvas/{id}.json with no note
+//!   danglingFolder     a note's folderId (or a folder's parentId) naming
+//!                      a folder that isn't in folders.json
+//!   folderCycle        folders that are each other's parents
+//!   duplicatePosition  two notes at the same position in one folder
+//!   unparsable         a note, canvas or settings file that can't be read
+//!   brokenEdge         a graph or canvas edge to something that is gone
+//!
+//! `repair` fixes the kinds it is asked to. It never deletes anything:
+//! unreadable files and orphaned canvases are moved to .lost+found/ (an
+//! orphan goes back into its note's trash entry if it has one).
+//!
+//! While folders.json can't be read, notes and folders aren't checked
+//! against it: every folder would look missing. An unreadable settings
+//! file (folders.json, graph.json, vault.json) is only set aside by a
+//! repair that fixes nothing else, so no fix in the same pass takes its
+//! absence for an empty folder tree or graph.
+//!
+//! Both work on the files themselves, so only on a vault kept in a folder
+//! (see store.rs).
+//!
+//! ===================
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::canvas::{self, ProblemKind};
+use crate::graph::{self, GraphMetadata};
+use crate::note_id::NoteId;
+use crate::store::Item;
+use crate::{trash, AppState, Folder, Note, VaultConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    OrphanedCanvas,
+    DanglingFolder,
+    FolderCycle,
+    DuplicatePosition,
+    Unparsable,
+    BrokenEdge,
+}
+
+impl IssueKind {
+    pub const ALL: [IssueKind; 6] = [
+        IssueKind::OrphanedCanvas,
+        IssueKind::DanglingFolder,
+        IssueKind::FolderCycle,
+        IssueKind::DuplicatePosition,
+        IssueKind::Unparsable,
+        IssueKind::BrokenEdge,
+    ];
+}
+
+/// Something wrong in the vault
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Issue {
+    pub kind: IssueKind,
+    /// The file concerned, relative to the vault folder
+    pub path: String,
+    /// The note, folder or edge concerned, if there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub message: String,
+}
+
+impl Issue {
+    fn new(kind: IssueKind, path: String, id: Option<&str>, message: String) -> Self {
+        Issue { kind, path, id: id.map(str::to_string), message }
+    }
+
+    /// Same problem, however it is worded
+    fn same(&self, other: &Issue) -> bool {
+        self.kind == other.kind && self.path == other.path && self.id == other.id
+    }
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// What `repair` did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Repair {
+    pub fixed: Vec<Issue>,
+    /// Issues still there afterwards, including kinds not asked for
+    pub remaining: Vec<Issue>,
+}
+
+/// Folders whose parentId names no folder
+pub fn orphaned_folders(folders: &[Folder]) -> Vec<&Folder> {
+    let ids: HashSet<&str> = folders.iter().map(|f| f.id.as_str()).collect();
+    folders.iter()
+        .filter(|f| f.parent_id.as_deref().is_some_and(|p| !ids.contains(p)))
+        .collect()
+}
+
+/// Notes whose folderId names no folder
+pub fn homeless_notes<'a>(notes: &'a [Note], folders: &[Folder]) -> Vec<&'a Note> {
+    let ids: HashSet<&str> = folders.iter().map(|f| f.id.as_str()).collect();
+    notes.iter()
+        .filter(|n| n.folder_id.as_deref().is_some_and(|f| !ids.contains(f)))
+        .collect()
+}
+
+/// Each loop in the folder tree, as the ids on it starting from the
+/// smallest
+pub fn folder_cycles(folders: &[Folder]) -> Vec<Vec<String>> {
+    let parents: HashMap<&str, &str> = folders.iter()
+        .filter_map(|f| Some((f.id.as_str(), f.parent_id.as_deref()?)))
+        .collect();
+    let mut cycles: Vec<Vec<String>> = Vec::new();
+    let mut done: HashSet<&str> = HashSet::new();
+
+    for folder in folders {
+        let mut path: Vec<&str> = Vec::new();
+        let mut current = Some(folder.id.as_str());
+
+        while let Some(id) = current {
+            if done.contains(id) {
+                break;
+            }
+            if let Some(start) = path.iter().position(|p| *p == id) {
+                let mut cycle: Vec<String> = path[start..].iter().map(|s| s.to_string()).collect();
+                let smallest = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap_or(0);
+                cycle.rotate_left(smallest);
+                cycles.push(cycle);
+                break;
+            }
+            path.push(id);
+            current = parents.get(id).copied();
+        }
+        done.extend(path);
+    }
+
+    cycles
+}
+
+/// Pairs of (note, the note whose position it repeats), per folder
+pub fn duplicate_positions(notes: &[Note]) -> Vec<(&Note, &Note)> {
+    let mut seen: HashMap<(Option<&str>, i32), &Note> = HashMap::new();
+    let mut sorted: Vec<&Note> = notes.iter().collect();
+    sorted.sort_by(|a, b| crate::compare_notes(a, b).then_with(|| a.id.cmp(&b.id)));
+
+    sorted.into_iter()
+        .filter_map(|note| {
+            let first = *seen.entry((note.folder_id.as_deref(), note.position)).or_insert(note);
+            (first.id != note.id).then_some((note, first))
+        })
+        .collect()
+}
+
+/// Reads the whole vault and reports what is wrong with it
+pub fn check(state: &AppState) -> Result<Vec<Issue>, String> {
+    let mut issues = Vec::new();
+
+    let notes = read_notes(state, &mut issues)?;
+    let folders: Option<Vec<Folder>> = read_json(state, &state.folders_file(), &mut issues).ok().map(Option::unwrap_or_default);
+    let graph: GraphMetadata = read_json(state, &state.graph_file(), &mut issues).ok().flatten().unwrap_or_default();
+    let _ = read_json::<VaultConfig>(state, &state.config_file(), &mut issues);
+
+    if let Some(folders) = &folders {
+        check_folders(state, &notes, folders, &mut issues);
+    }
+
+    for (note, first) in duplicate_positions(&notes) {
+        issues.push(Issue::new(
+            IssueKind::DuplicatePosition,
+            state.relative_path(&state.note_file(&note.id)),
+            Some(note.id.as_str()),
+            format!("Note \"{}\" has the same position ({}) as \"{}\"", note.title, note.position, first.title),
+        ));
+    }
+
+    let note_ids: HashSet<&str> = notes.iter().map(|n| n.id.as_str()).collect();
+    check_canvases(state, &note_ids, &mut issues)?;
+
+    // Unreadable notes are still notes; their edges aren't broken
+    let mut probe = graph.clone();
+    let pruned = graph::prune(&mut probe, |id| crate::note_exists(id, state));
+    for edge in graph.edges.iter().filter(|e| pruned.edges.contains(&e.id)) {
+        issues.push(Issue::new(
+            IssueKind::BrokenEdge,
+            state.relative_path(&state.graph_file()),
+            Some(&edge.id),
+            format!("Graph edge {} → {} points at a note that doesn't exist", edge.source, edge.target),
+        ));
+    }
+
+    Ok(issues)
+}
+
+/// Notes and folders in folders that don't exist, and folder loops
+fn check_folders(state: &AppState, notes: &[Note], folders: &[Folder], issues: &mut Vec<Issue>) {
+    for folder in orphaned_folders(folders) {
+        issues.push(Issue::new(
+            IssueKind::DanglingFolder,
+            state.relative_path(&state.folders_file()),
+            Some(&folder.id),
+            format!("Folder \"{}\" is inside folder {}, which doesn't exist", folder.name, folder.parent_id.as_deref().unwrap_or_default()),
+        ));
+    }
+    for note in homeless_notes(notes, folders) {
+        issues.push(Issue::new(
+            IssueKind::DanglingFolder,
+            state.relative_path(&state.note_file(&note.id)),
+            Some(note.id.as_str()),
+            format!("Note \"{}\" is in folder {}, which doesn't exist", note.title, note.folder_id.as_deref().unwrap_or_default()),
+        ));
+    }
+
+    for cycle in folder_cycles(folders) {
+        issues.push(Issue::new(
+            IssueKind::FolderCycle,
+            state.relative_path(&state.folders_file()),
+            cycle.first().map(String::as_str),
+            format!("Folders are inside each other: {}", cycle.join(" → ")),
+        ));
+    }
+}
+
+/// Fixes the issues of the given kinds (all of them if None), then checks
+/// again
+pub fn repair(kinds: Option<&[IssueKind]>, state: &AppState) -> Result<Repair, String> {
+    let kinds = kinds.unwrap_or(&IssueKind::ALL);
+    let store = state.store();
+    let lost_found_dir = state.lost_found_dir()?;
+    let issues = check(state)?;
+    let found: Vec<Issue> = issues.iter().filter(|i| kinds.contains(&i.kind)).cloned().collect();
+    let wants = |kind| found.iter().any(|i| i.kind == kind);
+
+    let settings = [state.folders_file(), state.graph_file(), state.config_file()].map(|file| state.relative_path(&file));
+    let is_settings = |issue: &Issue| settings.contains(&issue.path);
+    let graph_unreadable = issues.iter().any(|i| i.kind == IssueKind::Unparsable && i.path == settings[1]);
+
+    // Readable files first: the other fixes need to see the whole vault
+    if wants(IssueKind::Unparsable) {
+        for issue in found.iter().filter(|i| i.kind == IssueKind::Unparsable && !is_settings(i)) {
+            let path = state.data_dir().join(&issue.path);
+            set_aside(&path, &lost_found_dir.join(&issue.path), state)?;
+        }
+        state.invalidate_notes();
+    }
+
+    // Without a settings file the other fixes would see an empty folder
+    // tree or graph, so setting one aside waits for a pass with nothing
+    // else to fix
+    if found.iter().all(|i| i.kind == IssueKind::Unparsable && is_settings(i)) {
+        for issue in &found {
+            let path = state.data_dir().join(&issue.path);
+            set_aside(&path, &lost_found_dir.join(&issue.path), state)?;
+        }
+    }
+
+    if wants(IssueKind::FolderCycle) || wants(IssueKind::DanglingFolder) {
+        repair_folders(wants(IssueKind::FolderCycle), wants(IssueKind::DanglingFolder), state)?;
+    }
+    if wants(IssueKind::DanglingFolder) || wants(IssueKind::DuplicatePosition) {
+        repair_notes(wants(IssueKind::DanglingFolder), wants(IssueKind::DuplicatePosition), state)?;
+    }
+
+    if wants(IssueKind::OrphanedCanvas) {
+        for issue in found.iter().filter(|i| i.kind == IssueKind::OrphanedCanvas) {
+            let trashed = issue.id.as_deref()
+                .and_then(|id| NoteId::parse(id).ok())
+                .filter(|id| trash::contains(&*store, id) && !store.exists(&trash::canvas_item(id)));
+            match trashed {
+                Some(id) => store.rename(&Item::Canvas(id.clone()), &trash::canvas_item(&id))?,
+                None => {
+                    let path = state.data_dir().join(&issue.path);
+                    set_aside(&path, &lost_found_dir.join(&issue.path), state)?
+                }
+            }
+        }
+    }
+
+    if wants(IssueKind::BrokenEdge) {
+        let canvases: HashSet<&str> = found.iter()
+            .filter(|i| i.kind == IssueKind::BrokenEdge && i.path != state.relative_path(&state.graph_file()))
+            .map(|i| i.path.as_str())
+            .collect();
+        for path in canvases {
+            let Some(id) = Path::new(path).file_stem().and_then(|s| s.to_str()).and_then(|s| NoteId::parse(s).ok()) else {
+                continue;
+            };
+            if let Some(mut canvas) = state.read_canvas(&id) {
+                canvas::repair(&mut canvas);
+                crate::write_canvas(&id, &canvas, state, false)?;
+            }
+        }
+        if !graph_unreadable {
+            crate::prune_graph(state)?;
+        }
+    }
+
+    let remaining = check(state)?;
+    let fixed = found.into_iter().filter(|i| !remaining.iter().any(|r| r.same(i))).collect();
+
+    Ok(Repair { fixed, remaining })
+}
+
+/// Every note file, reporting the ones that can't be read. Files whose
+/// name isn't a note id are left alone, as the app never reads them.
+fn read_notes(state: &AppState, issues: &mut Vec<Issue>) -> Result<Vec<Note>, String> {
+    let mut notes = Vec::new();
+
+    for (id, path) in vault_files(&state.notes_dir(), "md")? {
+        match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|content| crate::parse_note(id.clone(), &content)) {
+            Ok(note) => notes.push(note),
+            Err(e) => issues.push(Issue::new(IssueKind::Unparsable, state.relative_path(&path), Some(id.as_str()), e)),
+        }
+    }
+
+    Ok(notes)
+}
+
+fn check_canvases(state: &AppState, note_ids: &HashSet<&str>, issues: &mut Vec<Issue>) -> Result<(), String> {
+    for (id, path) in vault_files(&state.canvas_dir(), "json")? {
+        let relative = state.relative_path(&path);
+
+        // A note that exists but can't be read still owns its canvas
+        if !note_ids.contains(id.as_str()) && !state.note_file(&id).exists() {
+            issues.push(Issue::new(IssueKind::OrphanedCanvas, relative, Some(id.as_str()), format!("Canvas for note {}, which doesn't exist", id)));
+            continue;
+        }
+
+        let canvas = match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|content| canvas::parse(&content)) {
+            Ok(canvas) => canvas,
+            Err(e) => {
+                issues.push(Issue::new(IssueKind::Unparsable, relative, Some(id.as_str()), e));
+                continue;
+            }
+        };
+        for problem in canvas::validate(&canvas).into_iter().filter(|p| p.kind == ProblemKind::DanglingEdge) {
+            issues.push(Issue::new(IssueKind::BrokenEdge, relative.clone(), Some(&problem.id), problem.message));
+        }
+    }
+
+    Ok(())
+}
+
+/// Files in `dir` named {note id}.{extension}, in name order
+fn vault_files(dir: &Path, extension: &str) -> Result<BTreeMap<NoteId, PathBuf>, String> {
+    let mut files = BTreeMap::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+
+    for entry in fs::read_dir(dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.extension().and_then(|s| s.to_str()) != Some(extension) {
+            continue;
+        }
+        if let Some(id) = path.file_stem().and_then(|s| s.to_str()).and_then(|s| NoteId::parse(s).ok()) {
+            files.insert(id, path);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Reads a JSON settings file, reporting it if it can't be read. A missing
+/// file is fine.
+fn read_json<T: serde::de::DeserializeOwned>(state: &AppState, path: &Path, issues: &mut Vec<Issue>) -> Result<Option<T>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string())) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) => {
+            issues.push(Issue::new(IssueKind::Unparsable, state.relative_path(path), None, e.clone()));
+            Err(e)
+        }
+    }
+}
+
+/// Breaks folder cycles and lifts folders out of missing parents
+fn repair_folders(cycles: bool, dangling: bool, state: &AppState) -> Result<(), String> {
+    let mut issues = Vec::new();
+    let Ok(Some(mut folders)) = read_json::<Vec<Folder>>(state, &state.folders_file(), &mut issues) else {
+        return Ok(());
+    };
+    let mut root: HashSet<String> = HashSet::new();
+
+    if cycles {
+        root.extend(folder_cycles(&folders).into_iter().filter_map(|cycle| cycle.into_iter().next()));
+    }
+    if dangling {
+        root.extend(orphaned_folders(&folders).into_iter().map(|f| f.id.clone()));
+    }
+    if root.is_empty() {
+        return Ok(());
+    }
+
+    for folder in folders.iter_mut().filter(|f| root.contains(&f.id)) {
+        folder.parent_id = None;
+    }
+    crate::save_folders(&folders, state)
+}
+
+/// Moves notes out of missing folders to the root, and renumbers folders
+/// where notes share a position (keeping the sidebar order). Like other
+/// automatic changes, this keeps the notes' updatedAt.
+fn repair_notes(dangling: bool, duplicates: bool, state: &AppState) -> Result<(), String> {
+    let mut issues = Vec::new();
+    let folders: Option<Vec<Folder>> = read_json(state, &state.folders_file(), &mut issues).ok().map(Option::unwrap_or_default);
+    let mut notes: Vec<Note> = state.with_notes(|index| index.values().cloned().collect())?;
+    let mut changed: HashSet<NoteId> = HashSet::new();
+
+    // Notes moved to the root go after the ones already there. Without a
+    // readable folders.json no folder is known to be missing.
+    if let Some(folders) = folders.as_deref().filter(|_| dangling) {
+        let homeless: HashSet<NoteId> = homeless_notes(&notes, folders).into_iter().map(|n| n.id.clone()).collect();
+        let first = notes.iter()
+            .filter(|n| n.folder_id.is_none())
+            .map(|n| n.position)
+            .max()
+            .unwrap_or(-1) + 1;
+        for (note, position) in notes.iter_mut().filter(|n| homeless.contains(&n.id)).zip(first..) {
+            note.folder_id = None;
+            note.position = position;
+            changed.insert(note.id.clone());
+        }
+    }
+
+    if duplicates {
+        let crowded: HashSet<Option<String>> = duplicate_positions(&notes).into_iter()
+            .map(|(note, _)| note.folder_id.clone())
+            .collect();
+        notes.sort_by(|a, b| crate::compare_notes(a, b).then_with(|| a.id.cmp(&b.id)));
+
+        let mut next: HashMap<Option<String>, i32> = HashMap::new();
+        for note in notes.iter_mut().filter(|n| crowded.contains(&n.folder_id)) {
+            let position = next.entry(note.folder_id.clone()).or_insert(0);
+            if note.position != *position {
+                note.position = *position;
+                changed.insert(note.id.clone());
+            }
+            *position += 1;
+        }
+    }
+
+    for note in notes.iter().filter(|n| changed.contains(&n.id)) {
+        crate::write_note(note, state, false)?;
+    }
+    Ok(())
+}
+
+/// Moves a file somewhere under .lost+found/, next to anything already
+/// set aside under the same name
+fn set_aside(from: &Path, to: &Path, state: &AppState) -> Result<(), String> {
+    if !from.exists() {
+        return Ok(());
+    }
+    if let Some(dir) = to.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    let mut to = to.to_path_buf();
+    let mut n = 1;
+    while to.exists() {
+        n += 1;
+        let name = from.file_name().and_then(|s| s.to_str()).unwrap_or("file");
+        to.set_file_name(format!("{}.{}", name, n));
+    }
+    state.move_file(from, &to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn note(id: &str, frontmatter: &str) -> Note {
+        let content = format!("---\ntitle: {}\n{}---\n\nbody\n", id, frontmatter);
+        crate::parse_note(NoteId::parse(id).unwrap(), &content).unwrap()
+    }
+
+    fn folder(id: &str, parent: Option<&str>) -> Folder {
+        Folder {
+            id: id.to_string(),
+            name: id.to_string(),
+            parent_id: parent.map(str::to_string),
+            created_at: String::new(),
+            updated_at: String::new(),
+            expanded: false,
+        }
+    }
+
+    fn kinds(issues: &[Issue]) -> Vec<IssueKind> {
+        let mut kinds: Vec<IssueKind> = issues.iter().map(|i| i.kind).collect();
+        kinds.sort_by_key(|k| IssueKind::ALL.iter().position(|a| a == k));
+        kinds
+    }
+
+    #[test]
+    fn finds_missing_folders_and_cycles() {
+        let folders = [folder("a", None), folder("b", Some("c")), folder("c", Some("d")), folder("d", Some("b")), folder("e", Some("gone"))];
+        let notes = [note("n1", "folderId: a\n"), note("n2", "folderId: nowhere\n")];
+
+        assert_eq!(orphaned_folders(&folders).iter().map(|f| f.id.as_str()).collect::<Vec<_>>(), ["e"]);
+        assert_eq!(homeless_notes(&notes, &folders).iter().map(|n| n.id.as_str()).collect::<Vec<_>>(), ["n2"]);
+
+        assert_eq!(folder_cycles(&folders), [["b", "c", "d"]]);
+        assert!(folder_cycles(&[folder("a", Some("a"))]).len() == 1);
+        assert!(folder_cycles(&folders[..1]).is_empty());
+    }
+
+    #[test]
+    fn finds_notes_sharing_a_position_in_the_same_folder() {
+        let notes = [
+            note("n1", "position: 0\n"),
+            note("n2", "position: 0\n"),
+            note("n3", "position: 0\nfolderId: a\n"),
+            note("n4", "position: 1\n"),
+        ];
+
+        let duplicates: Vec<(&str, &str)> = duplicate_positions(&notes).into_iter()
+            .map(|(n, first)| (n.id.as_str(), first.id.as_str()))
+            .collect();
+        assert_eq!(duplicates.len(), 1);
+        assert!(duplicates[0] == ("n2", "n1") || duplicates[0] == ("n1", "n2"));
+    }
+
+    #[test]
+    fn repairs_a_messy_vault() {
+        let dir = TempDir::new("fsck");
+        let state = AppState::new(dir.0.clone());
+        state.ensure_dirs().unwrap();
+
+        let write = |path: PathBuf, content: &str| fs::write(path, content).unwrap();
+        write(state.note_file(&NoteId::parse("n1").unwrap()), "---\ntitle: One\nposition: 0\nfolderId: gone\n---\n\nbody\n");
+        write(state.note_file(&NoteId::parse("n2").unwrap()), "---\ntitle: Two\nposition: 0\n---\n\nbody\n");
+        write(state.note_file(&NoteId::parse("n3").unwrap()), "---\ntitle: Three\nposition: 0\n---\n\nbody\n");
+        write(state.note_file(&NoteId::parse("broken").unwrap()), "---\ntitle: [unclosed\n---\n");
+        write(state.folders_file(), r#"[{"id":"a","name":"A","parentId":"b","createdAt":"","updatedAt":""},{"id":"b","name":"B","parentId":"a","createdAt":"","updatedAt":""}]"#);
+        write(state.canvas_file(&NoteId::parse("n2").unwrap()), r#"{"nodes":[{"id":"x","type":"text","x":0,"y":0,"width":10,"height":10}],"edges":[{"id":"e","fromNode":"x","toNode":"y"}]}"#);
+        write(state.canvas_file(&NoteId::parse("deleted").unwrap()), r#"{"nodes":[],"edges":[]}"#);
+        write(state.graph_file(), r#"{"nodes":{},"edges":[{"id":"g","source":"n1","target":"deleted"}]}"#);
+
+        let issues = check(&state).unwrap();
+        assert_eq!(kinds(&issues), [
+            IssueKind::OrphanedCanvas,
+            IssueKind::DanglingFolder,
+            IssueKind::FolderCycle,
+            IssueKind::DuplicatePosition,
+            IssueKind::Unparsable,
+            IssueKind::BrokenEdge,
+            IssueKind::BrokenEdge,
+        ]);
+
+        let repair = repair(None, &state).unwrap();
+        assert_eq!(repair.fixed.len(), issues.len());
+        assert!(repair.remaining.is_empty(), "{:?}", repair.remaining);
+        assert!(dir.0.join(".lost+found/notes/broken.md").exists());
+        assert!(dir.0.join(".lost+found/canvas/deleted.json").exists());
+        assert!(check(&state).unwrap().is_empty());
+    }
+
+    #[test]
+    fn an_unreadable_folders_file_keeps_notes_in_their_folders() {
+        let dir = TempDir::new("fsck");
+        let state = AppState::new(dir.0.clone());
+        state.ensure_dirs().unwrap();
+        let one = NoteId::parse("n1").unwrap();
+        fs::write(state.note_file(&one), "---\ntitle: One\nposition: 0\nfolderId: a\n---\n\nbody\n").unwrap();
+        fs::write(state.note_file(&NoteId::parse("n2").unwrap()), "---\ntitle: Two\nposition: 0\nfolderId: a\n---\n\nbody\n").unwrap();
+        fs::write(state.folders_file(), "[{\"id\": \"a\",").unwrap();
+
+        assert_eq!(kinds(&check(&state).unwrap()), [IssueKind::DuplicatePosition, IssueKind::Unparsable]);
+
+        // Positions are fixed, but folders.json stays while that happens
+        let first = repair(None, &state).unwrap();
+        assert_eq!(kinds(&first.fixed), [IssueKind::DuplicatePosition]);
+        assert_eq!(kinds(&first.remaining), [IssueKind::Unparsable]);
+        assert!(state.folders_file().exists());
+        assert_eq!(crate::get_note(one, &state).unwrap().folder_id.as_deref(), Some("a"));
+
+        let second = repair(None, &state).unwrap();
+        assert_eq!(kinds(&second.fixed), [IssueKind::Unparsable]);
+        assert!(dir.0.join(".lost+found/folders.json").exists());
+    }
+
+    #[test]
+    fn repairs_only_the_kinds_asked_for() {
+        let dir = TempDir::new("fsck");
+        let state = AppState::new(dir.0.clone());
+        state.ensure_dirs().unwrap();
+        fs::write(state.canvas_file(&NoteId::parse("deleted").unwrap()), "{}").unwrap();
+        fs::write(state.graph_file(), r#"{"edges":[{"id":"g","source":"a","target":"b"}]}"#).unwrap();
+
+        let repair = repair(Some(&[IssueKind::BrokenEdge]), &state).unwrap();
+        assert_eq!(kinds(&repair.fixed), [IssueKind::BrokenEdge]);
+        assert_eq!(kinds(&repair.remaining), [IssueKind::OrphanedCanvas]);
+    }
+}
@@ -0,0 +1,219 @@
+//! ====== GRAPH ======
+//!
+//! graph.json holds the graph view of the whole vault:
+//!
+//!   nodes: per note id, where the note sits and how it is moving
+//!          ({x, y, vx, vy, radius, lastVisited})
+//!   edges: connections between notes, drawn by hand or derived from
+//!          [[links]]
+//!
+//! What the webview sends is checked before it is saved: every node must
+//! be keyed by a note id and hold finite numbers. Entries and edges for
+//! notes that are no longer on disk are pruned rather than rejected, since
+//! the graph view can briefly lag behind a delete.
+//!
+//! ===================
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use crate::note_id::NoteId;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphMetadata {
+    /// Unreadable entries in graph.json are skipped; the graph view places
+    /// those notes afresh
+    #[serde(default, deserialize_with = "readable_nodes")]
+    pub nodes: BTreeMap<String, GraphNodeMeta>,
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+}
+
+/// Where a note sits in the graph view
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphNodeMeta {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub vx: f64,
+    #[serde(default)]
+    pub vy: f64,
+    #[serde(default = "default_radius")]
+    pub radius: f64,
+    /// Milliseconds since the epoch, 0 if never opened
+    #[serde(rename = "lastVisited", default)]
+    pub last_visited: f64,
+    /// Fields written by newer versions of the app
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+fn default_radius() -> f64 {
+    8.0
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Edge {
+    pub id: String,
+    pub source: String,
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    /// Drawn by hand in the graph view
+    #[default]
+    Manual,
+    /// Derived from a [[link]] in the source note's text; owned by the
+    /// backend and rebuilt whenever notes change
+    Wikilink,
+}
+
+/// What pruning removed from a graph
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Pruned {
+    /// Note ids whose node entries were removed
+    pub nodes: Vec<String>,
+    /// Ids of removed edges
+    pub edges: Vec<String>,
+}
+
+impl Pruned {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.edges.is_empty()
+    }
+}
+
+fn readable_nodes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<String, GraphNodeMeta>, D::Error> {
+    let raw: BTreeMap<String, Value> = Option::deserialize(deserializer)?.unwrap_or_default();
+
+    Ok(raw.into_iter()
+        .filter_map(|(id, value)| Some((id, serde_json::from_value(value).ok()?)))
+        .collect())
+}
+
+/// Parses the node entries sent by the webview, refusing the whole lot if
+/// any entry is malformed
+pub fn parse_nodes(nodes: Value) -> Result<BTreeMap<String, GraphNodeMeta>, String> {
+    let Value::Object(entries) = nodes else {
+        return Err("Graph nodes must be an object keyed by note id".to_string());
+    };
+
+    let mut parsed = BTreeMap::new();
+    for (id, value) in entries {
+        if NoteId::parse(&id).is_err() {
+            return Err(format!("Graph node \"{}\" is not a note id", id));
+        }
+        // NaN and Infinity arrive from JavaScript as null
+        let meta: GraphNodeMeta = serde_json::from_value(value)
+            .map_err(|e| format!("Graph node {}: {}", id, e))?;
+        validate_node(&id, &meta)?;
+        parsed.insert(id, meta);
+    }
+
+    Ok(parsed)
+}
+
+fn validate_node(id: &str, meta: &GraphNodeMeta) -> Result<(), String> {
+    let numbers = [("x", meta.x), ("y", meta.y), ("vx", meta.vx), ("vy", meta.vy), ("radius", meta.radius), ("lastVisited", meta.last_visited)];
+
+    if let Some((name, _)) = numbers.iter().find(|(_, v)| !v.is_finite()) {
+        return Err(format!("Graph node {}: {} must be a finite number", id, name));
+    }
+    if meta.radius <= 0.0 {
+        return Err(format!("Graph node {}: radius must be positive", id));
+    }
+    Ok(())
+}
+
+/// Checks the edges sent by the webview
+pub fn validate_edges(edges: &[Edge]) -> Result<(), String> {
+    match edges.iter().find(|e| e.id.is_empty() || e.source.is_empty() || e.target.is_empty()) {
+        Some(edge) => Err(format!("Graph edge \"{}\" is missing its id, source or target", edge.id)),
+        None => Ok(()),
+    }
+}
+
+/// Removes node entries and edges for notes that `exists` says are gone
+pub fn prune(graph: &mut GraphMetadata, exists: impl Fn(&str) -> bool) -> Pruned {
+    let mut pruned = Pruned::default();
+
+    graph.nodes.retain(|id, _| {
+        let keep = exists(id);
+        if !keep {
+            pruned.nodes.push(id.clone());
+        }
+        keep
+    });
+    graph.edges.retain(|edge| {
+        let keep = exists(&edge.source) && exists(&edge.target);
+        if !keep {
+            pruned.edges.push(edge.id.clone());
+        }
+        keep
+    });
+
+    pruned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn meta(x: f64) -> Value {
+        json!({ "x": x, "y": 2, "vx": 0.5, "vy": -0.5, "radius": 8, "lastVisited": 1760000000000u64 })
+    }
+
+    #[test]
+    fn parses_what_the_graph_view_writes() {
+        let nodes = parse_nodes(json!({ "note-a": meta(1.0) })).unwrap();
+        assert_eq!(nodes["note-a"].x, 1.0);
+        assert_eq!(nodes["note-a"].last_visited, 1760000000000.0);
+    }
+
+    #[test]
+    fn rejects_bad_ids_and_numbers() {
+        assert!(parse_nodes(json!({ "../escape": meta(1.0) })).is_err());
+        assert!(parse_nodes(json!({ "a": { "x": null, "y": 0 } })).unwrap_err().contains("a:"));
+        assert!(parse_nodes(json!({ "a": { "x": 0, "y": 0, "radius": 0 } })).is_err());
+        assert!(parse_nodes(json!([1, 2])).is_err());
+    }
+
+    #[test]
+    fn loading_skips_unreadable_entries_and_keeps_unknown_fields() {
+        let graph: GraphMetadata = serde_json::from_value(json!({
+            "nodes": { "a": { "x": 1, "y": 2, "pinned": true }, "b": "garbage" },
+            "edges": []
+        })).unwrap();
+
+        assert_eq!(graph.nodes.keys().collect::<Vec<_>>(), ["a"]);
+        assert_eq!(graph.nodes["a"].radius, 8.0);
+        assert_eq!(serde_json::to_value(&graph).unwrap()["nodes"]["a"]["pinned"], true);
+
+        let empty: GraphMetadata = serde_json::from_str(r#"{ "nodes": null }"#).unwrap();
+        assert!(empty.nodes.is_empty());
+    }
+
+    #[test]
+    fn prunes_nodes_and_edges_of_missing_notes() {
+        let mut graph: GraphMetadata = serde_json::from_value(json!({
+            "nodes": { "a": meta(0.0), "gone": meta(0.0) },
+            "edges": [
+                { "id": "e1", "source": "a", "target": "b" },
+                { "id": "e2", "source": "a", "target": "gone" }
+            ]
+        })).unwrap();
+
+        let pruned = prune(&mut graph, |id| id != "gone");
+        assert_eq!(pruned, Pruned { nodes: vec!["gone".to_string()], edges: vec!["e2".to_string()] });
+        assert_eq!(graph.edges.len(), 1);
+        assert!(prune(&mut graph, |id| id != "gone").is_empty());
+    }
+}
@@ -0,0 +1,351 @@
+//! ====== REVISION HISTORY ======
+//!
+//! Every save of a note or its canvas is also kept as a revision:
+//!
+//!   .history/
+//!   └── {id}/
+//!       ├── note/{revision}.md
+//!       └── canvas/{revision}.json
+//!
+//! A revision id is the UTC time it was started (20261017T093000.250Z), so
+//! file names sort chronologically. History is compacted as it grows:
+//!   - saves less than COALESCE_WINDOW after the latest revision was
+//!     started update that revision in place, so autosave doesn't leave a
+//!     file per keystroke pause, unless the save throws away most of the
+//!     text (select-all + delete), which always starts a new revision
+//!   - older revisions are thinned: all of the last day, the last one per
+//!     hour for a week, per day for a month, per week after that
+//!
+//! A save over a file the history doesn't have (a note from before there
+//! was history, or one edited outside the app) first records the file as
+//! it was, so that save can be undone too.
+//!
+//! Revisions are items of the vault's store (see store.rs).
+//!
+//! ==============================
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::frontmatter;
+use crate::note_id::NoteId;
+use crate::store::{Item, VaultStore};
+
+const ID_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// Saves closer together than this share a revision
+const COALESCE_WINDOW: Duration = Duration::minutes(2);
+
+/// A save that leaves less than this fraction of the text is a new revision
+const DRASTIC_SHRINK: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionKind {
+    Note,
+    Canvas,
+}
+
+impl RevisionKind {
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            RevisionKind::Note => "note",
+            RevisionKind::Canvas => "canvas",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            RevisionKind::Note => "md",
+            RevisionKind::Canvas => "json",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Revision {
+    pub id: String,
+    #[serde(rename = "noteId")]
+    pub note_id: NoteId,
+    pub kind: RevisionKind,
+    /// When the revision was started
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    /// When it was last written (later than createdAt if saves coalesced)
+    #[serde(rename = "savedAt")]
+    pub saved_at: String,
+    pub size: u64,
+}
+
+/// Records `contents` as the newest revision of the note's `kind` file.
+/// With `coalesce` false the revision is never merged into the previous
+/// one (used when restoring, so the state before it stays retrievable).
+pub fn record(
+    store: &dyn VaultStore,
+    id: &NoteId,
+    kind: RevisionKind,
+    contents: &str,
+    now: DateTime<Utc>,
+    coalesce: bool,
+) -> Result<(), String> {
+    let mut at = now;
+    if let Some((latest_id, started)) = revision_ids(store, id, kind)?.pop() {
+        let latest = Item::Revision(id.clone(), kind, latest_id.clone());
+        let previous = store.read_string(&latest).ok().flatten().unwrap_or_default();
+
+        if previous == contents {
+            return Ok(());
+        }
+        let drastic = is_drastic(text_len(kind, &previous), text_len(kind, contents));
+        if coalesce && now - started < COALESCE_WINDOW && !drastic {
+            return store.write(&latest, contents.as_bytes());
+        }
+        // Ids have millisecond precision; two revisions mustn't share one
+        if revision_id(now) <= latest_id {
+            at = started + Duration::milliseconds(1);
+        }
+    }
+
+    store.write(&Item::Revision(id.clone(), kind, revision_id(at)), contents.as_bytes())?;
+
+    compact(store, id, kind, now)
+}
+
+/// The contents of the newest revision of the note's `kind` file, if it
+/// has any
+pub fn latest(store: &dyn VaultStore, id: &NoteId, kind: RevisionKind) -> Result<Option<String>, String> {
+    match revision_ids(store, id, kind)?.pop() {
+        Some((revision, _)) => store.read_string(&Item::Revision(id.clone(), kind, revision)),
+        None => Ok(None),
+    }
+}
+
+/// Revisions of a note, newest first; both kinds unless one is given
+pub fn list(store: &dyn VaultStore, id: &NoteId, kind: Option<RevisionKind>) -> Result<Vec<Revision>, String> {
+    let kinds = match kind {
+        Some(kind) => vec![kind],
+        None => vec![RevisionKind::Note, RevisionKind::Canvas],
+    };
+    let mut revisions = Vec::new();
+
+    for kind in kinds {
+        for (revision, started) in revision_ids(store, id, kind)? {
+            let item = Item::Revision(id.clone(), kind, revision.clone());
+            let saved_at = store.modified(&item)?.unwrap_or(started);
+
+            revisions.push(Revision {
+                id: revision,
+                note_id: id.clone(),
+                kind,
+                created_at: started.to_rfc3339(),
+                saved_at: saved_at.to_rfc3339(),
+                size: store.size(&item)?.unwrap_or(0),
+            });
+        }
+    }
+
+    revisions.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(revisions)
+}
+
+/// The file contents stored for one revision
+pub fn read(store: &dyn VaultStore, id: &NoteId, kind: RevisionKind, revision: &str) -> Result<String, String> {
+    parse_revision_id(revision)?;
+
+    store.read_string(&Item::Revision(id.clone(), kind, revision.to_string()))?
+        .ok_or_else(|| "Revision not found".to_string())
+}
+
+/// Deletes all history of a note
+pub fn remove(store: &dyn VaultStore, id: &NoteId) -> Result<(), String> {
+    for kind in [RevisionKind::Note, RevisionKind::Canvas] {
+        for revision in store.revisions(id, kind)? {
+            store.remove(&Item::Revision(id.clone(), kind, revision))?;
+        }
+    }
+    Ok(())
+}
+
+/// Parses a revision id, rejecting anything that isn't exactly one (so a
+/// revision id from the webview can never become an arbitrary path)
+pub fn parse_revision_id(revision: &str) -> Result<DateTime<Utc>, String> {
+    NaiveDateTime::parse_from_str(revision, ID_FORMAT)
+        .ok()
+        .map(|t| t.and_utc())
+        .filter(|t| revision_id(*t) == revision)
+        .ok_or_else(|| format!("Invalid revision id: {:?}", revision))
+}
+
+fn revision_id(time: DateTime<Utc>) -> String {
+    time.format(ID_FORMAT).to_string()
+}
+
+/// A note's revision ids of one kind with their start times, oldest first
+fn revision_ids(store: &dyn VaultStore, id: &NoteId, kind: RevisionKind) -> Result<Vec<(String, DateTime<Utc>)>, String> {
+    let mut ids: Vec<(String, DateTime<Utc>)> = store.revisions(id, kind)?
+        .into_iter()
+        .filter_map(|revision| {
+            let started = parse_revision_id(&revision).ok()?;
+            Some((revision, started))
+        })
+        .collect();
+
+    ids.sort();
+    Ok(ids)
+}
+
+/// Length of the text a user edits: a note's body without its frontmatter
+fn text_len(kind: RevisionKind, contents: &str) -> usize {
+    match kind {
+        RevisionKind::Note => frontmatter::parse(contents)
+            .map(|(_, body)| body.len())
+            .unwrap_or(contents.len()),
+        RevisionKind::Canvas => contents.len(),
+    }
+}
+
+fn is_drastic(previous_len: usize, next_len: usize) -> bool {
+    // Halving a line or two is ordinary editing
+    previous_len > 100 && (next_len as f64) < previous_len as f64 * DRASTIC_SHRINK
+}
+
+/// Thins out old revisions, keeping the newest one in each time bucket
+fn compact(store: &dyn VaultStore, id: &NoteId, kind: RevisionKind, now: DateTime<Utc>) -> Result<(), String> {
+    let revisions = revision_ids(store, id, kind)?;
+    let mut kept_bucket: Option<(i64, i64)> = None;
+
+    // Newest first, so the first revision seen in a bucket is the one kept
+    for (revision, started) in revisions.into_iter().rev() {
+        let age = now - started;
+        let bucket_len = if age < Duration::days(1) {
+            continue;
+        } else if age < Duration::days(7) {
+            Duration::hours(1)
+        } else if age < Duration::days(30) {
+            Duration::days(1)
+        } else {
+            Duration::weeks(1)
+        };
+        let bucket = (bucket_len.num_seconds(), started.timestamp() / bucket_len.num_seconds());
+
+        if kept_bucket == Some(bucket) {
+            store.remove(&Item::Revision(id.clone(), kind, revision))?;
+        } else {
+            kept_bucket = Some(bucket);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{FsStore, MemoryStore};
+    use crate::test_util::TempDir;
+
+    fn note_revisions(store: &dyn VaultStore, id: &NoteId) -> Vec<String> {
+        list(store, id, Some(RevisionKind::Note)).unwrap()
+            .into_iter()
+            .map(|r| read(store, id, RevisionKind::Note, &r.id).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn quick_saves_coalesce() {
+        let store = MemoryStore::default();
+        let id = NoteId::new();
+        let start = Utc::now();
+
+        record(&store, &id, RevisionKind::Note, "a", start, true).unwrap();
+        record(&store, &id, RevisionKind::Note, "ab", start + Duration::seconds(30), true).unwrap();
+        record(&store, &id, RevisionKind::Note, "abc", start + Duration::seconds(90), true).unwrap();
+
+        assert_eq!(note_revisions(&store, &id), ["abc"]);
+    }
+
+    #[test]
+    fn saves_after_the_window_start_a_revision() {
+        let store = MemoryStore::default();
+        let id = NoteId::new();
+        let start = Utc::now();
+
+        record(&store, &id, RevisionKind::Note, "first", start, true).unwrap();
+        record(&store, &id, RevisionKind::Note, "second", start + Duration::minutes(3), true).unwrap();
+
+        assert_eq!(note_revisions(&store, &id), ["second", "first"]);
+    }
+
+    #[test]
+    fn wiping_the_text_keeps_the_previous_revision() {
+        let store = MemoryStore::default();
+        let id = NoteId::new();
+        let start = Utc::now();
+        let long = "text ".repeat(100);
+
+        record(&store, &id, RevisionKind::Note, &long, start, true).unwrap();
+        record(&store, &id, RevisionKind::Note, "", start + Duration::seconds(5), true).unwrap();
+
+        assert_eq!(note_revisions(&store, &id), ["".to_string(), long]);
+    }
+
+    #[test]
+    fn uncoalesced_records_and_duplicates() {
+        let store = MemoryStore::default();
+        let id = NoteId::new();
+        let start = Utc::now();
+
+        record(&store, &id, RevisionKind::Note, "before", start, true).unwrap();
+        record(&store, &id, RevisionKind::Note, "before", start + Duration::seconds(1), false).unwrap();
+        record(&store, &id, RevisionKind::Note, "restored", start + Duration::seconds(2), false).unwrap();
+        record(&store, &id, RevisionKind::Note, "same instant", start + Duration::seconds(2), false).unwrap();
+
+        assert_eq!(note_revisions(&store, &id), ["same instant", "restored", "before"]);
+    }
+
+    #[test]
+    fn kinds_are_kept_apart() {
+        let store = MemoryStore::default();
+        let id = NoteId::new();
+        let now = Utc::now();
+
+        record(&store, &id, RevisionKind::Note, "note", now, true).unwrap();
+        record(&store, &id, RevisionKind::Canvas, "{}", now, true).unwrap();
+
+        assert_eq!(list(&store, &id, None).unwrap().len(), 2);
+        let canvas = list(&store, &id, Some(RevisionKind::Canvas)).unwrap();
+        assert_eq!(read(&store, &id, RevisionKind::Canvas, &canvas[0].id).unwrap(), "{}");
+    }
+
+    #[test]
+    fn old_revisions_are_thinned() {
+        let dir = TempDir::new("history");
+        let store = FsStore::new(dir.0.clone());
+        let id = NoteId::new();
+        let now = "2026-10-17T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let two_days_ago = now - Duration::days(2);
+
+        // Four revisions within one hour two days ago, two in the last day
+        for (i, minutes) in [0, 10, 20, 30].iter().enumerate() {
+            let at = two_days_ago + Duration::minutes(*minutes);
+            record(&store, &id, RevisionKind::Note, &format!("old {}", i), at, false).unwrap();
+        }
+        record(&store, &id, RevisionKind::Note, "recent 1", now - Duration::hours(2), false).unwrap();
+        record(&store, &id, RevisionKind::Note, "recent 2", now, false).unwrap();
+
+        let kept = note_revisions(&store, &id);
+        assert_eq!(&kept[..2], ["recent 2", "recent 1"]);
+        assert!(kept.len() <= 4, "{:?}", kept);
+        assert!(kept.contains(&"old 3".to_string()));
+        assert!(!kept.contains(&"old 0".to_string()));
+    }
+
+    #[test]
+    fn revision_ids_are_validated() {
+        let id = revision_id(Utc::now());
+        assert!(parse_revision_id(&id).is_ok());
+        for bad in ["", "../note", "20261017T093000Z", "20261017T093000.250Z/../x", "latest"] {
+            assert!(parse_revision_id(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,3003 @@
+//! ====== MESSY NOTES - LOCAL FILE STORAGE ======
+//!
+//! This is a LOCAL-FIRST application. All data is stored on the user's computer.
+//!
+//! FILE STRUCTURE:
+//!   ~/Documents/MessyNotes/
+//!   ├── notes/              ← Notes as .md files: YAML frontmatter + Markdown body
+//!   │   ├── {uuid}.md
+//!   │   └── {uuid}.md
+//!   ├── folders.json        ← Folder hierarchy
+//!   ├── canvas/             ← Canvas data as JSON (per-note mindmaps)
+//!   │   ├── {uuid}.json
+//!   │   └── {uuid}.json
+//!   ├── graph.json          ← Global graph (node positions & connections)
+//!   ├── vault.json          ← Vault settings (format version, trash retention)
+//!   ├── .trash/             ← Deleted notes, restorable until purged
+//!   ├── .history/           ← Past revisions of each note and canvas
+//!   ├── .search/            ← Full-text search index (rebuilt if missing)
+//!   ├── .lost+found/        ← Unreadable files set aside by repair_vault
+//!   ├── .previews/          ← Cached image thumbnails (rebuilt if missing)
+//!   └── attachments/        ← Attached files, named by content hash
+//!
+//! The app reads and writes this layout through FsStore (see store.rs);
+//! only the caches (.search/, .previews/) and repairs (.lost+found/) are
+//! files beside it.
+//!
+//! That is the default vault. Other folders can be vaults too; the app
+//! keeps a list of them in its config dir and opens one at a time
+//! (see vaults.rs).
+//!
+//! NO CLOUD SYNC - Everything stays on the user's machine!
+//!
+//! ===============================================
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use uuid::Uuid;
+use chrono::Utc;
+use anyhow::Result;
+
+pub mod attachments;
+pub mod canvas;
+pub mod diff;
+pub mod durable;
+pub mod frontmatter;
+pub mod fsck;
+pub mod graph;
+pub mod history;
+pub mod lifecycle;
+pub mod links;
+pub mod markdown;
+pub mod note_id;
+pub mod obsidian;
+pub mod preview;
+pub mod protocol;
+pub mod search;
+pub mod store;
+#[cfg(test)]
+mod test_util;
+pub mod tags;
+pub mod trash;
+pub mod vaults;
+
+use attachments::Attachment;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use canvas::Canvas;
+use preview::Preview;
+use diff::DiffLine;
+use graph::{Edge, EdgeKind, GraphMetadata};
+use history::{Revision, RevisionKind};
+use lifecycle::Action;
+use note_id::NoteId;
+use search::{SearchDoc, SearchHit, SearchIndex};
+use store::{FsStore, Item, VaultStore};
+use trash::TrashEntry;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Note {
+    pub id: NoteId,
+    pub title: String,
+    #[serde(rename = "rawText")]
+    pub raw_text: Option<String>,
+    pub content: Option<serde_json::Value>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    pub sticky: bool,
+    pub ephemeral: bool,
+    pub archived: bool,
+    #[serde(rename = "type")]
+    pub note_type: String,
+    pub color: String,
+    #[serde(rename = "folderId", skip_serializing_if = "Option::is_none")]
+    pub folder_id: Option<String>,
+    #[serde(default)]
+    pub position: i32,
+    /// Other names the note goes by; [[links]] and mentions match them too
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// Tags set in frontmatter; #hashtags in the body are tags too but stay
+    /// in the body (see `note_tags`)
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Frontmatter the app doesn't use itself, e.g. written by Obsidian;
+    /// it is written back as it was
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Folder {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "parentId", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: String,
+    #[serde(default)]
+    pub expanded: bool,
+}
+
+/// A [[link]] written in a note, resolved or not
+#[derive(Debug, Serialize, Clone)]
+pub struct NoteLink {
+    pub source: NoteId,
+    /// The linked note, None if no note matches
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<NoteId>,
+    /// The link text as written, e.g. "Project Plan" for [[Project Plan|plan]]
+    #[serde(rename = "targetText")]
+    pub target_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+/// Where a backlink comes from
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BacklinkSource {
+    /// A [[link]] in another note's text
+    Note,
+    /// A [[link]] on a card in another note's canvas
+    Canvas,
+    /// An edge drawn by hand in the graph view
+    Graph,
+}
+
+/// Something that links to a note, with the text around each link
+#[derive(Debug, Serialize, Clone)]
+pub struct Backlink {
+    #[serde(rename = "noteId")]
+    pub note_id: NoteId,
+    pub title: String,
+    pub source: BacklinkSource,
+    /// The canvas card holding the links, for canvas backlinks
+    #[serde(rename = "cardId", skip_serializing_if = "Option::is_none")]
+    pub card_id: Option<String>,
+    /// One line of context per link; a graph edge's label, if it has one
+    pub contexts: Vec<String>,
+}
+
+/// A plain-text occurrence of a note's title or alias that isn't a link
+#[derive(Debug, Serialize, Clone)]
+pub struct UnlinkedMention {
+    #[serde(rename = "noteId")]
+    pub note_id: NoteId,
+    pub title: String,
+    /// The mention as written
+    pub text: String,
+    pub context: String,
+}
+
+/// What renaming a note changed
+#[derive(Debug, Serialize, Clone)]
+pub struct RenameReport {
+    pub note: Note,
+    #[serde(rename = "oldTitle")]
+    pub old_title: String,
+    /// Every file written, relative to the vault folder
+    pub files: Vec<String>,
+    /// How many [[links]] now point at the new title
+    #[serde(rename = "linksUpdated")]
+    pub links_updated: usize,
+}
+
+/// A tag and how many notes carry it, nested tags included
+#[derive(Debug, Serialize, Clone)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// What renaming or merging tags changed
+#[derive(Debug, Serialize, Clone)]
+pub struct RetagReport {
+    /// Every file written, relative to the vault folder
+    pub files: Vec<String>,
+    #[serde(rename = "notesUpdated")]
+    pub notes_updated: Vec<NoteId>,
+}
+
+/// What one run of the ephemeral note lifecycle did
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct LifecycleReport {
+    pub promoted: Vec<Note>,
+    pub archived: Vec<Note>,
+    pub trashed: Vec<NoteId>,
+}
+
+/// An ephemeral note that will expire soon unless it is touched
+#[derive(Debug, Serialize, Clone)]
+pub struct ExpiringNote {
+    #[serde(rename = "noteId")]
+    pub note_id: NoteId,
+    pub title: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+    /// What will happen to it: archive or trash
+    pub action: Action,
+}
+
+/// A note to create; whatever is left out gets the usual default
+#[derive(Debug, Default)]
+pub struct NewNote {
+    pub title: Option<String>,
+    pub raw_text: Option<String>,
+    pub content: Option<serde_json::Value>,
+    pub sticky: Option<bool>,
+    pub ephemeral: Option<bool>,
+    pub note_type: Option<String>,
+    pub color: Option<String>,
+    pub folder_id: Option<String>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// Changes to a note; None leaves a field as it is
+#[derive(Debug, Default)]
+pub struct NoteChanges {
+    pub title: Option<String>,
+    pub raw_text: Option<String>,
+    pub content: Option<serde_json::Value>,
+    pub sticky: Option<bool>,
+    pub ephemeral: Option<bool>,
+    pub archived: Option<bool>,
+    /// Some(None) moves the note to the root
+    pub folder_id: Option<Option<String>>,
+    pub position: Option<i32>,
+    pub aliases: Option<Vec<String>>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// On-disk frontmatter of a note; field order is the order written to the file
+#[derive(Serialize)]
+struct NoteFrontmatter<'a> {
+    title: &'a str,
+    #[serde(rename = "createdAt")]
+    created_at: &'a str,
+    #[serde(rename = "updatedAt")]
+    updated_at: &'a str,
+    #[serde(rename = "type")]
+    note_type: &'a str,
+    color: &'a str,
+    sticky: bool,
+    ephemeral: bool,
+    archived: bool,
+    #[serde(rename = "folderId", skip_serializing_if = "Option::is_none")]
+    folder_id: Option<&'a str>,
+    position: i32,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    aliases: &'a [String],
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+    #[serde(flatten)]
+    extra: &'a serde_json::Map<String, serde_json::Value>,
+}
+
+/// Frontmatter keys the app reads into a Note's fields (`content` is the
+/// TipTap tree older versions stored); any others are kept in `extra`
+pub const FRONTMATTER_KEYS: &[&str] = &[
+    "title", "createdAt", "updatedAt", "type", "color", "sticky", "ephemeral",
+    "archived", "folderId", "position", "aliases", "tags", "content",
+];
+
+/// Per-vault settings stored in vault.json
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultConfig {
+    /// On-disk format revision; bumped when a startup migration has run
+    #[serde(rename = "formatVersion", default)]
+    format_version: u32,
+    /// Days a deleted note stays in .trash/ before it is purged (0 = forever)
+    #[serde(rename = "trashRetentionDays", default = "default_trash_retention_days")]
+    trash_retention_days: u32,
+    /// When ephemeral notes expire or become permanent
+    #[serde(default)]
+    ephemeral: lifecycle::Policy,
+    /// When the ephemeral note TTL started applying to this vault; notes
+    /// untouched since before then count their TTL from it
+    #[serde(rename = "ephemeralSince", default, skip_serializing_if = "Option::is_none")]
+    ephemeral_since: Option<String>,
+}
+
+impl Default for VaultConfig {
+    fn default() -> Self {
+        VaultConfig {
+            format_version: 0,
+            trash_retention_days: default_trash_retention_days(),
+            ephemeral: lifecycle::Policy::default(),
+            ephemeral_since: None,
+        }
+    }
+}
+
+fn default_trash_retention_days() -> u32 {
+    30
+}
+
+/// Format version written by this build
+/// (2 = YAML frontmatter, 3 = body is Markdown, no `content` in frontmatter)
+const VAULT_FORMAT_VERSION: u32 = 3;
+
+/// In-memory copy of every note in notes/, keyed by id
+type NoteIndex = HashMap<NoteId, Note>;
+
+/// The note index and the names [[links]] to its notes resolve by
+struct LoadedNotes {
+    index: NoteIndex,
+    names: links::NameIndex,
+}
+
+/// Why repairs fail for a vault without a folder
+const NOT_IN_A_FOLDER: &str = "This vault is not kept in a folder";
+
+/// Minimum time between writes of the search index to disk
+const SEARCH_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// An open vault: where it is and what is cached about it. The app keeps
+/// one in its managed state; messy-cli makes its own for each run.
+pub struct AppState {
+    /// Where the open vault is kept; switch_vault points this at another one
+    store: RwLock<Arc<dyn VaultStore>>,
+    /// Loaded from the store on first use, then kept current by the write
+    /// paths (save_note, delete_note) so listing never re-reads the whole vault
+    notes: RwLock<Option<LoadedNotes>>,
+    /// Full-text index, loaded on first search and then updated alongside
+    /// the note index
+    search: Mutex<Option<SearchIndex>>,
+}
+
+impl AppState {
+    /// The vault in the folder `data_dir`
+    pub fn new(data_dir: PathBuf) -> Self {
+        AppState::with_store(Arc::new(FsStore::new(data_dir)))
+    }
+
+    /// A vault kept in any store, e.g. a MemoryStore in tests
+    pub fn with_store(store: Arc<dyn VaultStore>) -> Self {
+        AppState {
+            store: RwLock::new(store),
+            notes: RwLock::new(None),
+            search: Mutex::new(None),
+        }
+    }
+
+    pub fn store(&self) -> Arc<dyn VaultStore> {
+        self.store.read().map(|store| store.clone()).unwrap_or_else(|e| e.into_inner().clone())
+    }
+
+    /// Returns path to the open vault: ~/Documents/MessyNotes/ by default.
+    /// Empty for a vault that isn't kept in a folder.
+    pub fn data_dir(&self) -> PathBuf {
+        self.store().files().map(|files| files.dir().to_path_buf()).unwrap_or_default()
+    }
+
+    /// Points the state at the vault in another folder, dropping everything
+    /// cached about the old one
+    pub fn set_data_dir(&self, data_dir: PathBuf) -> Result<(), String> {
+        // Saved while search_file() still points into the old vault
+        self.flush_search();
+        *self.store.write().map_err(|e| e.to_string())? = Arc::new(FsStore::new(data_dir));
+        self.drop_notes();
+        Ok(())
+    }
+
+    /// The vault folder, for what is kept there beside the store (see
+    /// store.rs)
+    fn vault_dir(&self) -> Result<PathBuf, String> {
+        self.store().files()
+            .map(|files| files.dir().to_path_buf())
+            .ok_or_else(|| NOT_IN_A_FOLDER.to_string())
+    }
+
+    /// Returns path to notes directory: ~/Documents/MessyNotes/notes/
+    pub fn notes_dir(&self) -> PathBuf {
+        self.data_dir().join("notes")
+    }
+
+    /// Returns path to attachments directory: ~/Documents/MessyNotes/attachments/
+    pub fn attachments_dir(&self) -> PathBuf {
+        self.data_dir().join("attachments")
+    }
+
+    /// Returns path to graph file: ~/Documents/MessyNotes/graph.json
+    pub fn graph_file(&self) -> PathBuf {
+        self.data_dir().join(Item::Graph.path())
+    }
+
+    /// Returns path to folders file: ~/Documents/MessyNotes/folders.json
+    pub fn folders_file(&self) -> PathBuf {
+        self.data_dir().join(Item::Folders.path())
+    }
+
+    /// Returns path to vault settings: ~/Documents/MessyNotes/vault.json
+    pub fn config_file(&self) -> PathBuf {
+        self.data_dir().join(Item::Config.path())
+    }
+
+    /// Returns path to set-aside files: ~/Documents/MessyNotes/.lost+found/
+    fn lost_found_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.vault_dir()?.join(".lost+found"))
+    }
+
+    /// Returns path to image thumbnails: ~/Documents/MessyNotes/.previews/
+    fn previews_dir(&self) -> Option<PathBuf> {
+        self.vault_dir().ok().map(|dir| dir.join(".previews"))
+    }
+
+    /// Returns path to search index: ~/Documents/MessyNotes/.search/index.json
+    fn search_file(&self) -> Option<PathBuf> {
+        self.vault_dir().ok().map(|dir| dir.join(".search").join("index.json"))
+    }
+
+    /// Returns path to note file: ~/Documents/MessyNotes/notes/{note_id}.md
+    pub fn note_file(&self, note_id: &NoteId) -> PathBuf {
+        self.data_dir().join(Item::Note(note_id.clone()).path())
+    }
+
+    /// Returns path to canvas directory: ~/Documents/MessyNotes/canvas/
+    pub fn canvas_dir(&self) -> PathBuf {
+        self.data_dir().join("canvas")
+    }
+
+    /// Returns path to canvas file: ~/Documents/MessyNotes/canvas/{note_id}.json
+    pub fn canvas_file(&self, note_id: &NoteId) -> PathBuf {
+        self.data_dir().join(Item::Canvas(note_id.clone()).path())
+    }
+
+    /// Ensures all required directories exist
+    pub fn ensure_dirs(&self) -> Result<()> {
+        match self.store().files() {
+            Some(files) => files.ensure_dirs(),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs `f` over the note index, loading it from the store first if needed
+    pub fn with_notes<R>(&self, f: impl FnOnce(&NoteIndex) -> R) -> Result<R, String> {
+        self.with_loaded(|loaded| f(&loaded.index))
+    }
+
+    /// Runs `f` over the names links resolve by, loading the notes first
+    /// if needed
+    fn with_names<R>(&self, f: impl FnOnce(&links::NameIndex) -> R) -> Result<R, String> {
+        self.with_loaded(|loaded| f(&loaded.names))
+    }
+
+    fn with_loaded<R>(&self, f: impl FnOnce(&LoadedNotes) -> R) -> Result<R, String> {
+        {
+            let loaded = self.notes.read().map_err(|e| e.to_string())?;
+            if let Some(loaded) = loaded.as_ref() {
+                return Ok(f(loaded));
+            }
+        }
+        
+        let mut loaded = self.notes.write().map_err(|e| e.to_string())?;
+        if loaded.is_none() {
+            let index = load_note_index(self)?;
+            let mut names = links::NameIndex::default();
+            for note in index.values() {
+                names.insert(&note.id, &note.created_at, &note.title, &note.aliases);
+            }
+            *loaded = Some(LoadedNotes { index, names });
+        }
+        Ok(f(loaded.as_ref().unwrap()))
+    }
+
+    /// Records a note that was just written to the store
+    pub fn index_note(&self, note: &Note) {
+        if let Ok(mut loaded) = self.notes.write() {
+            if let Some(loaded) = loaded.as_mut() {
+                loaded.index.insert(note.id.clone(), note.clone());
+                loaded.names.insert(&note.id, &note.created_at, &note.title, &note.aliases);
+            }
+        }
+        self.update_search(|search| search.update(self.search_doc(note)));
+    }
+
+    /// Forgets a note that was just removed from the store
+    pub fn unindex_note(&self, id: &NoteId) {
+        if let Ok(mut loaded) = self.notes.write() {
+            if let Some(loaded) = loaded.as_mut() {
+                loaded.index.remove(id);
+                loaded.names.remove(id);
+            }
+        }
+        self.update_search(|search| search.remove(id));
+    }
+
+    /// Drops the index so the next read reloads it from the store, saving
+    /// the search index first so its work isn't lost
+    fn invalidate_notes(&self) {
+        self.flush_search();
+        self.drop_notes();
+    }
+
+    fn drop_notes(&self) {
+        if let Ok(mut loaded) = self.notes.write() {
+            *loaded = None;
+        }
+        if let Ok(mut search) = self.search.lock() {
+            *search = None;
+        }
+    }
+
+    /// Runs `f` over the search index, loading it first if needed. A stale
+    /// index on disk is brought up to date with the notes as it loads;
+    /// only notes whose stamp changed are read and indexed again.
+    pub fn with_search<R>(&self, f: impl FnOnce(&mut SearchIndex) -> R) -> Result<R, String> {
+        let mut slot = self.search.lock().map_err(|e| e.to_string())?;
+        
+        if slot.is_none() {
+            let mut search = self.search_file().map(|file| SearchIndex::load(&file)).unwrap_or_default();
+            let notes: Vec<Note> = self.with_notes(|index| index.values().cloned().collect())?;
+            
+            let live: std::collections::HashSet<&NoteId> = notes.iter().map(|n| &n.id).collect();
+            for id in search.ids() {
+                if !live.contains(&id) {
+                    search.remove(&id);
+                }
+            }
+            for note in &notes {
+                if search.stamp(&note.id) != Some(self.search_stamp(note).as_str()) {
+                    search.update(self.search_doc(note));
+                }
+            }
+            
+            *slot = Some(search);
+        }
+        
+        let search = slot.as_mut().unwrap();
+        let result = f(search);
+        self.save_search(search);
+        Ok(result)
+    }
+
+    /// Applies a change to the search index if it is loaded; an unloaded
+    /// index catches up when it loads
+    fn update_search(&self, f: impl FnOnce(&mut SearchIndex)) {
+        if let Ok(mut slot) = self.search.lock() {
+            if let Some(search) = slot.as_mut() {
+                f(search);
+                self.save_search(search);
+            }
+        }
+    }
+
+    /// Writes the search index now if it has changes that aren't on disk
+    fn flush_search(&self) {
+        let Some(file) = self.search_file() else {
+            return;
+        };
+        if let Ok(mut slot) = self.search.lock() {
+            if let Some(search) = slot.as_mut() {
+                if let Err(e) = search.save_if_due(&file, Duration::ZERO) {
+                    eprintln!("Failed to save search index: {}", e);
+                }
+            }
+        }
+    }
+
+    fn save_search(&self, search: &mut SearchIndex) {
+        if let Some(file) = self.search_file() {
+            if let Err(e) = search.save_if_due(&file, SEARCH_SAVE_INTERVAL) {
+                eprintln!("Failed to save search index: {}", e);
+            }
+        }
+    }
+
+    /// Reindexes a note after its canvas changed
+    pub fn canvas_changed(&self, id: &NoteId) {
+        let note = self.notes.read().ok().and_then(|loaded| loaded.as_ref()?.index.get(id).cloned());
+        if let Some(note) = note {
+            self.update_search(|search| search.update(self.search_doc(&note)));
+        }
+    }
+
+    /// A note's canvas, if it has one that can be read
+    fn read_canvas(&self, id: &NoteId) -> Option<Canvas> {
+        let content = self.store().read_string(&Item::Canvas(id.clone())).ok()??;
+        canvas::parse(&content).ok()
+    }
+    
+    /// The searchable text of a note and its canvas
+    fn search_doc(&self, note: &Note) -> SearchDoc {
+        let canvas = self.read_canvas(&note.id)
+            .map(|canvas| search::canvas_text(&canvas))
+            .unwrap_or_default();
+        
+        SearchDoc {
+            id: note.id.clone(),
+            title: note.title.clone(),
+            body: note.raw_text.clone().unwrap_or_default(),
+            canvas,
+            stamp: self.search_stamp(note),
+        }
+    }
+
+    /// What a note's search document is built from: its title and text,
+    /// and the size and time of its canvas file, so telling whether the
+    /// document is stale doesn't take reading the canvas
+    fn search_stamp(&self, note: &Note) -> String {
+        let store = self.store();
+        let canvas = Item::Canvas(note.id.clone());
+        let size = store.size(&canvas).ok().flatten();
+        let modified = store.modified(&canvas).ok().flatten();
+        let body = note.raw_text.as_deref().unwrap_or_default();
+        
+        let stamp = store::fingerprint(format!("{}\0{}\0{:?}\0{:?}", note.title, body, size, modified).as_bytes());
+        format!("{:016x}", stamp)
+    }
+
+    /// A vault file's path relative to the vault folder, for reports
+    pub fn relative_path(&self, path: &Path) -> String {
+        path.strip_prefix(self.data_dir()).unwrap_or(path).display().to_string()
+    }
+    
+    /// Moves a file within the vault folder, e.g. a note into the trash
+    fn move_file(&self, from: &Path, to: &Path) -> Result<(), String> {
+        let store = self.store();
+        store.files().ok_or(NOT_IN_A_FOLDER)?.move_file(from, to)
+    }
+
+    /// True if `path` still holds exactly what the app last wrote there
+    /// (or is still gone after the app removed it)
+    pub fn is_own_change(&self, path: &Path) -> bool {
+        self.store().files().is_some_and(|files| files.is_own_change(path))
+    }
+}
+
+/// Gets a vault ready to be read: sweeps up interrupted writes, upgrades
+/// notes in an older format, purges the trash and catches links and the
+/// graph up with edits made while the app was closed. The app runs this,
+/// then the ephemeral note lifecycle, whenever it opens a vault.
+pub fn prepare_vault(state: &AppState) -> Result<(), String> {
+    state.ensure_dirs().map_err(|e| e.to_string())?;
+    
+    // Sweep up anything a crash left mid-write before the UI reads the vault
+    let recovered = match state.store().files() {
+        Some(files) => files.recover_interrupted_writes().map_err(|e| e.to_string())?,
+        None => 0,
+    };
+    if recovered > 0 {
+        eprintln!("Recovered from {} interrupted write(s)", recovered);
+    }
+    
+    // One-time upgrade of notes written in an older format
+    let mut config = load_vault_config(state)?;
+    if config.format_version < VAULT_FORMAT_VERSION {
+        let migrated = migrate_legacy_notes(state)?;
+        if migrated > 0 {
+            eprintln!("Migrated {} note(s) to the current note format", migrated);
+        }
+        config.format_version = VAULT_FORMAT_VERSION;
+        save_vault_config(&config, state)?;
+    }
+    
+    if config.trash_retention_days > 0 {
+        let purged = purge_trash(config.trash_retention_days, state)?;
+        if purged > 0 {
+            eprintln!("Purged {} note(s) from the trash", purged);
+        }
+    }
+    
+    // Links may have been edited, and notes deleted, while the app was closed
+    sync_wikilinks(state, None)?;
+    let pruned = prune_graph(state)?;
+    if !pruned.is_empty() {
+        eprintln!("Pruned {} graph node(s) and {} edge(s) of missing notes", pruned.nodes.len(), pruned.edges.len());
+    }
+    
+    Ok(())
+}
+
+// ==================== NOTE OPERATIONS ====================
+// Each note is stored as: ~/Documents/MessyNotes/notes/{uuid}.md
+
+pub fn get_notes(state: &AppState) -> Result<Vec<Note>, String> {
+    state.ensure_dirs().map_err(|e| e.to_string())?;
+    
+    let mut notes: Vec<Note> = state.with_notes(|index| index.values().cloned().collect())?;
+    notes.sort_by(compare_notes);
+    
+    Ok(notes)
+}
+
+pub fn get_note(id: NoteId, state: &AppState) -> Result<Note, String> {
+    // Always read the file itself so a single note is never stale
+    let Some(content) = state.store().read_string(&Item::Note(id.clone()))? else {
+        state.unindex_note(&id);
+        return Err("Note not found".to_string());
+    };
+    let note = parse_note(id, &content)?;
+    state.index_note(&note);
+    
+    Ok(note)
+}
+
+/// A note by id, or else by title or alias the way [[links]] resolve
+pub fn find_note(query: &str, state: &AppState) -> Result<Note, String> {
+    if let Ok(id) = NoteId::parse(query) {
+        if state.store().exists(&Item::Note(id.clone())) {
+            return get_note(id, state);
+        }
+    }
+    
+    let notes = notes_for_linking(state)?;
+    let id = link_resolver(&notes).resolve(query).cloned()
+        .ok_or_else(|| format!("No note called \"{}\"", query))?;
+    get_note(id, state)
+}
+
+pub fn create_note(new: NewNote, state: &AppState) -> Result<Note, String> {
+    state.ensure_dirs().map_err(|e| e.to_string())?;
+    
+    let NewNote { title, raw_text, content, sticky, ephemeral, note_type, color, folder_id, tags } = new;
+    
+    let id = NoteId::new();
+    let now = Utc::now().to_rfc3339();
+    let title = title.unwrap_or_else(|| "Untitled Thought".to_string());
+    let raw_text = raw_text.unwrap_or_default();
+    
+    // Quick captures send plain text only; it is read as Markdown
+    let content = content.unwrap_or_else(|| markdown::to_doc(&raw_text));
+    
+    // Get max position in the target folder/root
+    let max_position = state
+        .with_notes(|index| {
+            index.values()
+                .filter(|n| n.folder_id == folder_id)
+                .map(|n| n.position)
+                .max()
+        })?
+        .unwrap_or(-1);
+    
+    let note = Note {
+        id: id.clone(),
+        title: title.clone(),
+        raw_text: Some(raw_text.clone()),
+        content: Some(content),
+        updated_at: now.clone(),
+        created_at: now.clone(),
+        sticky: sticky.unwrap_or(false),
+        ephemeral: ephemeral.unwrap_or(true),
+        archived: false,
+        note_type: note_type.unwrap_or_else(|| "text".to_string()),
+        color: color.unwrap_or_else(|| "#ffffff".to_string()),
+        folder_id,
+        position: max_position + 1,
+        aliases: Vec::new(),
+        tags: clean_tags(tags.unwrap_or_default()),
+        extra: serde_json::Map::new(),
+    };
+    
+    save_note(&note, state)?;
+    
+    Ok(note)
+}
+
+pub fn update_note(id: NoteId, changes: NoteChanges, state: &AppState) -> Result<Note, String> {
+    let mut note = get_note(id, state)?;
+    let NoteChanges { title, raw_text, content, sticky, ephemeral, archived, folder_id, position, aliases, tags } = changes;
+    
+    if let Some(t) = title {
+        note.title = t;
+    }
+    // The content tree wins; rawText alone (non-empty) replaces the body.
+    // Every update from the webview carries rawText, often as "".
+    if let Some(c) = content {
+        note.raw_text = Some(markdown::plain_text(&c));
+        note.content = Some(c);
+    } else if let Some(rt) = raw_text.filter(|rt| !rt.is_empty()) {
+        note.content = Some(markdown::to_doc(&rt));
+        note.raw_text = Some(rt);
+    }
+    if let Some(s) = sticky {
+        note.sticky = s;
+    }
+    if let Some(e) = ephemeral {
+        note.ephemeral = e;
+    }
+    if let Some(a) = archived {
+        note.archived = a;
+    }
+    if let Some(fid) = folder_id {
+        note.folder_id = fid;
+    }
+    if let Some(p) = position {
+        note.position = p;
+    }
+    if let Some(a) = aliases {
+        note.aliases = a.into_iter()
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty())
+            .collect();
+    }
+    if let Some(t) = tags {
+        note.tags = clean_tags(t);
+    }
+
+    note.updated_at = Utc::now().to_rfc3339();
+    
+    save_note(&note, state)?;
+    
+    Ok(note)
+}
+
+pub fn reorder_notes(
+    note_id: NoteId,
+    target_folder_id: Option<String>,
+    new_position: i32,
+    state: &AppState,
+) -> Result<(), String> {
+    // Only the notes in the source and target folders are affected
+    let (mut moved_note, mut folder_notes, mut old_folder_notes) = state.with_notes(|index| {
+        let moved_note = index.get(&note_id)?.clone();
+        let mut folder_notes = Vec::new();
+        let mut old_folder_notes = Vec::new();
+        
+        for note in index.values() {
+            if note.id == note_id {
+                continue;
+            }
+            if note.folder_id == target_folder_id {
+                folder_notes.push(note.clone());
+            } else if note.folder_id == moved_note.folder_id {
+                old_folder_notes.push(note.clone());
+            }
+        }
+        
+        Some((moved_note, folder_notes, old_folder_notes))
+    })?.ok_or("Note not found")?;
+    let old_folder_id = moved_note.folder_id.clone();
+    
+    // Same order the sidebar shows
+    folder_notes.sort_by(compare_notes);
+    old_folder_notes.sort_by(compare_notes);
+    
+    // Update folder if changed
+    moved_note.folder_id = target_folder_id.clone();
+    
+    // Insert at new position
+    let insert_pos = new_position.max(0).min(folder_notes.len() as i32) as usize;
+    folder_notes.insert(insert_pos, moved_note);
+    
+    // Renumber the target folder, writing only notes whose position changed
+    for (idx, note) in folder_notes.iter_mut().enumerate() {
+        if note.position != idx as i32 || note.id == note_id {
+            note.position = idx as i32;
+            save_note(note, state)?;
+        }
+    }
+    
+    // If folder changed, close the gap in the old folder too
+    if old_folder_id != target_folder_id {
+        for (idx, note) in old_folder_notes.iter_mut().enumerate() {
+            if note.position != idx as i32 {
+                note.position = idx as i32;
+                save_note(note, state)?;
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Moves the note to the trash (see trash.rs); restore_note brings it back
+pub fn delete_note(id: NoteId, state: &AppState) -> Result<(), String> {
+    if !state.store().exists(&Item::Note(id.clone())) {
+        state.unindex_note(&id);
+        return Ok(());
+    }
+    
+    // The note's graph node and edges go to the trash with it
+    let mut graph = load_graph(state).ok();
+    
+    trash_note(&id, &Utc::now().to_rfc3339(), graph.as_mut(), state)?;
+    
+    if let Some(graph) = graph {
+        save_graph(&graph, state)?;
+    }
+    
+    // Links to its title may now point at another note with the same title
+    sync_wikilinks(state, None)
+}
+
+/// Moves every note to the trash; returns how many were moved
+pub fn delete_all_notes(state: &AppState) -> Result<usize, String> {
+    let ids = state.store().notes()?;
+    
+    if !ids.is_empty() {
+        let mut graph = load_graph(state).ok();
+        let deleted_at = Utc::now().to_rfc3339();
+        
+        for id in &ids {
+            trash_note(id, &deleted_at, graph.as_mut(), state)?;
+        }
+        
+        if let Some(graph) = graph {
+            save_graph(&graph, state)?;
+        }
+    }
+    state.invalidate_notes();
+    
+    Ok(ids.len())
+}
+
+// ==================== TRASH OPERATIONS ====================
+// Deleted notes live in: ~/Documents/MessyNotes/.trash/{note_id}/
+
+pub fn list_trash(state: &AppState) -> Result<Vec<TrashEntry>, String> {
+    trash::list(&*state.store())
+}
+
+pub fn restore_note(id: NoteId, state: &AppState) -> Result<Note, String> {
+    let store = state.store();
+    
+    if !trash::contains(&*store, &id) {
+        return Err("Note is not in the trash".to_string());
+    }
+    if store.exists(&Item::Note(id.clone())) {
+        return Err("A note with this id already exists".to_string());
+    }
+    
+    let entry = trash::read_entry(&*store, &id)?;
+    
+    store.rename(&trash::note_item(&id), &Item::Note(id.clone()))?;
+    if store.exists(&trash::canvas_item(&id)) {
+        store.rename(&trash::canvas_item(&id), &Item::Canvas(id.clone()))?;
+    }
+    
+    // Put the graph node back, and the edges whose other end is still around.
+    // Edges to a note that is itself in the trash wait in that note's entry.
+    let mut graph = load_graph(state)?;
+    if let Some(node) = entry.node.clone().and_then(|node| serde_json::from_value(node).ok()) {
+        graph.nodes.insert(id.to_string(), node);
+    }
+    for edge in entry.edges {
+        let other = if id == edge.source { &edge.target } else { &edge.source };
+        let other_id = NoteId::parse(other).ok();
+        
+        if graph.edges.iter().any(|e| e.id == edge.id) {
+            continue;
+        }
+        match other_id {
+            Some(other_id) if trash::contains(&*store, &other_id) => {
+                let mut other_entry = trash::read_entry(&*store, &other_id)?;
+                if !other_entry.edges.iter().any(|e| e.id == edge.id) {
+                    other_entry.edges.push(edge);
+                    trash::write_entry(&*store, &other_entry)?;
+                }
+            }
+            _ => graph.edges.push(edge),
+        }
+    }
+    save_graph(&graph, state)?;
+    
+    trash::remove(&*store, &id)?;
+    
+    let mut note = get_note(id, state)?;
+    
+    // Its folder may have been deleted in the meantime
+    if let Some(folder_id) = &note.folder_id {
+        let folders = get_folders(state)?;
+        if !folders.iter().any(|f| &f.id == folder_id) {
+            note.folder_id = None;
+            save_note(&note, state)?;
+        }
+    }
+    
+    sync_wikilinks(state, None)?;
+    
+    Ok(note)
+}
+
+/// Permanently deletes everything in the trash; returns how many notes
+pub fn empty_trash(state: &AppState) -> Result<usize, String> {
+    let store = state.store();
+    let entries = trash::list(&*store)?;
+    
+    for entry in &entries {
+        trash::remove(&*store, &entry.id)?;
+        history::remove(&*store, &entry.id)?;
+    }
+    
+    Ok(entries.len())
+}
+
+/// Sets how long deleted notes are kept (0 = forever) and applies it right
+/// away; returns how many notes were purged
+pub fn set_trash_retention(days: u32, state: &AppState) -> Result<usize, String> {
+    let mut config = load_vault_config(state)?;
+    config.trash_retention_days = days;
+    save_vault_config(&config, state)?;
+    
+    if days == 0 {
+        return Ok(0);
+    }
+    purge_trash(days, state)
+}
+
+// ==================== EPHEMERAL NOTES ====================
+
+pub fn get_ephemeral_policy(state: &AppState) -> Result<lifecycle::Policy, String> {
+    Ok(load_vault_config(state)?.ephemeral)
+}
+
+/// Changes when ephemeral notes expire or become permanent. The caller
+/// runs the lifecycle to apply the new rules.
+pub fn set_ephemeral_policy(
+    policy: lifecycle::Policy,
+    state: &AppState,
+) -> Result<(), String> {
+    let mut config = load_vault_config(state)?;
+    // Turning the TTL back on mustn't expire everything left alone meanwhile
+    if config.ephemeral.ttl_days == 0 && policy.ttl_days > 0 {
+        config.ephemeral_since = Some(Utc::now().to_rfc3339());
+    }
+    config.ephemeral = policy;
+    save_vault_config(&config, state)
+}
+
+/// Ephemeral notes that expire within `within_days` (default 3), soonest
+/// first; notes already past due are included
+pub fn get_expiring_notes(within_days: Option<u32>, state: &AppState) -> Result<Vec<ExpiringNote>, String> {
+    let config = load_vault_config(state)?;
+    let since = ephemeral_since(&config).unwrap_or_else(Utc::now);
+    let policy = config.ephemeral;
+    let action = match policy.expiry {
+        lifecycle::Expiry::Archive => Action::Archive,
+        lifecycle::Expiry::Trash => Action::Trash,
+    };
+    let horizon = Utc::now() + chrono::Duration::days(within_days.unwrap_or(3) as i64);
+    let graph = load_graph(state)?;
+    
+    let notes: Vec<Note> = state.with_notes(|index| {
+        index.values().filter(|n| lifecycle::is_managed(n)).cloned().collect()
+    })?;
+    
+    let mut expiring: Vec<(chrono::DateTime<Utc>, ExpiringNote)> = Vec::new();
+    for note in notes {
+        let Some(expires_at) = lifecycle::expires_at(&note, &policy, since).filter(|at| *at <= horizon) else {
+            continue;
+        };
+        if lifecycle::is_promotable(note_activity(&note.id, &graph, state), &policy) {
+            continue;
+        }
+        expiring.push((expires_at, ExpiringNote {
+            note_id: note.id,
+            title: note.title,
+            expires_at: expires_at.to_rfc3339(),
+            action,
+        }));
+    }
+    expiring.sort_by_key(|(at, _)| *at);
+    
+    Ok(expiring.into_iter().map(|(_, note)| note).collect())
+}
+
+// ==================== SEARCH ====================
+// The index is stored as: ~/Documents/MessyNotes/.search/index.json
+
+/// Full-text search over titles, note text and canvas cards, best first
+pub fn search_notes(
+    query: String,
+    limit: Option<usize>,
+    state: &AppState,
+) -> Result<Vec<SearchHit>, String> {
+    state.with_search(|search| search.search(&query, limit.unwrap_or(50)))
+}
+
+// ==================== REVISION HISTORY ====================
+// Revisions are stored as: ~/Documents/MessyNotes/.history/{note_id}/
+
+/// Past revisions of a note and/or its canvas, newest first
+pub fn list_revisions(
+    note_id: NoteId,
+    kind: Option<RevisionKind>,
+    state: &AppState,
+) -> Result<Vec<Revision>, String> {
+    history::list(&*state.store(), &note_id, kind)
+}
+
+/// The stored file of one revision (.md for notes, .json for canvases)
+pub fn get_revision(
+    note_id: NoteId,
+    kind: RevisionKind,
+    revision_id: String,
+    state: &AppState,
+) -> Result<String, String> {
+    history::read(&*state.store(), &note_id, kind, &revision_id)
+}
+
+/// Line diff from one revision to another, or to the current file when
+/// `to` is omitted. Notes are compared by their Markdown body.
+pub fn diff_revisions(
+    note_id: NoteId,
+    kind: RevisionKind,
+    from: String,
+    to: Option<String>,
+    state: &AppState,
+) -> Result<Vec<DiffLine>, String> {
+    let store = state.store();
+    let old = history::read(&*store, &note_id, kind, &from)?;
+    let new = match to {
+        Some(to) => history::read(&*store, &note_id, kind, &to)?,
+        None => {
+            let item = match kind {
+                RevisionKind::Note => Item::Note(note_id.clone()),
+                RevisionKind::Canvas => Item::Canvas(note_id.clone()),
+            };
+            store.read_string(&item)?.unwrap_or_default()
+        }
+    };
+    
+    let (old, new) = match kind {
+        RevisionKind::Note => (note_body(&old)?, note_body(&new)?),
+        RevisionKind::Canvas => (old, new),
+    };
+    
+    Ok(diff::lines(&old, &new))
+}
+
+/// Brings back an old revision. A note gets its title and body back but
+/// stays where it is in the sidebar. The state being replaced remains in
+/// the history, so a restore can itself be undone.
+pub fn restore_revision(
+    note_id: NoteId,
+    kind: RevisionKind,
+    revision_id: String,
+    state: &AppState,
+) -> Result<(), String> {
+    let stored = history::read(&*state.store(), &note_id, kind, &revision_id)?;
+    
+    match kind {
+        RevisionKind::Note => {
+            let revision = parse_note(note_id.clone(), &stored)?;
+            let mut note = get_note(note_id, state)?;
+            
+            note.title = revision.title;
+            note.raw_text = revision.raw_text;
+            note.content = revision.content;
+            note.updated_at = Utc::now().to_rfc3339();
+            
+            write_note(&note, state, false)
+        }
+        RevisionKind::Canvas => {
+            // Revisions from before canvases were validated may need fixing
+            let mut canvas = canvas::parse(&stored)?;
+            canvas::repair(&mut canvas);
+            write_canvas(&note_id, &canvas, state, false)
+        }
+    }
+}
+
+// ==================== FOLDER OPERATIONS ====================
+// Folders are stored as: ~/Documents/MessyNotes/folders.json
+
+pub fn get_folders(state: &AppState) -> Result<Vec<Folder>, String> {
+    let Some(content) = state.store().read_string(&Item::Folders)? else {
+        return Ok(vec![]);
+    };
+    
+    let folders: Vec<Folder> = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse folders.json: {}", e))?;
+    
+    Ok(folders)
+}
+
+pub fn create_folder(
+    name: String,
+    parent_id: Option<String>,
+    state: &AppState,
+) -> Result<Folder, String> {
+    state.ensure_dirs().map_err(|e| e.to_string())?;
+    
+    let id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    
+    let folder = Folder {
+        id: id.clone(),
+        name,
+        parent_id,
+        created_at: now.clone(),
+        updated_at: now,
+        expanded: true,
+    };
+    
+    let mut folders = get_folders(state)?;
+    folders.push(folder.clone());
+    
+    save_folders(&folders, state)?;
+    
+    Ok(folder)
+}
+
+pub fn update_folder(
+    id: String,
+    name: Option<String>,
+    parent_id: Option<String>,
+    expanded: Option<bool>,
+    state: &AppState,
+) -> Result<Folder, String> {
+    let mut folders = get_folders(state)?;
+    
+    let folder = folders.iter_mut()
+        .find(|f| f.id == id)
+        .ok_or("Folder not found")?;
+    
+    if let Some(n) = name {
+        folder.name = n;
+    }
+    if parent_id.is_some() {
+        folder.parent_id = parent_id;
+    }
+    if let Some(e) = expanded {
+        folder.expanded = e;
+    }
+    
+    folder.updated_at = Utc::now().to_rfc3339();
+    
+    let updated_folder = folder.clone();
+    save_folders(&folders, state)?;
+    
+    Ok(updated_folder)
+}
+
+pub fn delete_folder(id: String, state: &AppState) -> Result<(), String> {
+    let mut folders = get_folders(state)?;
+    
+    // Move all notes in this folder to root (null folderId)
+    let note_ids: Vec<NoteId> = state.with_notes(|index| {
+        index.values()
+            .filter(|n| n.folder_id.as_ref() == Some(&id))
+            .map(|n| n.id.clone())
+            .collect()
+    })?;
+    for note_id in note_ids {
+        update_note(note_id, NoteChanges { folder_id: Some(None), ..NoteChanges::default() }, state)?;
+    }
+    
+    // Remove the folder
+    let parent_id = folders.iter().find(|f| f.id == id).and_then(|f| f.parent_id.clone());
+    folders.retain(|f| f.id != id);
+    
+    // Move child folders to parent
+    for folder in folders.iter_mut() {
+        if folder.parent_id.as_ref() == Some(&id) {
+            folder.parent_id = parent_id.clone();
+        }
+    }
+    
+    save_folders(&folders, state)?;
+    
+    Ok(())
+}
+
+// ==================== GRAPH OPERATIONS ====================
+// Graph is stored as: ~/Documents/MessyNotes/graph.json
+
+pub fn get_graph(state: &AppState) -> Result<GraphMetadata, String> {
+    load_graph(state)
+}
+
+/// Saves the graph view's node positions and hand-drawn edges. Malformed
+/// entries are refused; entries and edges for notes that are gone are
+/// dropped and returned.
+pub fn save_graph_data(
+    nodes: serde_json::Value,
+    edges: Vec<Edge>,
+    state: &AppState,
+) -> Result<graph::Pruned, String> {
+    let nodes = graph::parse_nodes(nodes)?;
+    graph::validate_edges(&edges)?;
+    
+    // Wikilink edges come from note text, not from the graph view: keep the
+    // ones on disk whatever the webview sent back
+    let mut edges: Vec<Edge> = edges.into_iter().filter(|e| !is_wikilink_edge(e)).collect();
+    if let Ok(current) = load_graph(state) {
+        edges.extend(current.edges.into_iter().filter(is_wikilink_edge));
+    }
+    
+    let mut graph = GraphMetadata { nodes, edges };
+    let pruned = graph::prune(&mut graph, |id| note_exists(id, state));
+    save_graph(&graph, state)?;
+    
+    Ok(pruned)
+}
+
+/// Every [[link]] written in a note (or in all notes), resolved to the
+/// notes they point at where possible
+pub fn get_links(note_id: Option<NoteId>, state: &AppState) -> Result<Vec<NoteLink>, String> {
+    let notes = notes_for_linking(state)?;
+    let resolver = link_resolver(&notes);
+    
+    Ok(notes.iter()
+        .filter(|n| note_id.as_ref().is_none_or(|id| &n.id == id))
+        .flat_map(|note| {
+            let resolver = &resolver;
+            note_links(note).into_iter().map(move |link| NoteLink {
+                source: note.id.clone(),
+                target: resolver.resolve(&link.target).cloned(),
+                target_text: link.target,
+                alias: link.alias,
+            })
+        })
+        .collect())
+}
+
+/// Renames a note and, unless `update_links` is false, rewrites every
+/// [[Old Title]] link to it in note bodies and canvas cards. Either every
+/// file is written or none is. Notes whose links were rewritten keep their
+/// updatedAt, like other automatic changes.
+pub fn rename_note(
+    note_id: NoteId,
+    title: String,
+    update_links: Option<bool>,
+    state: &AppState,
+) -> Result<RenameReport, String> {
+    let title = title.trim().to_string();
+    let update_links = update_links.unwrap_or(true);
+    if title.is_empty() {
+        return Err("Title can't be empty".to_string());
+    }
+    if update_links && !links::is_linkable(&title) {
+        return Err(format!("\"{}\" can't be linked to: titles in links can't contain [ ] | or #", title));
+    }
+    
+    let notes = notes_for_linking(state)?;
+    let mut note = notes.iter().find(|n| n.id == note_id).cloned().ok_or("Note not found")?;
+    let old_title = std::mem::replace(&mut note.title, title.clone());
+    note.updated_at = Utc::now().to_rfc3339();
+    
+    let mut changed_notes: Vec<Note> = Vec::new();
+    let mut changed_canvases: Vec<(NoteId, Canvas)> = Vec::new();
+    let mut links_updated = 0;
+    
+    if update_links {
+        // Links by id still work; only links that reach the note through
+        // its old title would break
+        let resolver = link_resolver(&notes);
+        let old_key = links::normalize_title(&old_title);
+        let is_old_link = |link: &links::WikiLink| {
+            link.target != note_id.as_str()
+                && links::normalize_title(&link.target) == old_key
+                && resolver.resolve(&link.target) == Some(&note_id)
+        };
+        
+        for other in &notes {
+            let mut other = if other.id == note_id { note.clone() } else { other.clone() };
+            let count = match &mut other.content {
+                Some(doc) => links::retarget_doc(doc, &title, &is_old_link),
+                None => {
+                    let (text, count) = links::retarget(other.raw_text.as_deref().unwrap_or_default(), &title, &is_old_link);
+                    other.raw_text = Some(text);
+                    count
+                }
+            };
+            if count > 0 {
+                if let Some(doc) = &other.content {
+                    other.raw_text = Some(markdown::plain_text(doc));
+                }
+                links_updated += count;
+                
+                if other.id == note_id {
+                    note = other.clone();
+                } else {
+                    changed_notes.push(other.clone());
+                }
+            }
+            
+            let Some(mut canvas) = state.read_canvas(&other.id) else {
+                continue;
+            };
+            let count = retarget_cards(&mut canvas, &title, &is_old_link);
+            if count > 0 {
+                links_updated += count;
+                changed_canvases.push((other.id.clone(), canvas));
+            }
+        }
+    }
+    changed_notes.insert(0, note.clone());
+    
+    let mut writes: Vec<(Item, String)> = Vec::new();
+    for changed in &changed_notes {
+        writes.push((Item::Note(changed.id.clone()), render_note(changed)?));
+    }
+    for (id, canvas) in &changed_canvases {
+        let json = serde_json::to_string_pretty(canvas)
+            .map_err(|e| format!("Failed to serialize canvas: {}", e))?;
+        writes.push((Item::Canvas(id.clone()), json));
+    }
+    record_unsaved_revisions(&writes, state)?;
+    write_items(&writes, state)?;
+    
+    for changed in &changed_notes {
+        state.index_note(changed);
+    }
+    for (id, _) in &changed_canvases {
+        state.canvas_changed(id);
+    }
+    sync_wikilinks(state, None)?;
+    
+    // Each rewrite is its own revision so it can be undone from history
+    let kinds = changed_notes.iter().map(|n| (&n.id, RevisionKind::Note))
+        .chain(changed_canvases.iter().map(|(id, _)| (id, RevisionKind::Canvas)));
+    for ((id, kind), (_, contents)) in kinds.zip(&writes) {
+        record_revision(id, kind, contents, false, state)?;
+    }
+    
+    let files = writes.iter().map(|(item, _)| item.path().display().to_string()).collect();
+    
+    Ok(RenameReport {
+        note,
+        old_title,
+        files,
+        links_updated,
+    })
+}
+
+/// Every note, canvas card and hand-drawn graph edge that links to a note.
+/// Text links are found through the wikilink edges in graph.json, so only
+/// notes known to link here are read.
+pub fn get_backlinks(note_id: NoteId, state: &AppState) -> Result<Vec<Backlink>, String> {
+    let notes = notes_for_linking(state)?;
+    if !notes.iter().any(|n| n.id == note_id) {
+        return Err("Note not found".to_string());
+    }
+    let resolver = link_resolver(&notes);
+    let graph = load_graph(state)?;
+    let title_of = |id: &str| notes.iter().find(|n| n.id.as_str() == id).map(|n| n.title.clone());
+    let mut backlinks = Vec::new();
+    
+    let linking_notes = graph.edges.iter()
+        .filter(|e| is_wikilink_edge(e) && e.target == note_id.as_str())
+        .filter_map(|e| notes.iter().find(|n| n.id.as_str() == e.source));
+    for note in linking_notes {
+        let text = note_text(note);
+        let contexts: Vec<String> = links::spans(&text).into_iter()
+            .filter(|span| resolver.resolve(&span.link.target) == Some(&note_id))
+            .map(|span| links::context(&text, span.range))
+            .collect();
+        if !contexts.is_empty() {
+            backlinks.push(Backlink {
+                note_id: note.id.clone(),
+                title: note.title.clone(),
+                source: BacklinkSource::Note,
+                card_id: None,
+                contexts,
+            });
+        }
+    }
+    
+    // Canvas cards have no edges of their own; every canvas is read
+    for note in notes.iter().filter(|n| n.id != note_id) {
+        let Some(canvas) = state.read_canvas(&note.id) else {
+            continue;
+        };
+        for (card_id, text) in canvas_cards(&canvas) {
+            let contexts: Vec<String> = links::spans(&text).into_iter()
+                .filter(|span| resolver.resolve(&span.link.target) == Some(&note_id))
+                .map(|span| links::context(&text, span.range))
+                .collect();
+            if !contexts.is_empty() {
+                backlinks.push(Backlink {
+                    note_id: note.id.clone(),
+                    title: note.title.clone(),
+                    source: BacklinkSource::Canvas,
+                    card_id: Some(card_id),
+                    contexts,
+                });
+            }
+        }
+    }
+    
+    let drawn = graph.edges.iter()
+        .filter(|e| !is_wikilink_edge(e) && e.target == note_id.as_str() && e.source != e.target);
+    for edge in drawn {
+        let (Some(title), Ok(source)) = (title_of(&edge.source), NoteId::parse(&edge.source)) else {
+            continue;
+        };
+        backlinks.push(Backlink {
+            note_id: source,
+            title,
+            source: BacklinkSource::Graph,
+            card_id: None,
+            contexts: edge.label.iter().cloned().collect(),
+        });
+    }
+    
+    Ok(backlinks)
+}
+
+/// Places in other notes that mention a note's title or one of its aliases
+/// in plain text without linking to it
+pub fn get_unlinked_mentions(note_id: NoteId, state: &AppState) -> Result<Vec<UnlinkedMention>, String> {
+    let notes = notes_for_linking(state)?;
+    let note = notes.iter().find(|n| n.id == note_id).ok_or("Note not found")?;
+    
+    // Longest first, so "Project Plan" is found rather than just "Plan"
+    let mut names: Vec<&str> = std::iter::once(note.title.as_str())
+        .chain(note.aliases.iter().map(|a| a.as_str()))
+        .filter(|name| !name.trim().is_empty())
+        .collect();
+    names.sort_by_key(|name| std::cmp::Reverse(name.chars().count()));
+    names.dedup_by(|a, b| links::normalize_title(a) == links::normalize_title(b));
+    
+    let mut mentions = Vec::new();
+    for other in notes.iter().filter(|n| n.id != note_id) {
+        let text = note_text(other);
+        let mut ranges: Vec<std::ops::Range<usize>> = Vec::new();
+        
+        for name in &names {
+            for range in links::mentions(&text, name) {
+                if !ranges.iter().any(|r| r.start < range.end && range.start < r.end) {
+                    ranges.push(range);
+                }
+            }
+        }
+        ranges.sort_by_key(|r| r.start);
+        
+        mentions.extend(ranges.into_iter().map(|range| UnlinkedMention {
+            note_id: other.id.clone(),
+            title: other.title.clone(),
+            text: text[range.clone()].to_string(),
+            context: links::context(&text, range),
+        }));
+    }
+    
+    Ok(mentions)
+}
+
+// ==================== TAG OPERATIONS ====================
+// Tags live in each note's frontmatter and body; there is no separate file
+
+/// Every tag in the vault with how many notes carry it. Parent tags are
+/// listed too, counting the notes under them.
+pub fn list_tags(state: &AppState) -> Result<Vec<TagCount>, String> {
+    let notes = notes_for_linking(state)?;
+    let mut counts: Vec<TagCount> = Vec::new();
+    let mut by_key: HashMap<String, usize> = HashMap::new();
+    
+    for note in &notes {
+        // Each tag once per note, even if both a tag and its child are set
+        let mut seen: Vec<&str> = Vec::new();
+        let note_tags = note_tags(note);
+        for tag in note_tags.iter().flat_map(|t| tags::with_parents(t)) {
+            if seen.iter().any(|s| tags::key(s) == tags::key(tag)) {
+                continue;
+            }
+            seen.push(tag);
+            
+            let slot = *by_key.entry(tags::key(tag)).or_insert_with(|| {
+                counts.push(TagCount { tag: tag.to_string(), count: 0 });
+                counts.len() - 1
+            });
+            counts[slot].count += 1;
+        }
+    }
+    
+    counts.sort_by_key(|t| tags::key(&t.tag));
+    Ok(counts)
+}
+
+/// Notes carrying a tag or any tag nested under it, in sidebar order
+pub fn get_notes_by_tag(tag: String, state: &AppState) -> Result<Vec<Note>, String> {
+    let tag = tags::normalize(&tag).ok_or_else(|| format!("Invalid tag: {}", tag))?;
+    
+    let mut notes: Vec<Note> = state.with_notes(|index| {
+        index.values()
+            .filter(|n| note_tags(n).iter().any(|t| tags::is_within(t, &tag)))
+            .cloned()
+            .collect()
+    })?;
+    notes.sort_by(compare_notes);
+    
+    Ok(notes)
+}
+
+/// Renames a tag, and the tags nested under it, in every note's frontmatter
+/// and body
+pub fn rename_tag(from: String, to: String, state: &AppState) -> Result<RetagReport, String> {
+    retag(&[from], &to, state)
+}
+
+/// Folds several tags into one, in every note's frontmatter and body
+pub fn merge_tags(sources: Vec<String>, target: String, state: &AppState) -> Result<RetagReport, String> {
+    retag(&sources, &target, state)
+}
+
+// ==================== CANVAS OPERATIONS ====================
+// Canvas is stored as: ~/Documents/MessyNotes/canvas/{note_id}.json
+
+/// A note's canvas. A damaged canvas is repaired on the way out so it can
+/// still be opened; the file itself is only fixed by repair_canvas or the
+/// next save.
+pub fn get_canvas(note_id: NoteId, state: &AppState) -> Result<Canvas, String> {
+    let Some(content) = state.store().read_string(&Item::Canvas(note_id))? else {
+        return Ok(Canvas::default());
+    };
+    
+    let mut canvas = canvas::parse(&content)?;
+    canvas::repair(&mut canvas);
+    
+    Ok(canvas)
+}
+
+/// Saves a canvas. One with problems (duplicate ids, edges to missing
+/// nodes, wrong parentIds, bad geometry) is refused with a description of
+/// them, unless `repair` is set: then it is fixed first and what was fixed
+/// is returned.
+pub fn save_canvas_data(
+    note_id: NoteId,
+    nodes: serde_json::Value,
+    edges: serde_json::Value,
+    repair: Option<bool>,
+    state: &AppState,
+) -> Result<Vec<canvas::Problem>, String> {
+    let mut canvas = canvas::from_values(nodes, edges)?;
+    
+    let problems = if repair.unwrap_or(false) {
+        canvas::repair(&mut canvas)
+    } else {
+        let problems = canvas::validate(&canvas);
+        if !problems.is_empty() {
+            return Err(format!("Invalid canvas: {}", canvas::describe(&problems)));
+        }
+        problems
+    };
+    
+    write_canvas(&note_id, &canvas, state, true)?;
+    Ok(problems)
+}
+
+/// What is wrong with a canvas the webview holds, without saving it
+pub fn validate_canvas(nodes: serde_json::Value, edges: serde_json::Value) -> Result<Vec<canvas::Problem>, String> {
+    Ok(canvas::validate(&canvas::from_values(nodes, edges)?))
+}
+
+/// Fixes a note's stored canvas in place; returns what was fixed
+pub fn repair_canvas(note_id: NoteId, state: &AppState) -> Result<Vec<canvas::Problem>, String> {
+    let Some(content) = state.store().read_string(&Item::Canvas(note_id.clone()))? else {
+        return Ok(Vec::new());
+    };
+    
+    let mut canvas = canvas::parse(&content)?;
+    let fixed = canvas::repair(&mut canvas);
+    
+    if !fixed.is_empty() {
+        write_canvas(&note_id, &canvas, state, false)?;
+    }
+    Ok(fixed)
+}
+
+// ==================== ATTACHMENTS ====================
+// Attachments are stored as: ~/Documents/MessyNotes/attachments/{sha256}.{ext}
+// (see attachments.rs)
+
+/// Copies a file into the vault as an attachment
+pub fn import_attachment(path: String, state: &AppState) -> Result<Attachment, String> {
+    let path = PathBuf::from(path);
+    let size = fs::metadata(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > attachments::MAX_SIZE {
+        return Err(format!("Attachments can be at most {} MB", attachments::MAX_SIZE / 1024 / 1024));
+    }
+    
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    store_attachment(&bytes, name, state)
+}
+
+/// Stores a file the webview sends as base64 (a data: URL works too)
+pub fn import_attachment_data(name: String, data: String, state: &AppState) -> Result<Attachment, String> {
+    let encoded = match data.split_once(";base64,") {
+        Some((prefix, rest)) if prefix.starts_with("data:") => rest,
+        _ => data.as_str(),
+    };
+    let bytes = BASE64.decode(encoded.trim())
+        .map_err(|e| format!("Invalid attachment data: {}", e))?;
+    
+    store_attachment(&bytes, &name, state)
+}
+
+/// Every attachment, with the notes that use it
+pub fn list_attachments(state: &AppState) -> Result<Vec<Attachment>, String> {
+    let store = state.store();
+    let mut references = attachment_references(state)?;
+    let mut list = Vec::new();
+    
+    for name in store.attachments()? {
+        // Files put there by hand have no hash to link them by
+        let Some(hash) = attachments::hash_of(&name) else {
+            continue;
+        };
+        let size = store.size(&Item::Attachment(name.clone()))?.unwrap_or(0);
+        let notes = references.remove(hash).unwrap_or_default();
+        list.push(Attachment::new(hash, name.clone(), size, notes));
+    }
+    
+    Ok(list)
+}
+
+/// Removes an attachment, unless a note still uses it
+pub fn delete_attachment(hash: String, state: &AppState) -> Result<(), String> {
+    let store = state.store();
+    let name = attachments::find(&hash, &*store)?.ok_or("Attachment not found")?;
+    
+    if let Some(notes) = attachment_references(state)?.get(&hash) {
+        return Err(format!("Attachment is still used by {} note(s)", notes.len()));
+    }
+    store.remove(&Item::Attachment(name))?;
+    forget_previews(&hash, state);
+    Ok(())
+}
+
+/// Removes every attachment no note uses; returns their file names
+pub fn collect_attachment_garbage(state: &AppState) -> Result<Vec<String>, String> {
+    let references = attachment_references(state)?;
+    let store = state.store();
+    let mut removed = Vec::new();
+    
+    for name in store.attachments()? {
+        let Some(hash) = attachments::hash_of(&name) else {
+            continue;
+        };
+        if !references.contains_key(hash) {
+            store.remove(&Item::Attachment(name.clone()))?;
+            forget_previews(hash, state);
+            removed.push(name);
+        }
+    }
+    
+    Ok(removed)
+}
+
+/// A downscaled, upright preview of an image attachment, no larger than
+/// `max_size` pixels on its longest side (see preview.rs)
+pub fn get_attachment_preview(hash: String, max_size: Option<u32>, state: &AppState) -> Result<Preview, String> {
+    // The hash names the cache file, so it must be one before it is used
+    if !attachments::is_hash(&hash) {
+        return Err("Attachment not found".to_string());
+    }
+    let size = preview::size(max_size);
+    let cache_file = state.previews_dir().map(|dir| preview::cache_file(&dir, &hash, size));
+    if let Some(cached) = cache_file.as_deref().and_then(preview::load) {
+        return Ok(cached);
+    }
+    
+    let store = state.store();
+    let name = attachments::find(&hash, &*store)?.ok_or("Attachment not found")?;
+    let bytes = store.read(&Item::Attachment(name))?.ok_or("Attachment not found")?;
+    let preview = preview::generate(&hash, &bytes, size)?;
+    
+    // Without a cache the preview is just made again next time
+    if let Some(file) = cache_file {
+        if let Err(e) = preview::save(&file, &preview) {
+            eprintln!("Failed to cache preview of {}: {}", hash, e);
+        }
+    }
+    Ok(preview)
+}
+
+// ==================== IMPORT ====================
+// See obsidian.rs
+
+/// Imports the Obsidian vault at `path` into a new folder named after it,
+/// or with `dry_run` only reports what that would do
+pub fn import_obsidian_vault(path: String, dry_run: Option<bool>, state: &AppState) -> Result<obsidian::Report, String> {
+    let source = Path::new(&path);
+    if state.vault_dir().is_ok_and(|dir| vaults::canonical(&dir) == vaults::canonical(source)) {
+        return Err("That is the open vault".to_string());
+    }
+    
+    let plan = obsidian::plan(source)?;
+    if dry_run.unwrap_or(false) {
+        return Ok(plan.report(true));
+    }
+    state.ensure_dirs().map_err(|e| e.to_string())?;
+    
+    // Attachments first, so no note links to a file that isn't there yet
+    let store = state.store();
+    for attachment in &plan.attachments {
+        let bytes = fs::read(&attachment.source)
+            .map_err(|e| format!("Failed to read {}: {}", attachment.path, e))?;
+        attachments::import(&bytes, &attachment.path, &*store)?;
+    }
+    
+    let now = Utc::now().to_rfc3339();
+    let new_folder = |name: &str, parent_id: Option<String>| Folder {
+        id: Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        parent_id,
+        created_at: now.clone(),
+        updated_at: now.clone(),
+        expanded: false,
+    };
+    
+    let mut folders = get_folders(state)?;
+    let top = Folder { expanded: true, ..new_folder(&plan.name, None) };
+    let mut folder_ids: HashMap<&str, String> = HashMap::new();
+    folders.push(top.clone());
+    for planned in &plan.folders {
+        let parent_id = planned.parent.as_deref().and_then(|p| folder_ids.get(p)).unwrap_or(&top.id);
+        let folder = new_folder(&planned.name, Some(parent_id.clone()));
+        folder_ids.insert(&planned.path, folder.id.clone());
+        folders.push(folder);
+    }
+    
+    // Imported notes are kept, not ephemeral scratch notes
+    let mut positions: HashMap<String, i32> = HashMap::new();
+    let notes: Vec<Note> = plan.notes.iter().map(|planned| {
+        let folder_id = planned.folder.as_deref().and_then(|f| folder_ids.get(f)).unwrap_or(&top.id).clone();
+        let position = positions.entry(folder_id.clone()).or_insert(0);
+        *position += 1;
+        let content = markdown::to_doc(&planned.body);
+        
+        Note {
+            id: planned.id.clone(),
+            title: planned.title.clone(),
+            raw_text: Some(markdown::plain_text(&content)),
+            content: Some(content),
+            updated_at: planned.updated_at.clone(),
+            created_at: planned.created_at.clone(),
+            sticky: false,
+            ephemeral: false,
+            archived: false,
+            note_type: "text".to_string(),
+            color: "#ffffff".to_string(),
+            folder_id: Some(folder_id),
+            position: *position - 1,
+            aliases: planned.aliases.clone(),
+            tags: clean_tags(planned.tags.clone()),
+            extra: planned.extra.clone(),
+        }
+    }).collect();
+    
+    let folders_json = serde_json::to_string_pretty(&folders)
+        .map_err(|e| format!("Failed to serialize folders: {}", e))?;
+    let mut items = vec![(Item::Folders, folders_json)];
+    for note in &notes {
+        items.push((Item::Note(note.id.clone()), render_note(note)?));
+    }
+    write_items(&items, state)?;
+    
+    for (note, (_, content)) in notes.iter().zip(&items[1..]) {
+        state.index_note(note);
+        record_revision(&note.id, RevisionKind::Note, content, false, state)?;
+    }
+    sync_wikilinks(state, None)?;
+    
+    Ok(plan.report(false))
+}
+
+// ==================== VAULT CHECK ====================
+// See fsck.rs
+
+/// Everything that doesn't add up between notes, folders, canvases and the
+/// graph; nothing is changed
+pub fn check_vault(state: &AppState) -> Result<Vec<fsck::Issue>, String> {
+    fsck::check(state)
+}
+
+/// Fixes the given kinds of issue (all of them if None); returns what was
+/// fixed and what is left
+pub fn repair_vault(kinds: Option<Vec<fsck::IssueKind>>, state: &AppState) -> Result<fsck::Repair, String> {
+    fsck::repair(kinds.as_deref(), state)
+}
+
+// ==================== HELPER FUNCTIONS ====================
+
+/// Saves a note to disk as a .md file with YAML frontmatter.
+/// The body is the TipTap content as Markdown; rawText is only written
+/// as-is for a note that has no content tree.
+fn save_note(note: &Note, state: &AppState) -> Result<(), String> {
+    write_note(note, state, true)
+}
+
+/// save_note, choosing whether the revision it records may be merged into
+/// the previous one (see history.rs)
+fn write_note(note: &Note, state: &AppState, coalesce: bool) -> Result<(), String> {
+    let content = render_note(note)?;
+    let kept = record_unsaved_revision(&note.id, RevisionKind::Note, &content, state)?;
+    state.store().write(&Item::Note(note.id.clone()), content.as_bytes())?;
+    note_written(note, state)?;
+    
+    record_revision(&note.id, RevisionKind::Note, &content, coalesce && !kept, state)
+}
+
+/// Indexes a note just written to the store, by the app or from outside
+/// it, and brings its wikilink edges in graph.json up to date
+pub fn note_written(note: &Note, state: &AppState) -> Result<(), String> {
+    let previous = state.with_notes(|index| index.get(&note.id).cloned())?;
+    state.index_note(note);
+    
+    // A new or renamed note can change where other notes' links resolve;
+    // otherwise graph.json only changes if this note's own links did
+    match previous {
+        Some(previous) if previous.title == note.title
+            && previous.aliases == note.aliases
+            && previous.created_at == note.created_at => {
+            let (before, after) = state.with_names(|names| (linked_notes(&previous, names), linked_notes(note, names)))?;
+            if before != after {
+                sync_wikilinks(state, Some(&note.id))?;
+            }
+            Ok(())
+        }
+        _ => sync_wikilinks(state, None),
+    }
+}
+
+/// A note's file contents: YAML frontmatter and a Markdown body
+pub fn render_note(note: &Note) -> Result<String, String> {
+    let metadata = NoteFrontmatter {
+        title: &note.title,
+        created_at: &note.created_at,
+        updated_at: &note.updated_at,
+        note_type: &note.note_type,
+        color: &note.color,
+        sticky: note.sticky,
+        ephemeral: note.ephemeral,
+        archived: note.archived,
+        folder_id: note.folder_id.as_deref(),
+        position: note.position,
+        aliases: &note.aliases,
+        tags: &note.tags,
+        extra: &note.extra,
+    };
+    
+    frontmatter::render(&metadata, &note_markdown(note))
+}
+
+/// A note's body as Markdown
+pub fn note_markdown(note: &Note) -> String {
+    match &note.content {
+        Some(doc) => markdown::from_doc(doc),
+        None => note.raw_text.clone().unwrap_or_default(),
+    }
+}
+
+/// Saves a canvas to disk as JSON and records it as a revision
+fn write_canvas(note_id: &NoteId, canvas: &Canvas, state: &AppState, coalesce: bool) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(canvas)
+        .map_err(|e| format!("Failed to serialize canvas: {}", e))?;
+    
+    let kept = record_unsaved_revision(note_id, RevisionKind::Canvas, &json, state)?;
+    state.store().write(&Item::Canvas(note_id.clone()), json.as_bytes())?;
+    state.canvas_changed(note_id);
+    
+    record_revision(note_id, RevisionKind::Canvas, &json, coalesce && !kept, state)
+}
+
+/// Stores an attachment's bytes and describes it
+fn store_attachment(bytes: &[u8], name: &str, state: &AppState) -> Result<Attachment, String> {
+    state.ensure_dirs().map_err(|e| e.to_string())?;
+    
+    let (hash, file_name) = attachments::import(bytes, name, &*state.store())?;
+    let notes = attachment_references(state)?.remove(&hash).unwrap_or_default();
+    
+    Ok(Attachment::new(&hash, file_name, bytes.len() as u64, notes))
+}
+
+/// Drops the cached previews of a removed attachment
+fn forget_previews(hash: &str, state: &AppState) {
+    if let Some(dir) = state.previews_dir() {
+        if let Err(e) = preview::forget(&dir, hash) {
+            eprintln!("Failed to remove previews of {}: {}", hash, e);
+        }
+    }
+}
+
+/// Which notes use which attachments, going by their note files and
+/// canvases, trashed ones included
+fn attachment_references(state: &AppState) -> Result<BTreeMap<String, Vec<NoteId>>, String> {
+    let store = state.store();
+    let mut texts: Vec<(NoteId, String)> = Vec::new();
+    
+    for id in store.notes()? {
+        if let Some(text) = store.read_string(&Item::Note(id.clone()))? {
+            texts.push((id, text));
+        }
+    }
+    for id in store.canvases()? {
+        if let Some(text) = store.read_string(&Item::Canvas(id.clone()))? {
+            texts.push((id, text));
+        }
+    }
+    // Straight from the trashed files: a note whose entry.json is unreadable
+    // can still be restored by hand, so its attachments must survive
+    for id in store.trashed()? {
+        for item in [trash::note_item(&id), trash::canvas_item(&id)] {
+            if let Some(text) = store.read_string(&item)? {
+                texts.push((id.clone(), text));
+            }
+        }
+    }
+    
+    Ok(attachments::count_references(texts.iter().map(|(id, text)| (id, text.as_str()))))
+}
+
+/// Records a revision in the note's history
+fn record_revision(id: &NoteId, kind: RevisionKind, contents: &str, coalesce: bool, state: &AppState) -> Result<(), String> {
+    history::record(&*state.store(), id, kind, contents, Utc::now(), coalesce)
+}
+
+/// Before a note or canvas is overwritten with `next`, records what is
+/// stored now if the history doesn't have it: a note from before history
+/// was kept, or one last changed outside the app. Otherwise the first save
+/// over it (say, select-all + delete) couldn't be undone. Returns whether
+/// it recorded one, after which the save must start a revision of its own.
+fn record_unsaved_revision(id: &NoteId, kind: RevisionKind, next: &str, state: &AppState) -> Result<bool, String> {
+    let store = state.store();
+    let item = match kind {
+        RevisionKind::Note => Item::Note(id.clone()),
+        RevisionKind::Canvas => Item::Canvas(id.clone()),
+    };
+    let Some(current) = store.read_string(&item).ok().flatten() else {
+        return Ok(false);
+    };
+    if current == next || history::latest(&*store, id, kind)?.as_deref() == Some(current.as_str()) {
+        return Ok(false);
+    }
+    
+    record_revision(id, kind, &current, false, state)?;
+    Ok(true)
+}
+
+/// record_unsaved_revision for each note and canvas about to be rewritten
+/// together by write_items
+fn record_unsaved_revisions(writes: &[(Item, String)], state: &AppState) -> Result<(), String> {
+    for (item, contents) in writes {
+        match item {
+            Item::Note(id) => record_unsaved_revision(id, RevisionKind::Note, contents, state)?,
+            Item::Canvas(id) => record_unsaved_revision(id, RevisionKind::Canvas, contents, state)?,
+            _ => continue,
+        };
+    }
+    Ok(())
+}
+
+/// The Markdown body of a note file
+fn note_body(content: &str) -> Result<String, String> {
+    frontmatter::parse(content).map(|(_, body)| body)
+}
+
+/// When the vault's ephemeral note TTL started applying, if it has
+fn ephemeral_since(config: &VaultConfig) -> Option<chrono::DateTime<Utc>> {
+    config.ephemeral_since.as_deref()
+        .and_then(|since| chrono::DateTime::parse_from_rfc3339(since).ok())
+        .map(|since| since.with_timezone(&Utc))
+}
+
+/// How much a note has been edited and linked to. Only revisions that
+/// moved updatedAt on count as edits, so automatic saves don't.
+fn note_activity(id: &NoteId, graph: &GraphMetadata, state: &AppState) -> lifecycle::Activity {
+    let store = state.store();
+    let mut revisions = history::list(&*store, id, Some(RevisionKind::Note)).unwrap_or_default();
+    revisions.reverse();
+    
+    let mut edits = 0;
+    let mut last_updated_at = None;
+    for revision in revisions {
+        let updated_at = history::read(&*store, id, RevisionKind::Note, &revision.id).ok()
+            .and_then(|contents| frontmatter::parse(&contents).ok())
+            .and_then(|(metadata, _)| metadata.get("updatedAt")?.as_str().map(str::to_string));
+        if updated_at != last_updated_at {
+            edits += 1;
+            last_updated_at = updated_at;
+        }
+    }
+    
+    let mut sources: Vec<&str> = graph.edges.iter()
+        .filter(|e| e.target == id.as_str() && e.source != id.as_str())
+        .map(|e| e.source.as_str())
+        .collect();
+    sources.sort_unstable();
+    sources.dedup();
+    
+    lifecycle::Activity { edits, links: sources.len() }
+}
+
+/// Promotes, archives or trashes ephemeral notes as the vault's policy
+/// says. Automatic changes keep the note's updatedAt, so they don't count
+/// as the note being touched.
+pub fn run_lifecycle(state: &AppState) -> Result<LifecycleReport, String> {
+    let mut config = load_vault_config(state)?;
+    let now = Utc::now();
+    let since = match ephemeral_since(&config) {
+        Some(since) => since,
+        None => {
+            config.ephemeral_since = Some(now.to_rfc3339());
+            save_vault_config(&config, state)?;
+            now
+        }
+    };
+    let policy = config.ephemeral;
+    let graph = load_graph(state)?;
+    
+    let notes: Vec<Note> = state.with_notes(|index| {
+        index.values().filter(|n| lifecycle::is_managed(n)).cloned().collect()
+    })?;
+    
+    let mut report = LifecycleReport::default();
+    let mut to_trash = Vec::new();
+    
+    for mut note in notes {
+        match lifecycle::decide(&note, note_activity(&note.id, &graph, state), &policy, since, now) {
+            Some(Action::Promote) => {
+                note.ephemeral = false;
+                save_note(&note, state)?;
+                report.promoted.push(note);
+            }
+            Some(Action::Archive) => {
+                note.archived = true;
+                save_note(&note, state)?;
+                report.archived.push(note);
+            }
+            Some(Action::Trash) => to_trash.push(note.id),
+            None => {}
+        }
+    }
+    
+    // Saving notes may have rewritten graph.json; trash against a fresh copy
+    if !to_trash.is_empty() {
+        let mut graph = load_graph(state).ok();
+        for id in &to_trash {
+            trash_note(id, &now.to_rfc3339(), graph.as_mut(), state)?;
+        }
+        if let Some(graph) = graph {
+            save_graph(&graph, state)?;
+        }
+        sync_wikilinks(state, None)?;
+        report.trashed = to_trash;
+    }
+    
+    Ok(report)
+}
+
+/// Purges trash entries older than `retention_days`, along with their
+/// history; returns how many notes were purged
+fn purge_trash(retention_days: u32, state: &AppState) -> Result<usize, String> {
+    let store = state.store();
+    let purged = trash::purge(&*store, retention_days, Utc::now())?;
+    
+    for id in &purged {
+        history::remove(&*store, id)?;
+    }
+    
+    Ok(purged.len())
+}
+
+/// Saves folders to disk as JSON
+fn save_folders(folders: &[Folder], state: &AppState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(folders)
+        .map_err(|e| format!("Failed to serialize folders: {}", e))?;
+    
+    state.store().write(&Item::Folders, json.as_bytes())?;
+    
+    Ok(())
+}
+
+/// Writes several items as one change (see VaultStore::write_all)
+fn write_items(items: &[(Item, String)], state: &AppState) -> Result<(), String> {
+    let items: Vec<(Item, Vec<u8>)> = items.iter()
+        .map(|(item, contents)| (item.clone(), contents.clone().into_bytes()))
+        .collect();
+    state.store().write_all(&items)
+}
+
+/// Reads graph.json, or an empty graph if there is none yet
+fn load_graph(state: &AppState) -> Result<GraphMetadata, String> {
+    let Some(content) = state.store().read_string(&Item::Graph)? else {
+        return Ok(GraphMetadata::default());
+    };
+    
+    let graph: GraphMetadata = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse graph.json: {}", e))?;
+    
+    Ok(graph)
+}
+
+/// True if a note with this id is in the store (trashed notes are not)
+fn note_exists(id: &str, state: &AppState) -> bool {
+    NoteId::parse(id).is_ok_and(|id| state.store().exists(&Item::Note(id)))
+}
+
+/// Drops graph entries and edges for notes deleted while the app was
+/// closed, writing graph.json only if something was dropped
+fn prune_graph(state: &AppState) -> Result<graph::Pruned, String> {
+    let mut graph = load_graph(state)?;
+    let pruned = graph::prune(&mut graph, |id| note_exists(id, state));
+    if !pruned.is_empty() {
+        save_graph(&graph, state)?;
+    }
+    Ok(pruned)
+}
+
+/// Saves graph data to disk as JSON
+fn save_graph(graph: &GraphMetadata, state: &AppState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(graph)
+        .map_err(|e| format!("Failed to serialize graph: {}", e))?;
+    
+    state.store().write(&Item::Graph, json.as_bytes())?;
+    
+    Ok(())
+}
+
+/// Moves a note, its canvas and (if given) its graph node and edges into
+/// .trash/{id}/. The caller saves the graph.
+fn trash_note(
+    id: &NoteId,
+    deleted_at: &str,
+    graph: Option<&mut GraphMetadata>,
+    state: &AppState,
+) -> Result<(), String> {
+    let store = state.store();
+    
+    // Unparseable notes can still be trashed, just without a title
+    let note = store.read_string(&Item::Note(id.clone()))
+        .ok()
+        .flatten()
+        .and_then(|content| parse_note(id.clone(), &content).ok());
+    
+    let mut entry = TrashEntry {
+        id: id.clone(),
+        title: note.as_ref().map(|n| n.title.clone()).unwrap_or_else(|| "Untitled".to_string()),
+        deleted_at: deleted_at.to_string(),
+        folder_id: note.and_then(|n| n.folder_id),
+        node: None,
+        edges: vec![],
+    };
+    if let Some(graph) = graph {
+        entry.node = graph.nodes.remove(id.as_str()).and_then(|node| serde_json::to_value(node).ok());
+        let (touching, rest) = graph.edges.drain(..).partition(|e| id == &e.source || id == &e.target);
+        entry.edges = touching;
+        graph.edges = rest;
+    }
+    
+    // The entry goes first: a crash before the note is moved leaves an
+    // entry that list_trash ignores, never a note with no entry
+    trash::write_entry(&*store, &entry)?;
+    store.rename(&Item::Note(id.clone()), &trash::note_item(id))?;
+    
+    if store.exists(&Item::Canvas(id.clone())) {
+        store.rename(&Item::Canvas(id.clone()), &trash::canvas_item(id))?;
+    }
+    
+    state.unindex_note(id);
+    
+    Ok(())
+}
+
+fn is_wikilink_edge(edge: &Edge) -> bool {
+    edge.kind == EdgeKind::Wikilink
+}
+
+/// The [[links]] in a note's text
+fn note_links(note: &Note) -> Vec<links::WikiLink> {
+    match &note.content {
+        Some(doc) => links::from_doc(doc),
+        None => links::parse(note.raw_text.as_deref().unwrap_or_default()),
+    }
+}
+
+/// The notes a note's [[links]] lead to, in order, each once and never
+/// the note itself
+fn linked_notes(note: &Note, names: &links::NameIndex) -> Vec<NoteId> {
+    let mut targets: Vec<NoteId> = Vec::new();
+    for link in note_links(note) {
+        if let Some(target) = names.resolve(&link.target) {
+            if target != &note.id && !targets.contains(target) {
+                targets.push(target.clone());
+            }
+        }
+    }
+    targets
+}
+
+/// All notes, oldest first, so that when two notes share a title links
+/// consistently go to the older one
+fn notes_for_linking(state: &AppState) -> Result<Vec<Note>, String> {
+    let mut notes: Vec<Note> = state.with_notes(|index| index.values().cloned().collect())?;
+    notes.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+    Ok(notes)
+}
+
+/// Titles take precedence over aliases when they collide
+fn link_resolver(notes: &[Note]) -> links::Resolver<'_> {
+    let titles = notes.iter().map(|n| (&n.id, n.title.as_str()));
+    let aliases = notes.iter().flat_map(|n| n.aliases.iter().map(move |a| (&n.id, a.as_str())));
+    links::Resolver::new(titles.chain(aliases))
+}
+
+/// The text of a note that links and mentions are looked for in
+fn note_text(note: &Note) -> String {
+    match &note.content {
+        Some(doc) => links::doc_text(doc),
+        None => note.raw_text.clone().unwrap_or_default(),
+    }
+}
+
+/// Valid, distinct tags from a list as given by the user or frontmatter
+fn clean_tags(raw: Vec<String>) -> Vec<String> {
+    let mut cleaned = Vec::new();
+    for tag in raw.iter().filter_map(|t| tags::normalize(t)) {
+        tags::push_unique(&mut cleaned, tag);
+    }
+    cleaned
+}
+
+/// A note's frontmatter tags followed by the #hashtags in its body
+pub fn note_tags(note: &Note) -> Vec<String> {
+    let mut all = note.tags.clone();
+    for tag in tags::extract(&note_text(note)) {
+        tags::push_unique(&mut all, tag);
+    }
+    all
+}
+
+/// Moves every tag within one of `sources` to `target` across the vault,
+/// writing all affected notes or none of them; they keep their updatedAt
+fn retag(sources: &[String], target: &str, state: &AppState) -> Result<RetagReport, String> {
+    let target = tags::normalize(target).ok_or_else(|| format!("Invalid tag: {}", target))?;
+    let sources: Vec<String> = sources.iter()
+        .map(|s| tags::normalize(s).ok_or_else(|| format!("Invalid tag: {}", s)))
+        .collect::<Result<_, _>>()?;
+    let rename = |tag: &str| sources.iter().find_map(|from| tags::renamed(tag, from, &target));
+    
+    let mut changed: Vec<Note> = Vec::new();
+    
+    for mut note in notes_for_linking(state)? {
+        let mut count = 0;
+        
+        let mut retagged = Vec::new();
+        for tag in &note.tags {
+            match rename(tag) {
+                Some(new_tag) => {
+                    count += 1;
+                    tags::push_unique(&mut retagged, new_tag);
+                }
+                None => tags::push_unique(&mut retagged, tag.clone()),
+            }
+        }
+        note.tags = retagged;
+        
+        count += match &mut note.content {
+            Some(doc) => links::rewrite_doc_text(doc, &|text| tags::rewrite(text, &rename)),
+            None => {
+                let (text, count) = tags::rewrite(note.raw_text.as_deref().unwrap_or_default(), &rename);
+                note.raw_text = Some(text);
+                count
+            }
+        };
+        
+        if count > 0 {
+            if let Some(doc) = &note.content {
+                note.raw_text = Some(markdown::plain_text(doc));
+            }
+            changed.push(note);
+        }
+    }
+    
+    let mut writes: Vec<(Item, String)> = Vec::new();
+    for note in &changed {
+        writes.push((Item::Note(note.id.clone()), render_note(note)?));
+    }
+    record_unsaved_revisions(&writes, state)?;
+    write_items(&writes, state)?;
+    
+    for (note, (_, contents)) in changed.iter().zip(&writes) {
+        state.index_note(note);
+        record_revision(&note.id, RevisionKind::Note, contents, false, state)?;
+    }
+    
+    Ok(RetagReport {
+        files: writes.iter().map(|(item, _)| item.path().display().to_string()).collect(),
+        notes_updated: changed.into_iter().map(|n| n.id).collect(),
+    })
+}
+
+/// `links::retarget` over the label and text of every card on a canvas
+fn retarget_cards(canvas: &mut Canvas, new_target: &str, matches: &dyn Fn(&links::WikiLink) -> bool) -> usize {
+    let mut count = 0;
+    
+    canvas.for_each_node_mut(&mut |node| {
+        for text in [&mut node.label, &mut node.text] {
+            let (retargeted, changed) = links::retarget(text, new_target, matches);
+            if changed > 0 {
+                *text = retargeted;
+                count += changed;
+            }
+        }
+    });
+    
+    count
+}
+
+/// (card id, label and text) of every card on a canvas, nested ones included
+fn canvas_cards(canvas: &Canvas) -> Vec<(String, String)> {
+    canvas.all_nodes()
+        .into_iter()
+        .filter_map(|node| {
+            let text: Vec<&str> = [node.label.as_str(), node.text.as_str()].into_iter()
+                .filter(|t| !t.trim().is_empty())
+                .collect();
+            (!text.is_empty()).then(|| (node.id.clone(), text.join("\n")))
+        })
+        .collect()
+}
+
+/// Rebuilds the wikilink edges in graph.json from note text: those going
+/// out of `source`, or all of them. graph.json is only written if the
+/// edges actually changed.
+pub fn sync_wikilinks(state: &AppState, source: Option<&NoteId>) -> Result<(), String> {
+    let notes = match source {
+        Some(id) => state.with_notes(|index| index.get(id).cloned().into_iter().collect())?,
+        None => notes_for_linking(state)?,
+    };
+    let in_scope = |id: &str| source.is_none_or(|s| s == id);
+    
+    let edges: Vec<Edge> = state.with_names(|names| {
+        notes.iter().flat_map(|note| {
+            linked_notes(note, names).into_iter().map(|target| Edge {
+                id: format!("wikilink:{}:{}", note.id, target),
+                source: note.id.to_string(),
+                target: target.to_string(),
+                label: None,
+                kind: EdgeKind::Wikilink,
+            })
+        }).collect()
+    })?;
+    
+    let mut graph = load_graph(state)?;
+    let mut current: Vec<&str> = graph.edges.iter()
+        .filter(|e| is_wikilink_edge(e) && in_scope(&e.source))
+        .map(|e| e.id.as_str())
+        .collect();
+    let mut wanted: Vec<&str> = edges.iter().map(|e| e.id.as_str()).collect();
+    current.sort_unstable();
+    wanted.sort_unstable();
+    if current == wanted {
+        return Ok(());
+    }
+    
+    graph.edges.retain(|e| !(is_wikilink_edge(e) && in_scope(&e.source)));
+    graph.edges.extend(edges);
+    save_graph(&graph, state)
+}
+
+/// Reads every note in notes/ for the in-memory index
+fn load_note_index(state: &AppState) -> Result<NoteIndex, String> {
+    let mut index = NoteIndex::new();
+    let store = state.store();
+    
+    for id in store.notes()? {
+        let item = Item::Note(id.clone());
+        let Some(content) = store.read_string(&item)? else {
+            continue;
+        };
+        
+        // One broken file shouldn't hide the rest of the vault; it is
+        // left untouched on disk so nothing overwrites its metadata
+        match parse_note(id, &content) {
+            Ok(note) => {
+                index.insert(note.id.clone(), note);
+            }
+            Err(e) => eprintln!("Skipping {}: {}", item.path().display(), e),
+        }
+    }
+    
+    Ok(index)
+}
+
+/// Sidebar order: root notes first, then grouped by folder; within a
+/// folder by position (ascending), then by updated_at (descending)
+pub fn compare_notes(a: &Note, b: &Note) -> std::cmp::Ordering {
+    match (&a.folder_id, &b.folder_id) {
+        (Some(f1), Some(f2)) if f1 == f2 => {
+            // Same folder: sort by position, then by updated_at
+            match a.position.cmp(&b.position) {
+                std::cmp::Ordering::Equal => b.updated_at.cmp(&a.updated_at),
+                other => other,
+            }
+        },
+        (None, None) => {
+            // Both in root: sort by position, then by updated_at
+            match a.position.cmp(&b.position) {
+                std::cmp::Ordering::Equal => b.updated_at.cmp(&a.updated_at),
+                other => other,
+            }
+        },
+        (None, Some(_)) => std::cmp::Ordering::Less,
+        (Some(_), None) => std::cmp::Ordering::Greater,
+        (Some(f1), Some(f2)) => f1.cmp(f2),
+    }
+}
+
+/// Builds a Note from the contents of its .md file
+pub fn parse_note(id: NoteId, content: &str) -> Result<Note, String> {
+    let (metadata, body) = frontmatter::parse(content)
+        .map_err(|e| format!("Failed to parse note {}: {}", id, e))?;
+    
+    let title = metadata.get("title")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Untitled")
+        .to_string();
+    
+    // The Markdown body is the source of truth. Notes from before format 3
+    // still carry the TipTap tree in frontmatter (and plain text as body).
+    let (note_content, raw_text) = match metadata.get("content") {
+        Some(stored_content) if !stored_content.is_null() => (stored_content.clone(), body),
+        _ => {
+            let doc = markdown::to_doc(&body);
+            let text = markdown::plain_text(&doc);
+            (doc, text)
+        }
+    };
+    
+    Ok(Note {
+        id,
+        title,
+        raw_text: Some(raw_text),
+        content: Some(note_content),
+        updated_at: metadata.get("updatedAt")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&Utc::now().to_rfc3339())
+            .to_string(),
+        created_at: metadata.get("createdAt")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&Utc::now().to_rfc3339())
+            .to_string(),
+        sticky: metadata.get("sticky").and_then(|v| v.as_bool()).unwrap_or(false),
+        ephemeral: metadata.get("ephemeral").and_then(|v| v.as_bool()).unwrap_or(true),
+        archived: metadata.get("archived").and_then(|v| v.as_bool()).unwrap_or(false),
+        note_type: metadata.get("type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("text")
+            .to_string(),
+        color: metadata.get("color")
+            .and_then(|v| v.as_str())
+            .unwrap_or("#ffffff")
+            .to_string(),
+        folder_id: metadata.get("folderId")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        position: metadata.get("position")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0) as i32,
+        // A list, or a single name as other editors also write it
+        aliases: match metadata.get("aliases") {
+            Some(serde_json::Value::String(alias)) => vec![alias.clone()],
+            Some(serde_json::Value::Array(aliases)) => aliases.iter()
+                .filter_map(|a| a.as_str())
+                .map(|a| a.trim().to_string())
+                .filter(|a| !a.is_empty())
+                .collect(),
+            _ => Vec::new(),
+        },
+        // A list, or names separated by commas or spaces
+        tags: clean_tags(match metadata.get("tags") {
+            Some(serde_json::Value::String(tags)) => tags
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .map(|t| t.to_string())
+                .collect(),
+            Some(serde_json::Value::Array(tags)) => tags.iter()
+                .filter_map(|t| t.as_str())
+                .map(|t| t.to_string())
+                .collect(),
+            _ => Vec::new(),
+        }),
+        extra: match metadata {
+            serde_json::Value::Object(fields) => fields.into_iter()
+                .filter(|(key, _)| !FRONTMATTER_KEYS.contains(&key.as_str()))
+                .collect(),
+            _ => serde_json::Map::new(),
+        },
+    })
+}
+
+/// Loads vault.json, falling back to defaults for a vault that predates it
+fn load_vault_config(state: &AppState) -> Result<VaultConfig, String> {
+    let Some(content) = state.store().read_string(&Item::Config)? else {
+        return Ok(VaultConfig::default());
+    };
+    
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse vault.json: {}", e))
+}
+
+/// Saves vault settings to disk as JSON
+fn save_vault_config(config: &VaultConfig, state: &AppState) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize vault config: {}", e))?;
+    
+    state.store().write(&Item::Config, json.as_bytes())?;
+    
+    Ok(())
+}
+
+/// One-time rewrite of notes in an older format: JSON frontmatter becomes
+/// YAML, and a TipTap tree stored in frontmatter moves into the body as
+/// Markdown. Returns how many files were rewritten.
+fn migrate_legacy_notes(state: &AppState) -> Result<usize, String> {
+    let mut migrated = 0;
+    let store = state.store();
+    
+    for id in store.notes()? {
+        let item = Item::Note(id.clone());
+        let Some(content) = store.read_string(&item)? else {
+            continue;
+        };
+        let has_stored_content = frontmatter::parse(&content)
+            .map(|(metadata, _)| metadata.get("content").is_some())
+            .unwrap_or(false);
+        if !frontmatter::is_legacy(&content) && !has_stored_content {
+            continue;
+        }
+        
+        match parse_note(id, &content) {
+            Ok(note) => {
+                save_note(&note, state)?;
+                migrated += 1;
+            }
+            Err(e) => eprintln!("Not migrating {}: {}", item.path().display(), e),
+        }
+    }
+    
+    Ok(migrated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::MemoryStore;
+    use trash::TrashFile;
+    use test_util::TempDir;
+
+    fn vault() -> AppState {
+        AppState::with_store(Arc::new(MemoryStore::default()))
+    }
+
+    fn new_note(title: &str, folder_id: Option<&str>, state: &AppState) -> Note {
+        create_note(NewNote {
+            title: Some(title.to_string()),
+            folder_id: folder_id.map(|f| f.to_string()),
+            ..NewNote::default()
+        }, state).unwrap()
+    }
+
+    /// Titles in each folder, in sidebar order
+    fn sidebar(state: &AppState) -> Vec<(Option<String>, String, i32)> {
+        get_notes(state).unwrap().into_iter().map(|n| (n.folder_id, n.title, n.position)).collect()
+    }
+
+    fn entry(folder: Option<&str>, title: &str, position: i32) -> (Option<String>, String, i32) {
+        (folder.map(|f| f.to_string()), title.to_string(), position)
+    }
+
+    #[test]
+    fn created_notes_go_to_the_end_of_their_folder() {
+        let state = vault();
+        new_note("One", None, &state);
+        new_note("Two", None, &state);
+        new_note("Inside", Some("f"), &state);
+
+        let note = create_note(NewNote {
+            title: Some("Three".to_string()),
+            raw_text: Some("Some **bold** text".to_string()),
+            tags: Some(vec!["#Work".to_string(), "work".to_string(), "bad tag".to_string()]),
+            ..NewNote::default()
+        }, &state).unwrap();
+
+        assert_eq!(note.position, 2);
+        assert!(note.ephemeral && !note.sticky);
+        assert_eq!(note.tags, ["Work"]);
+        assert_eq!(sidebar(&state), [
+            entry(None, "One", 0),
+            entry(None, "Two", 1),
+            entry(None, "Three", 2),
+            entry(Some("f"), "Inside", 0),
+        ]);
+
+        // What was stored reads back the same
+        let stored = state.store().read_string(&Item::Note(note.id.clone())).unwrap().unwrap();
+        let parsed = parse_note(note.id.clone(), &stored).unwrap();
+        assert_eq!(parsed.title, "Three");
+        assert_eq!(note_markdown(&parsed), "Some **bold** text\n");
+    }
+
+    #[test]
+    fn reordering_within_a_folder_renumbers_it() {
+        let state = vault();
+        let a = new_note("A", None, &state);
+        new_note("B", None, &state);
+        new_note("C", None, &state);
+
+        reorder_notes(a.id, None, 2, &state).unwrap();
+
+        assert_eq!(sidebar(&state), [entry(None, "B", 0), entry(None, "C", 1), entry(None, "A", 2)]);
+    }
+
+    #[test]
+    fn moving_a_note_closes_the_gap_it_leaves() {
+        let state = vault();
+        new_note("A", None, &state);
+        let b = new_note("B", None, &state);
+        new_note("C", None, &state);
+        new_note("X", Some("f"), &state);
+        new_note("Y", Some("f"), &state);
+
+        reorder_notes(b.id.clone(), Some("f".to_string()), 1, &state).unwrap();
+
+        assert_eq!(sidebar(&state), [
+            entry(None, "A", 0),
+            entry(None, "C", 1),
+            entry(Some("f"), "X", 0),
+            entry(Some("f"), "B", 1),
+            entry(Some("f"), "Y", 2),
+        ]);
+        // Positions past the end land at the end
+        reorder_notes(b.id, None, i32::MAX, &state).unwrap();
+        assert_eq!(sidebar(&state)[2], entry(None, "B", 2));
+        assert!(reorder_notes(NoteId::new(), None, 0, &state).is_err());
+    }
+
+    #[test]
+    fn deleting_a_folder_keeps_its_notes_and_subfolders() {
+        let state = vault();
+        let outer = create_folder("Outer".to_string(), None, &state).unwrap();
+        let inner = create_folder("Inner".to_string(), Some(outer.id.clone()), &state).unwrap();
+        let nested = create_folder("Nested".to_string(), Some(inner.id.clone()), &state).unwrap();
+        new_note("Loose", None, &state);
+        new_note("Kept", Some(&inner.id), &state);
+
+        delete_folder(inner.id.clone(), &state).unwrap();
+
+        let folders = get_folders(&state).unwrap();
+        assert_eq!(folders.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), ["Outer", "Nested"]);
+        assert_eq!(folders.iter().find(|f| f.id == nested.id).unwrap().parent_id, Some(outer.id));
+
+        let notes = get_notes(&state).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert!(notes.iter().all(|n| n.folder_id.is_none()));
+    }
+
+    #[test]
+    fn deleted_notes_are_restored_with_their_canvas_and_edges() {
+        let state = vault();
+        let target = new_note("Target", None, &state);
+        let source = create_note(NewNote {
+            title: Some("Source".to_string()),
+            raw_text: Some("See [[Target]]".to_string()),
+            ..NewNote::default()
+        }, &state).unwrap();
+        save_canvas_data(target.id.clone(), serde_json::json!([]), serde_json::json!([]), None, &state).unwrap();
+        assert_eq!(get_graph(&state).unwrap().edges.len(), 1);
+
+        delete_note(target.id.clone(), &state).unwrap();
+        assert_eq!(list_trash(&state).unwrap().iter().map(|e| &e.id).collect::<Vec<_>>(), [&target.id]);
+        assert!(!state.store().exists(&Item::Canvas(target.id.clone())));
+        assert!(get_graph(&state).unwrap().edges.is_empty());
+        assert_eq!(get_notes(&state).unwrap().len(), 1);
+
+        let restored = restore_note(target.id.clone(), &state).unwrap();
+        assert_eq!(restored.title, "Target");
+        assert!(state.store().exists(&Item::Canvas(target.id.clone())));
+        assert!(list_trash(&state).unwrap().is_empty());
+        let graph = get_graph(&state).unwrap();
+        assert_eq!((graph.edges[0].source.as_str(), graph.edges[0].target.as_str()), (source.id.as_str(), target.id.as_str()));
+
+        delete_note(source.id.clone(), &state).unwrap();
+        assert_eq!(empty_trash(&state).unwrap(), 1);
+        assert!(restore_note(source.id.clone(), &state).is_err());
+        assert!(list_revisions(source.id, None, &state).unwrap().is_empty());
+    }
+
+    #[test]
+    fn saves_only_rewrite_graph_json_when_their_links_change() {
+        let state = vault();
+        let alpha = new_note("Alpha", None, &state);
+        let gamma = new_note("Gamma", None, &state);
+        let source = create_note(NewNote { raw_text: Some("See [[alpha]]".to_string()), ..NewNote::default() }, &state).unwrap();
+        assert_eq!(get_graph(&state).unwrap().edges.len(), 1);
+
+        // Left alone by a save that links to the same notes
+        state.store().write(&Item::Graph, b"{}").unwrap();
+        let edit = |text: &str| NoteChanges { raw_text: Some(text.to_string()), ..NoteChanges::default() };
+        update_note(source.id.clone(), edit("See [[Alpha]], again"), &state).unwrap();
+        assert_eq!(state.store().read_string(&Item::Graph).unwrap().as_deref(), Some("{}"));
+
+        update_note(source.id.clone(), edit("See [[Alpha]] and [[Gamma]]"), &state).unwrap();
+        let targets: Vec<String> = get_graph(&state).unwrap().edges.into_iter().map(|e| e.target).collect();
+        assert_eq!(targets, [alpha.id.to_string(), gamma.id.to_string()]);
+    }
+
+    #[test]
+    fn the_search_index_is_saved_before_a_vault_switch_and_caught_up_on_load() {
+        let (dir, other) = (TempDir::new("search"), TempDir::new("search-other"));
+        let state = AppState::new(dir.0.clone());
+        let note = new_note("Plans", None, &state);
+        assert_eq!(search_notes("plans".to_string(), None, &state).unwrap().len(), 1);
+
+        // Saved well within SEARCH_SAVE_INTERVAL of the last write
+        let edit = NoteChanges { raw_text: Some("gardening".to_string()), ..NoteChanges::default() };
+        update_note(note.id.clone(), edit, &state).unwrap();
+        state.set_data_dir(other.0.clone()).unwrap();
+        let saved = SearchIndex::load(&dir.0.join(".search").join("index.json"));
+        assert_eq!(saved.search("gardening", 10).len(), 1);
+
+        // Changed while the app wasn't looking
+        let mut changed = get_note(note.id.clone(), &AppState::new(dir.0.clone())).unwrap();
+        changed.raw_text = Some("beekeeping".to_string());
+        changed.content = None;
+        dir.write(&Item::Note(note.id.clone()).path().display().to_string(), render_note(&changed).unwrap().as_bytes());
+
+        let reopened = AppState::new(dir.0.clone());
+        assert_eq!(search_notes("beekeeping".to_string(), None, &reopened).unwrap().len(), 1);
+        assert!(search_notes("gardening".to_string(), None, &reopened).unwrap().is_empty());
+    }
+
+    #[test]
+    fn history_and_settings_live_in_the_store() {
+        let state = vault();
+        let note = new_note("Draft", None, &state);
+        update_note(note.id.clone(), NoteChanges { raw_text: Some("First words".to_string()), ..NoteChanges::default() }, &state).unwrap();
+
+        let revisions = list_revisions(note.id.clone(), Some(RevisionKind::Note), &state).unwrap();
+        assert_eq!(revisions.len(), 1);
+        let stored = get_revision(note.id.clone(), RevisionKind::Note, revisions[0].id.clone(), &state).unwrap();
+        assert!(stored.contains("First words"));
+
+        assert_eq!(load_vault_config(&state).unwrap().trash_retention_days, VaultConfig::default().trash_retention_days);
+        set_trash_retention(5, &state).unwrap();
+        assert_eq!(load_vault_config(&state).unwrap().trash_retention_days, 5);
+        assert!(state.store().exists(&Item::Config));
+        assert_eq!(state.data_dir(), PathBuf::new());
+    }
+
+    #[test]
+    fn text_saved_before_there_was_history_can_be_restored() {
+        let state = vault();
+        let id = NoteId::parse("old-note").unwrap();
+        let original = "---\ntitle: Old note\nephemeral: false\n---\n\nYears of notes\n";
+        state.store().write(&Item::Note(id.clone()), original.as_bytes()).unwrap();
+
+        // Select-all + delete, autosaved
+        update_note(id.clone(), NoteChanges { raw_text: Some(String::new()), ..NoteChanges::default() }, &state).unwrap();
+
+        let revisions = list_revisions(id.clone(), Some(RevisionKind::Note), &state).unwrap();
+        assert_eq!(revisions.len(), 2);
+        restore_revision(id.clone(), RevisionKind::Note, revisions[1].id.clone(), &state).unwrap();
+        assert_eq!(note_markdown(&get_note(id.clone(), &state).unwrap()), "Years of notes\n");
+
+        // Once recorded, it isn't recorded again
+        update_note(id.clone(), NoteChanges { raw_text: Some("More".to_string()), ..NoteChanges::default() }, &state).unwrap();
+        assert_eq!(list_revisions(id, Some(RevisionKind::Note), &state).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn renaming_keeps_what_linking_notes_said_before() {
+        let state = vault();
+        let linking = NoteId::parse("linking").unwrap();
+        let original = "---\ntitle: Linking\n---\n\nSee [[Alpha]]\n";
+        state.store().write(&Item::Note(linking.clone()), original.as_bytes()).unwrap();
+        let alpha = new_note("Alpha", None, &state);
+
+        rename_note(alpha.id, "Beta".to_string(), None, &state).unwrap();
+        assert_eq!(note_markdown(&get_note(linking.clone(), &state).unwrap()), "See [[Beta]]\n");
+
+        let revisions = list_revisions(linking.clone(), Some(RevisionKind::Note), &state).unwrap();
+        assert_eq!(revisions.len(), 2);
+        let before = history::read(&*state.store(), &linking, RevisionKind::Note, &revisions[1].id).unwrap();
+        assert_eq!(before, original);
+    }
+
+    #[test]
+    fn retagging_keeps_what_tagged_notes_said_before() {
+        let state = vault();
+        let tagged = NoteId::parse("tagged").unwrap();
+        let original = "---\ntitle: Tagged\ntags:\n- draft\n---\n\nStill #draft\n";
+        state.store().write(&Item::Note(tagged.clone()), original.as_bytes()).unwrap();
+
+        rename_tag("draft".to_string(), "done".to_string(), &state).unwrap();
+        assert_eq!(get_note(tagged.clone(), &state).unwrap().tags, ["done"]);
+
+        let revisions = list_revisions(tagged.clone(), Some(RevisionKind::Note), &state).unwrap();
+        assert_eq!(revisions.len(), 2);
+        let before = history::read(&*state.store(), &tagged, RevisionKind::Note, &revisions[1].id).unwrap();
+        assert_eq!(before, original);
+    }
+
+    #[test]
+    fn the_lifecycle_spares_old_notes_and_ignores_automatic_saves() {
+        let state = vault();
+        let old = NoteId::parse("old-note").unwrap();
+        let original = "---\ntitle: From last year\nupdatedAt: 2025-10-01T12:00:00Z\n---\n\nStill useful\n";
+        state.store().write(&Item::Note(old.clone()), original.as_bytes()).unwrap();
+
+        // The first run after upgrading starts the TTL rather than applying it
+        let report = run_lifecycle(&state).unwrap();
+        assert!(report.archived.is_empty() && report.trashed.is_empty());
+        assert!(get_expiring_notes(Some(3), &state).unwrap().is_empty());
+        assert_eq!(get_expiring_notes(Some(8), &state).unwrap()[0].note_id, old);
+
+        // Retagging saves revisions, but nobody edited the note
+        let scratch = create_note(NewNote { tags: Some(vec!["a".to_string()]), ..NewNote::default() }, &state).unwrap();
+        for (from, to) in [("a", "b"), ("b", "c"), ("c", "d")] {
+            rename_tag(from.to_string(), to.to_string(), &state).unwrap();
+        }
+        assert_eq!(list_revisions(scratch.id.clone(), Some(RevisionKind::Note), &state).unwrap().len(), 4);
+        assert!(run_lifecycle(&state).unwrap().promoted.is_empty());
+        assert!(get_note(scratch.id, &state).unwrap().ephemeral);
+    }
+
+    #[test]
+    fn attachments_in_use_are_kept_and_the_rest_collected() {
+        let state = vault();
+        let used = import_attachment_data("cat.png".to_string(), format!("data:image/png;base64,{}", BASE64.encode(b"cat")), &state).unwrap();
+        let unused = import_attachment_data("memo.txt".to_string(), BASE64.encode(b"memo"), &state).unwrap();
+        assert!(import_attachment_data("bad".to_string(), "not base64!".to_string(), &state).is_err());
+
+        let note = create_note(NewNote {
+            title: Some("Pets".to_string()),
+            raw_text: Some(format!("![cat]({})", used.uri)),
+            ..NewNote::default()
+        }, &state).unwrap();
+
+        let listed = list_attachments(&state).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed.iter().find(|a| a.hash == used.hash).unwrap().notes, std::slice::from_ref(&note.id));
+        assert_eq!(listed.iter().find(|a| a.hash == unused.hash).unwrap().size, 4);
+
+        assert!(delete_attachment(used.hash.clone(), &state).unwrap_err().contains("1 note"));
+        assert_eq!(collect_attachment_garbage(&state).unwrap(), [unused.file_name]);
+        assert_eq!(list_attachments(&state).unwrap().len(), 1);
+
+        // A trashed note still holds on to its attachments, even when its
+        // trash entry can no longer be read
+        delete_note(note.id.clone(), &state).unwrap();
+        state.store().write(&Item::Trashed(note.id.clone(), TrashFile::Entry), b"{").unwrap();
+        assert!(collect_attachment_garbage(&state).unwrap().is_empty());
+        assert_eq!(list_attachments(&state).unwrap().len(), 1);
+        assert!(get_attachment_preview("../../graph".to_string(), None, &state).is_err());
+    }
+
+    #[test]
+    fn importing_an_obsidian_vault_creates_folders_notes_and_edges() {
+        let obsidian = TempDir::new("import");
+        obsidian.write("Home.md", b"See [[Projects/Plan]] ![[cat.png]]");
+        obsidian.write("Projects/Plan.md", b"---\ntags: work\nstatus: draft\n---\nBack [[Home]]");
+        obsidian.write("cat.png", b"cat");
+        
+        let state = vault();
+        let report = import_obsidian_vault(obsidian.0.display().to_string(), Some(true), &state).unwrap();
+        assert_eq!((report.notes.len(), report.attachments.len(), report.links), (2, 1, 2));
+        assert!(get_notes(&state).unwrap().is_empty());
+        
+        import_obsidian_vault(obsidian.0.display().to_string(), None, &state).unwrap();
+        
+        let folders = get_folders(&state).unwrap();
+        assert_eq!(folders.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), [report.name.as_str(), "Projects"]);
+        assert_eq!(folders[1].parent_id.as_ref(), Some(&folders[0].id));
+        
+        let plan = find_note("Plan", &state).unwrap();
+        assert_eq!(plan.folder_id.as_ref(), Some(&folders[1].id));
+        assert!(!plan.ephemeral);
+        assert_eq!(plan.tags, ["work"]);
+        assert!(render_note(&plan).unwrap().contains("status: draft"));
+        
+        let home = find_note("Home", &state).unwrap();
+        assert!(note_markdown(&home).contains(attachments::URI_PREFIX));
+        assert_eq!(list_attachments(&state).unwrap()[0].notes, std::slice::from_ref(&home.id));
+        assert_eq!(get_links(Some(home.id), &state).unwrap()[0].target, Some(plan.id));
+        assert_eq!(load_graph(&state).unwrap().edges.len(), 2);
+    }
+}
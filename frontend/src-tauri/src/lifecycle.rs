@@ -0,0 +1,208 @@
+//! ====== EPHEMERAL NOTES ======
+//!
+//! New notes start out ephemeral: scratch thoughts that should go away
+//! unless they turn out to matter. At startup and then every hour:
+//!
+//!   - an ephemeral note edited in enough separate sessions or linked from
+//!     enough other notes becomes permanent. A session is a revision in
+//!     .history/ with a new updatedAt; automatic saves (reordering,
+//!     retagging, migrations, repairs) keep updatedAt, so they don't count.
+//!   - an ephemeral note left untouched for the vault's TTL is archived, or
+//!     moved to the trash, as vault.json says. Time counts from when the
+//!     rules first applied to the vault, so notes from before then aren't
+//!     all expired at once.
+//!
+//! Pinned (sticky) and archived notes are left alone.
+//!
+//! =============================
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::Note;
+
+/// What happens to an ephemeral note whose time is up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expiry {
+    #[default]
+    Archive,
+    Trash,
+}
+
+/// The vault's rules for ephemeral notes, stored in vault.json
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Policy {
+    /// Days an untouched ephemeral note lives (0 = forever)
+    #[serde(rename = "ttlDays", default = "default_ttl_days")]
+    pub ttl_days: u32,
+    #[serde(default)]
+    pub expiry: Expiry,
+    /// Editing sessions that make a note permanent (0 = never)
+    #[serde(rename = "promoteAfterEdits", default = "default_promote_after_edits")]
+    pub promote_after_edits: u32,
+    /// Other notes linking to a note that make it permanent (0 = never)
+    #[serde(rename = "promoteAfterLinks", default = "default_promote_after_links")]
+    pub promote_after_links: u32,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            ttl_days: default_ttl_days(),
+            expiry: Expiry::default(),
+            promote_after_edits: default_promote_after_edits(),
+            promote_after_links: default_promote_after_links(),
+        }
+    }
+}
+
+fn default_ttl_days() -> u32 {
+    7
+}
+
+fn default_promote_after_edits() -> u32 {
+    3
+}
+
+fn default_promote_after_links() -> u32 {
+    2
+}
+
+/// How much a note has been worked on and referred to
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Activity {
+    /// Editing sessions in the note's history
+    pub edits: usize,
+    /// Distinct other notes linking to it, by [[link]] or graph edge
+    pub links: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Promote,
+    Archive,
+    Trash,
+}
+
+/// True for the notes the lifecycle applies to
+pub fn is_managed(note: &Note) -> bool {
+    note.ephemeral && !note.sticky && !note.archived
+}
+
+pub fn is_promotable(activity: Activity, policy: &Policy) -> bool {
+    let reached = |count: usize, threshold: u32| threshold > 0 && count >= threshold as usize;
+    reached(activity.edits, policy.promote_after_edits) || reached(activity.links, policy.promote_after_links)
+}
+
+/// When a note expires if nobody touches it, None if it never does.
+/// `since` is when the policy started applying to the vault; a note last
+/// touched before then gets its full TTL from that point.
+pub fn expires_at(note: &Note, policy: &Policy, since: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if policy.ttl_days == 0 || !is_managed(note) {
+        return None;
+    }
+
+    DateTime::parse_from_rfc3339(&note.updated_at)
+        .ok()
+        .map(|updated_at| updated_at.with_timezone(&Utc).max(since) + Duration::days(policy.ttl_days as i64))
+}
+
+/// What should happen to a note now, if anything
+pub fn decide(note: &Note, activity: Activity, policy: &Policy, since: DateTime<Utc>, now: DateTime<Utc>) -> Option<Action> {
+    if !is_managed(note) {
+        return None;
+    }
+    if is_promotable(activity, policy) {
+        return Some(Action::Promote);
+    }
+
+    match expires_at(note, policy, since) {
+        Some(expires_at) if expires_at <= now => Some(match policy.expiry {
+            Expiry::Archive => Action::Archive,
+            Expiry::Trash => Action::Trash,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note_id::NoteId;
+
+    fn note(frontmatter: &str) -> Note {
+        let content = format!("---\ntitle: t\nupdatedAt: 2026-10-01T12:00:00Z\n{}---\n\nbody\n", frontmatter);
+        crate::parse_note(NoteId::new(), &content).unwrap()
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    /// When the policy took effect, before any of the notes were touched
+    fn since() -> DateTime<Utc> {
+        at("2026-09-01T00:00:00Z")
+    }
+
+    #[test]
+    fn untouched_notes_expire_after_the_ttl() {
+        let policy = Policy::default();
+        let idle = Activity::default();
+        let scratch = note("ephemeral: true\n");
+
+        assert_eq!(expires_at(&scratch, &policy, since()), Some(at("2026-10-08T12:00:00Z")));
+        assert_eq!(decide(&scratch, idle, &policy, since(), at("2026-10-08T11:59:59Z")), None);
+        assert_eq!(decide(&scratch, idle, &policy, since(), at("2026-10-08T12:00:00Z")), Some(Action::Archive));
+
+        let trashing = Policy { expiry: Expiry::Trash, ..Policy::default() };
+        assert_eq!(decide(&scratch, idle, &trashing, since(), at("2026-11-01T00:00:00Z")), Some(Action::Trash));
+
+        let forever = Policy { ttl_days: 0, ..Policy::default() };
+        assert_eq!(decide(&scratch, idle, &forever, since(), at("2030-01-01T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn notes_from_before_the_policy_get_a_full_ttl() {
+        let policy = Policy::default();
+        let idle = Activity::default();
+        let old = note("ephemeral: true\n");
+        let upgraded = at("2026-10-20T08:00:00Z");
+
+        assert_eq!(expires_at(&old, &policy, upgraded), Some(at("2026-10-27T08:00:00Z")));
+        assert_eq!(decide(&old, idle, &policy, upgraded, upgraded), None);
+        assert_eq!(decide(&old, idle, &policy, upgraded, at("2026-10-27T08:00:00Z")), Some(Action::Archive));
+    }
+
+    #[test]
+    fn busy_notes_are_promoted_even_when_old() {
+        let policy = Policy::default();
+        let scratch = note("ephemeral: true\n");
+        let later = at("2026-12-01T00:00:00Z");
+
+        assert_eq!(decide(&scratch, Activity { edits: 3, links: 0 }, &policy, since(), later), Some(Action::Promote));
+        assert_eq!(decide(&scratch, Activity { edits: 0, links: 2 }, &policy, since(), later), Some(Action::Promote));
+
+        let never = Policy { promote_after_edits: 0, promote_after_links: 0, ..Policy::default() };
+        assert_eq!(decide(&scratch, Activity { edits: 99, links: 99 }, &never, since(), later), Some(Action::Archive));
+    }
+
+    #[test]
+    fn permanent_pinned_and_archived_notes_are_left_alone() {
+        let policy = Policy::default();
+        let later = at("2027-01-01T00:00:00Z");
+
+        for frontmatter in ["ephemeral: false\n", "ephemeral: true\nsticky: true\n", "ephemeral: true\narchived: true\n"] {
+            let note = note(frontmatter);
+            assert_eq!(expires_at(&note, &policy, since()), None);
+            assert_eq!(decide(&note, Activity { edits: 9, links: 9 }, &policy, since(), later), None);
+        }
+    }
+
+    #[test]
+    fn policy_defaults_fill_missing_fields() {
+        let policy: Policy = serde_json::from_str(r#"{ "expiry": "trash" }"#).unwrap();
+        assert_eq!(policy, Policy { expiry: Expiry::Trash, ..Policy::default() });
+    }
+}
@@ -0,0 +1,502 @@
+//! ====== WIKILINKS ======
+//!
+//! Notes reference each other with wiki-style links in their text:
+//!
+//!   [[Note Title]]          by title (case-insensitive)
+//!   [[note-id|shown text]]  by id, with an alias
+//!   [[Note Title#Heading]]  the heading part is ignored for linking
+//!
+//! Links inside inline code and code blocks are not links. A link resolves
+//! to the note whose id it names, else to the note with that title, else
+//! to a note with that alias; links to notes that don't exist stay
+//! unresolved rather than being dropped.
+//!
+//! =======================
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+
+use crate::note_id::NoteId;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WikiLink {
+    /// What's between the brackets, before any `|alias` or `#heading`
+    pub target: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+}
+
+/// A link and where it sits in the text, brackets included
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkSpan {
+    pub link: WikiLink,
+    pub range: Range<usize>,
+}
+
+/// Every [[link]] in a piece of plain text, in order
+pub fn parse(text: &str) -> Vec<WikiLink> {
+    spans(text).into_iter().map(|s| s.link).collect()
+}
+
+/// Every [[link]] in a piece of plain text with its byte range
+pub fn spans(text: &str) -> Vec<LinkSpan> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+
+    while let Some(open) = text[offset..].find("[[").map(|i| offset + i) {
+        let inner_start = open + 2;
+        let Some(close) = text[inner_start..].find("]]").map(|i| inner_start + i) else {
+            break;
+        };
+        let inner = &text[inner_start..close];
+
+        // "[[a [[b]]" links b; brackets and line breaks can't be in a link
+        if let Some(nested) = inner.rfind("[[") {
+            offset = inner_start + nested;
+            continue;
+        }
+        if !inner.contains(['[', ']', '\n']) {
+            if let Some(link) = parse_inner(inner) {
+                spans.push(LinkSpan { link, range: open..close + 2 });
+            }
+        }
+        offset = close + 2;
+    }
+
+    spans
+}
+
+fn parse_inner(inner: &str) -> Option<WikiLink> {
+    let (target, alias) = match inner.split_once('|') {
+        Some((target, alias)) => (target, Some(alias.trim()).filter(|a| !a.is_empty())),
+        None => (inner, None),
+    };
+    let target = target.split('#').next().unwrap_or("").trim();
+
+    if target.is_empty() {
+        return None;
+    }
+
+    Some(WikiLink {
+        target: target.to_string(),
+        alias: alias.map(|a| a.to_string()),
+    })
+}
+
+/// Every [[link]] in a TipTap document, skipping code
+pub fn from_doc(doc: &Value) -> Vec<WikiLink> {
+    parse(&doc_text(doc))
+}
+
+/// A TipTap document as plain text with one line per block and code left
+/// out, which is what links and mentions are looked for in
+pub fn doc_text(doc: &Value) -> String {
+    let mut text = String::new();
+    collect_text(doc, &mut text);
+    text
+}
+
+/// Flattens a document to text a link can't span: code and block
+/// boundaries become line breaks
+fn collect_text(node: &Value, text: &mut String) {
+    match node.get("type").and_then(|t| t.as_str()) {
+        Some("codeBlock") => text.push('\n'),
+        Some("text") => {
+            let is_code = node.get("marks")
+                .and_then(|m| m.as_array())
+                .is_some_and(|marks| marks.iter().any(|m| m.get("type").and_then(|t| t.as_str()) == Some("code")));
+
+            if is_code {
+                text.push('\n');
+            } else if let Some(s) = node.get("text").and_then(|t| t.as_str()) {
+                text.push_str(s);
+            }
+        }
+        _ => {
+            for child in node.get("content").and_then(|c| c.as_array()).into_iter().flatten() {
+                collect_text(child, text);
+            }
+            text.push('\n');
+        }
+    }
+}
+
+/// Points every [[link]] in `text` that `matches` at `new_target`, keeping
+/// its #heading and |alias. Returns the new text and how many links changed.
+pub fn retarget(text: &str, new_target: &str, matches: &dyn Fn(&WikiLink) -> bool) -> (String, usize) {
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut count = 0;
+
+    for span in spans(text).into_iter().filter(|s| matches(&s.link)) {
+        let inner = &text[span.range.start + 2..span.range.end - 2];
+        let suffix = inner.find(['|', '#']).map(|i| &inner[i..]).unwrap_or("");
+
+        out.push_str(&text[copied..span.range.start]);
+        out.push_str("[[");
+        out.push_str(new_target);
+        out.push_str(suffix);
+        out.push_str("]]");
+        copied = span.range.end;
+        count += 1;
+    }
+
+    out.push_str(&text[copied..]);
+    (out, count)
+}
+
+/// `retarget` over the text of a TipTap document, leaving code alone.
+/// Only links within a single run of text are seen, so a link whose
+/// brackets are styled differently from its title is left as it is.
+pub fn retarget_doc(doc: &mut Value, new_target: &str, matches: &dyn Fn(&WikiLink) -> bool) -> usize {
+    rewrite_doc_text(doc, &|text| retarget(text, new_target, matches))
+}
+
+/// Runs `rewrite` over every run of text in a TipTap document outside code,
+/// replacing the text it changed. Returns the total of the counts it gave.
+pub fn rewrite_doc_text(doc: &mut Value, rewrite: &dyn Fn(&str) -> (String, usize)) -> usize {
+    match doc.get("type").and_then(|t| t.as_str()) {
+        Some("codeBlock") => 0,
+        Some("text") => {
+            let is_code = doc.get("marks")
+                .and_then(|m| m.as_array())
+                .is_some_and(|marks| marks.iter().any(|m| m.get("type").and_then(|t| t.as_str()) == Some("code")));
+            let Some(text) = doc.get("text").and_then(|t| t.as_str()).filter(|_| !is_code) else {
+                return 0;
+            };
+
+            let (text, count) = rewrite(text);
+            if count > 0 {
+                doc["text"] = Value::String(text);
+            }
+            count
+        }
+        _ => doc.get_mut("content")
+            .and_then(|c| c.as_array_mut())
+            .map(|children| children.iter_mut().map(|c| rewrite_doc_text(c, rewrite)).sum())
+            .unwrap_or(0),
+    }
+}
+
+/// True if `title` can be written as the target of a [[link]]
+pub fn is_linkable(title: &str) -> bool {
+    !title.trim().is_empty() && !title.contains(['[', ']', '|', '#', '\n'])
+}
+
+/// Title comparison key: case, surrounding and repeated whitespace ignored
+pub fn normalize_title(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// Case-insensitive, whole-word occurrences of `phrase` in `text` that
+/// aren't already inside a [[link]]
+pub fn mentions(text: &str, phrase: &str) -> Vec<Range<usize>> {
+    let phrase = phrase.trim();
+    if phrase.is_empty() {
+        return Vec::new();
+    }
+
+    let links = spans(text);
+    let mut found = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    let mut previous: Option<char> = None;
+
+    while let Some((start, c)) = chars.next() {
+        let at_word_start = !previous.is_some_and(|p| p.is_alphanumeric());
+        previous = Some(c);
+
+        if !at_word_start {
+            continue;
+        }
+        let Some(len) = match_ignore_case(&text[start..], phrase) else {
+            continue;
+        };
+        let end = start + len;
+
+        let at_word_end = !text[end..].chars().next().is_some_and(|n| n.is_alphanumeric());
+        let in_link = links.iter().any(|l| l.range.start < end && start < l.range.end);
+        if at_word_end && !in_link {
+            found.push(start..end);
+
+            // Continue after the mention
+            while chars.peek().is_some_and(|(i, _)| *i < end) {
+                previous = chars.next().map(|(_, c)| c);
+            }
+        }
+    }
+
+    found
+}
+
+/// Byte length of the prefix of `text` equal to `phrase` ignoring case
+fn match_ignore_case(text: &str, phrase: &str) -> Option<usize> {
+    let mut text_chars = text.char_indices();
+
+    for p in phrase.chars() {
+        let (_, t) = text_chars.next()?;
+        if !t.to_lowercase().eq(p.to_lowercase()) {
+            return None;
+        }
+    }
+
+    Some(text_chars.next().map(|(i, _)| i).unwrap_or(text.len()))
+}
+
+/// The line of `text` around `range`, trimmed to about `CONTEXT_CHARS`
+/// characters either side
+pub fn context(text: &str, range: Range<usize>) -> String {
+    const CONTEXT_CHARS: usize = 80;
+
+    let line_start = text[..range.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line_end = text[range.end..].find('\n').map(|i| range.end + i).unwrap_or(text.len());
+
+    let before: Vec<char> = text[line_start..range.start].chars().collect();
+    let after: Vec<char> = text[range.end..line_end].chars().collect();
+
+    let mut context = String::new();
+    if before.len() > CONTEXT_CHARS {
+        context.push('…');
+    }
+    context.extend(&before[before.len().saturating_sub(CONTEXT_CHARS)..]);
+    context.push_str(&text[range.clone()]);
+    context.extend(after.iter().take(CONTEXT_CHARS));
+    if after.len() > CONTEXT_CHARS {
+        context.push('…');
+    }
+
+    context.trim().to_string()
+}
+
+/// Resolves link targets to note ids
+pub struct Resolver<'a> {
+    by_id: HashMap<&'a str, &'a NoteId>,
+    by_title: HashMap<String, &'a NoteId>,
+}
+
+impl<'a> Resolver<'a> {
+    /// `notes` are (id, name) pairs, a note's title and then its aliases;
+    /// when names collide the first one wins, so pass titles before aliases
+    /// and keep the order stable
+    pub fn new(notes: impl IntoIterator<Item = (&'a NoteId, &'a str)>) -> Self {
+        let mut by_id = HashMap::new();
+        let mut by_title = HashMap::new();
+
+        for (id, title) in notes {
+            by_id.insert(id.as_str(), id);
+            by_title.entry(normalize_title(title)).or_insert(id);
+        }
+
+        Resolver { by_id, by_title }
+    }
+
+    pub fn resolve(&self, target: &str) -> Option<&'a NoteId> {
+        self.by_id.get(target)
+            .or_else(|| self.by_title.get(&normalize_title(target)))
+            .copied()
+    }
+}
+
+/// A Resolver for the notes of an open vault, kept current one note at a
+/// time as notes are saved and deleted rather than rebuilt for each save
+#[derive(Debug, Default)]
+pub struct NameIndex {
+    /// Each note's createdAt and its names, normalized (title first)
+    notes: HashMap<NoteId, (String, Vec<String>)>,
+    /// The notes going by each name: titles before aliases, then the oldest
+    /// first, the same order Resolver gives way in
+    by_name: HashMap<String, BTreeSet<(bool, String, NoteId)>>,
+}
+
+impl NameIndex {
+    /// Adds a note, or updates it if it is already known
+    pub fn insert(&mut self, id: &NoteId, created_at: &str, title: &str, aliases: &[String]) {
+        self.remove(id);
+
+        let names: Vec<String> = std::iter::once(title)
+            .chain(aliases.iter().map(String::as_str))
+            .map(normalize_title)
+            .collect();
+        for (i, name) in names.iter().enumerate() {
+            self.by_name.entry(name.clone()).or_default().insert((i > 0, created_at.to_string(), id.clone()));
+        }
+        self.notes.insert(id.clone(), (created_at.to_string(), names));
+    }
+
+    pub fn remove(&mut self, id: &NoteId) {
+        let Some((created_at, names)) = self.notes.remove(id) else {
+            return;
+        };
+        for (i, name) in names.into_iter().enumerate() {
+            if let Some(ids) = self.by_name.get_mut(&name) {
+                ids.remove(&(i > 0, created_at.clone(), id.clone()));
+                if ids.is_empty() {
+                    self.by_name.remove(&name);
+                }
+            }
+        }
+    }
+
+    pub fn resolve(&self, target: &str) -> Option<&NoteId> {
+        let by_id = NoteId::parse(target).ok().and_then(|id| self.notes.get_key_value(&id));
+        match by_id {
+            Some((id, _)) => Some(id),
+            None => self.by_name.get(&normalize_title(target))?.first().map(|(_, _, id)| id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn link(target: &str, alias: Option<&str>) -> WikiLink {
+        WikiLink { target: target.to_string(), alias: alias.map(|a| a.to_string()) }
+    }
+
+    #[test]
+    fn parses_titles_aliases_and_headings() {
+        assert_eq!(parse("see [[Project Plan]] and [[abc-123|the list]], [[Ideas#Later]]"), vec![
+            link("Project Plan", None),
+            link("abc-123", Some("the list")),
+            link("Ideas", None),
+        ]);
+    }
+
+    #[test]
+    fn ignores_empty_and_broken_links() {
+        assert!(parse("[[]] [[ ]] [[#heading]] [[|alias]] [[unclosed").is_empty());
+        assert!(parse("[[line\nbreak]]").is_empty());
+    }
+
+    #[test]
+    fn nested_opening_brackets_link_the_inner_one() {
+        assert_eq!(parse("[[a [[b]]"), vec![link("b", None)]);
+    }
+
+    #[test]
+    fn doc_links_skip_code() {
+        let doc = json!({
+            "type": "doc",
+            "content": [
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "A " },
+                    { "type": "text", "text": "[[Bold]]", "marks": [{ "type": "bold" }] },
+                    { "type": "text", "text": " and " },
+                    { "type": "text", "text": "[[Code]]", "marks": [{ "type": "code" }] }
+                ]},
+                { "type": "codeBlock", "content": [{ "type": "text", "text": "[[Block]]" }] },
+                { "type": "paragraph", "content": [{ "type": "text", "text": "[[Split" }] },
+                { "type": "paragraph", "content": [{ "type": "text", "text": "Across]]" }] }
+            ]
+        });
+        assert_eq!(from_doc(&doc), vec![link("Bold", None)]);
+    }
+
+    #[test]
+    fn spans_cover_the_brackets() {
+        let text = "x [[a]] y [[b|c]]";
+        let ranges: Vec<&str> = spans(text).iter().map(|s| &text[s.range.clone()]).collect();
+        assert_eq!(ranges, ["[[a]]", "[[b|c]]"]);
+    }
+
+    #[test]
+    fn finds_unlinked_whole_word_mentions() {
+        let text = "Project Plan is linked as [[Project Plan]], not in projectplans, but project plan again.";
+        let found: Vec<&str> = mentions(text, "project plan").into_iter().map(|r| &text[r]).collect();
+        assert_eq!(found, ["Project Plan", "project plan"]);
+    }
+
+    #[test]
+    fn mentions_handle_non_ascii_case() {
+        let text = "Über alles, über";
+        assert_eq!(mentions(text, "über").len(), 2);
+        assert!(mentions(text, "").is_empty());
+    }
+
+    #[test]
+    fn context_is_the_surrounding_line() {
+        let text = "first line\nthe [[Target]] is here\nlast";
+        let range = spans(text)[0].range.clone();
+        assert_eq!(context(text, range), "the [[Target]] is here");
+
+        let long = format!("{}needle{}", "a ".repeat(100), " b".repeat(100));
+        let range = long.find("needle").map(|i| i..i + 6).unwrap();
+        let cut = context(&long, range);
+        assert!(cut.starts_with('…') && cut.ends_with('…') && cut.contains("needle"));
+    }
+
+    #[test]
+    fn retargets_matching_links_only() {
+        let is_old = |l: &WikiLink| normalize_title(&l.target) == "old title";
+        let (text, count) = retarget(
+            "[[Old Title]], [[old  title#Part|see]] and [[Other]]",
+            "New Title",
+            &is_old,
+        );
+        assert_eq!(text, "[[New Title]], [[New Title#Part|see]] and [[Other]]");
+        assert_eq!(count, 2);
+        assert_eq!(retarget("no links", "New", &is_old), ("no links".to_string(), 0));
+    }
+
+    #[test]
+    fn retargets_doc_text_but_not_code() {
+        let mut doc = json!({
+            "type": "doc",
+            "content": [
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "see [[Old]]" },
+                    { "type": "text", "text": "[[Old]]", "marks": [{ "type": "code" }] }
+                ]},
+                { "type": "codeBlock", "content": [{ "type": "text", "text": "[[Old]]" }] }
+            ]
+        });
+        assert_eq!(retarget_doc(&mut doc, "New", &|l| l.target == "Old"), 1);
+        assert_eq!(doc["content"][0]["content"][0]["text"], "see [[New]]");
+        assert_eq!(doc["content"][0]["content"][1]["text"], "[[Old]]");
+        assert_eq!(doc["content"][1]["content"][0]["text"], "[[Old]]");
+    }
+
+    #[test]
+    fn linkable_titles() {
+        assert!(is_linkable("Project Plan (v2)"));
+        assert!(!is_linkable("C# notes"));
+        assert!(!is_linkable("a|b"));
+        assert!(!is_linkable("  "));
+    }
+
+    #[test]
+    fn resolves_ids_then_titles() {
+        let a = NoteId::parse("a").unwrap();
+        let b = NoteId::parse("b").unwrap();
+        let c = NoteId::parse("c").unwrap();
+        let resolver = Resolver::new([(&a, "First Note"), (&b, "a"), (&c, "first  note")]);
+
+        assert_eq!(resolver.resolve("a"), Some(&a));
+        assert_eq!(resolver.resolve(" FIRST note "), Some(&a));
+        assert_eq!(resolver.resolve("b"), Some(&b));
+        assert_eq!(resolver.resolve("Missing"), None);
+    }
+
+    #[test]
+    fn name_index_resolves_like_a_resolver_as_notes_change() {
+        let old = NoteId::parse("old").unwrap();
+        let new = NoteId::parse("new").unwrap();
+        let mut names = NameIndex::default();
+        names.insert(&new, "2026-02-01T00:00:00Z", "Plan", &[]);
+        names.insert(&old, "2026-01-01T00:00:00Z", "Ideas", &["plan".to_string()]);
+
+        // A title beats an alias, and the older of two titles wins
+        assert_eq!(names.resolve("PLAN"), Some(&new));
+        names.insert(&old, "2026-01-01T00:00:00Z", "Plan", &[]);
+        assert_eq!(names.resolve("plan"), Some(&old));
+        assert_eq!(names.resolve("Ideas"), None);
+        assert_eq!(names.resolve("new"), Some(&new));
+
+        names.remove(&old);
+        assert_eq!(names.resolve("Plan"), Some(&new));
+        assert_eq!(names.resolve("old"), None);
+    }
+}
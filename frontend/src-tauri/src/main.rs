@@ -1,143 +1,58 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-/**
- * ====== MESSY NOTES - LOCAL FILE STORAGE ======
- * 
- * This is a LOCAL-FIRST application. All data is stored on the user's computer.
- * 
- * FILE STRUCTURE:
- * ~/Documents/MessyNotes/
- * ├── notes/              ← Notes as .md files with YAML frontmatter
- * │   ├── {uuid}.md
- * │   └── {uuid}.md
- * ├── folders.json        ← Folder hierarchy
- * ├── canvas/             ← Canvas data as JSON (per-note mindmaps)
- * │   ├── {uuid}.json
- * │   └── {uuid}.json
- * ├── graph.json          ← Global graph (node positions & connections)
- * └── attachments/        ← Future: file attachments
- * 
- * NO CLOUD SYNC - Everything stays on the user's machine!
- * 
- * ===============================================
- */
-
-use serde::{Deserialize, Serialize};
+//! ====== MESSY NOTES - DESKTOP APP ======
+//!
+//! The Tauri app. Each command hands the webview's request to the storage
+//! library (lib.rs), which owns the vault format and is shared with the
+//! messy-cli command-line tool (bin/messy-cli.rs).
+//!
+//! Only what needs a running app lives here: watching the vault for
+//! outside edits, keeping ephemeral notes on schedule and switching
+//! between vaults.
+//!
+//! =======================================
+
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::http::ResponseBuilder;
 use tauri::{Manager, State};
-use uuid::Uuid;
 use chrono::Utc;
-use anyhow::{Result, Context};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Note {
-    id: String,
-    title: String,
-    #[serde(rename = "rawText")]
-    raw_text: Option<String>,
-    content: Option<serde_json::Value>,
-    #[serde(rename = "updatedAt")]
-    updated_at: String,
-    #[serde(rename = "createdAt")]
-    created_at: String,
-    sticky: bool,
-    ephemeral: bool,
-    archived: bool,
-    #[serde(rename = "type")]
-    note_type: String,
-    color: String,
-    #[serde(rename = "folderId", skip_serializing_if = "Option::is_none")]
-    folder_id: Option<String>,
-    #[serde(default)]
-    position: i32,
-}
+use messy_notes::attachments::Attachment;
+use messy_notes::canvas::{self, Canvas};
+use messy_notes::diff::DiffLine;
+use messy_notes::graph::{self, Edge, GraphMetadata};
+use messy_notes::history::{Revision, RevisionKind};
+use messy_notes::note_id::NoteId;
+use messy_notes::preview::Preview;
+use messy_notes::search::SearchHit;
+use messy_notes::trash::TrashEntry;
+use messy_notes::{fsck, lifecycle, obsidian, protocol, vaults};
+use messy_notes::{AppState, Backlink, ExpiringNote, Folder, LifecycleReport, NewNote, Note, NoteChanges, NoteLink, RenameReport, RetagReport, TagCount, UnlinkedMention};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Folder {
-    id: String,
-    name: String,
-    #[serde(rename = "parentId", skip_serializing_if = "Option::is_none")]
-    parent_id: Option<String>,
-    #[serde(rename = "createdAt")]
-    created_at: String,
-    #[serde(rename = "updatedAt")]
-    updated_at: String,
-    #[serde(default)]
-    expanded: bool,
-}
+mod watcher;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct GraphMetadata {
-    #[serde(default)]
-    nodes: serde_json::Value,
-    #[serde(default)]
-    edges: Vec<Edge>,
-}
+use watcher::VaultWatcher;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Edge {
-    id: String,
-    source: String,
-    target: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    label: Option<String>,
-}
+/// How often ephemeral notes are checked for expiry while the app runs
+const LIFECYCLE_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
-#[derive(Debug, Serialize, Deserialize)]
-struct CanvasData {
-    nodes: serde_json::Value,
-    edges: serde_json::Value,
-}
-
-struct AppState {
-    data_dir: PathBuf,
-}
-
-impl AppState {
-    /// Returns path to notes directory: ~/Documents/MessyNotes/notes/
-    fn notes_dir(&self) -> PathBuf {
-        self.data_dir.join("notes")
-    }
-
-    /// Returns path to attachments directory: ~/Documents/MessyNotes/attachments/
-    fn attachments_dir(&self) -> PathBuf {
-        self.data_dir.join("attachments")
-    }
-
-    /// Returns path to graph file: ~/Documents/MessyNotes/graph.json
-    fn graph_file(&self) -> PathBuf {
-        self.data_dir.join("graph.json")
-    }
+/// Watches the open vault for edits made by other programs; replaced
+/// whenever the app switches vaults
+struct WatcherSlot(Mutex<Option<VaultWatcher>>);
 
-    /// Returns path to folders file: ~/Documents/MessyNotes/folders.json
-    fn folders_file(&self) -> PathBuf {
-        self.data_dir.join("folders.json")
-    }
-
-    /// Returns path to canvas file: ~/Documents/MessyNotes/canvas/{note_id}.json
-    fn canvas_file(&self, note_id: &str) -> PathBuf {
-        self.data_dir.join("canvas").join(format!("{}.json", note_id))
-    }
-
-    /// Ensures all required directories exist
-    fn ensure_dirs(&self) -> Result<()> {
-        fs::create_dir_all(&self.data_dir)?;
-        fs::create_dir_all(self.notes_dir())?;
-        fs::create_dir_all(self.attachments_dir())?;
-        fs::create_dir_all(self.data_dir.join("canvas"))?;
-        Ok(())
-    }
-}
-
-// Initialize app data directory
 #[tauri::command]
 async fn init_app(app_handle: tauri::AppHandle) -> Result<String, String> {
     let state = app_handle.state::<AppState>();
-    state.ensure_dirs().map_err(|e| e.to_string())?;
+    messy_notes::prepare_vault(&state)?;
+    
+    // Ephemeral notes may have expired while the app was closed
+    apply_lifecycle(&app_handle)?;
     
     // Return the data directory path
-    Ok(state.data_dir.to_string_lossy().to_string())
+    Ok(state.data_dir().to_string_lossy().to_string())
 }
 
 // ==================== NOTE OPERATIONS ====================
@@ -145,191 +60,12 @@ async fn init_app(app_handle: tauri::AppHandle) -> Result<String, String> {
 
 #[tauri::command]
 async fn get_notes(state: State<'_, AppState>) -> Result<Vec<Note>, String> {
-    state.ensure_dirs().map_err(|e| e.to_string())?;
-    
-    let mut notes = Vec::new();
-    let notes_dir = state.notes_dir();
-    
-    if !notes_dir.exists() {
-        return Ok(notes);
-    }
-    
-    for entry in fs::read_dir(&notes_dir).map_err(|e| e.to_string())? {
-        let entry = entry.map_err(|e| e.to_string())?;
-        let path = entry.path();
-        
-        if path.extension().and_then(|s| s.to_str()) == Some("md") {
-            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                let id = stem.to_string();
-                let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-                
-                // Parse frontmatter (YAML between --- delimiters)
-                let (metadata, raw_text) = parse_markdown_with_frontmatter(&content);
-                
-                let title = metadata.get("title")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("Untitled")
-                    .to_string();
-                
-                // CRITICAL: Use stored content if available, otherwise reconstruct from rawText
-                let note_content = if let Some(stored_content) = metadata.get("content") {
-                    Some(stored_content.clone())
-                } else if !raw_text.is_empty() {
-                    // Fallback for old notes without stored content
-                    Some(serde_json::json!({
-                        "type": "doc",
-                        "content": [{
-                            "type": "paragraph",
-                            "content": [{
-                                "type": "text",
-                                "text": raw_text
-                            }]
-                        }]
-                    }))
-                } else {
-                    Some(serde_json::json!({
-                        "type": "doc",
-                        "content": []
-                    }))
-                };
-                
-                notes.push(Note {
-                    id,
-                    title,
-                    raw_text: Some(raw_text.clone()),
-                    content: note_content,
-                    updated_at: metadata.get("updatedAt")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or(&Utc::now().to_rfc3339())
-                        .to_string(),
-                    created_at: metadata.get("createdAt")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or(&Utc::now().to_rfc3339())
-                        .to_string(),
-                    sticky: metadata.get("sticky")
-                        .and_then(|v| v.as_bool())
-                        .unwrap_or(false),
-                    ephemeral: metadata.get("ephemeral")
-                        .and_then(|v| v.as_bool())
-                        .unwrap_or(true),
-                    archived: metadata.get("archived")
-                        .and_then(|v| v.as_bool())
-                        .unwrap_or(false),
-                    note_type: metadata.get("type")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("text")
-                        .to_string(),
-                    color: metadata.get("color")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("#ffffff")
-                        .to_string(),
-                    folder_id: metadata.get("folderId")
-                        .and_then(|v| v.as_str())
-                        .map(|s| s.to_string()),
-                    position: metadata.get("position")
-                        .and_then(|v| v.as_i64())
-                        .unwrap_or(0) as i32,
-                });
-            }
-        }
-    }
-    
-    // Sort by position first (ascending), then by updated_at (descending)
-    notes.sort_by(|a, b| {
-        // First compare folder_id (group by folder)
-        match (&a.folder_id, &b.folder_id) {
-            (Some(f1), Some(f2)) if f1 == f2 => {
-                // Same folder: sort by position, then by updated_at
-                match a.position.cmp(&b.position) {
-                    std::cmp::Ordering::Equal => b.updated_at.cmp(&a.updated_at),
-                    other => other,
-                }
-            },
-            (None, None) => {
-                // Both in root: sort by position, then by updated_at
-                match a.position.cmp(&b.position) {
-                    std::cmp::Ordering::Equal => b.updated_at.cmp(&a.updated_at),
-                    other => other,
-                }
-            },
-            (None, Some(_)) => std::cmp::Ordering::Less,
-            (Some(_), None) => std::cmp::Ordering::Greater,
-            (Some(f1), Some(f2)) => f1.cmp(f2),
-        }
-    });
-    
-    Ok(notes)
+    messy_notes::get_notes(&state)
 }
 
 #[tauri::command]
-async fn get_note(id: String, state: State<'_, AppState>) -> Result<Note, String> {
-    let path = state.notes_dir().join(format!("{}.md", id));
-    
-    if !path.exists() {
-        return Err("Note not found".to_string());
-    }
-    
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let (metadata, raw_text) = parse_markdown_with_frontmatter(&content);
-    
-    let title = metadata.get("title")
-        .and_then(|v| v.as_str())
-        .unwrap_or("Untitled")
-        .to_string();
-    
-    // CRITICAL: Use stored content if available, otherwise reconstruct from rawText
-    let note_content = if let Some(stored_content) = metadata.get("content") {
-        Some(stored_content.clone())
-    } else if !raw_text.is_empty() {
-        // Fallback for old notes without stored content
-        Some(serde_json::json!({
-            "type": "doc",
-            "content": [{
-                "type": "paragraph",
-                "content": [{
-                    "type": "text",
-                    "text": raw_text
-                }]
-            }]
-        }))
-    } else {
-        Some(serde_json::json!({
-            "type": "doc",
-            "content": []
-        }))
-    };
-    
-    Ok(Note {
-        id,
-        title,
-        raw_text: Some(raw_text),
-        content: note_content,
-        updated_at: metadata.get("updatedAt")
-            .and_then(|v| v.as_str())
-            .unwrap_or(&Utc::now().to_rfc3339())
-            .to_string(),
-        created_at: metadata.get("createdAt")
-            .and_then(|v| v.as_str())
-            .unwrap_or(&Utc::now().to_rfc3339())
-            .to_string(),
-        sticky: metadata.get("sticky").and_then(|v| v.as_bool()).unwrap_or(false),
-        ephemeral: metadata.get("ephemeral").and_then(|v| v.as_bool()).unwrap_or(true),
-        archived: metadata.get("archived").and_then(|v| v.as_bool()).unwrap_or(false),
-        note_type: metadata.get("type")
-            .and_then(|v| v.as_str())
-            .unwrap_or("text")
-            .to_string(),
-        color: metadata.get("color")
-            .and_then(|v| v.as_str())
-            .unwrap_or("#ffffff")
-            .to_string(),
-        folder_id: metadata.get("folderId")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string()),
-        position: metadata.get("position")
-            .and_then(|v| v.as_i64())
-            .unwrap_or(0) as i32,
-    })
+async fn get_note(id: NoteId, state: State<'_, AppState>) -> Result<Note, String> {
+    messy_notes::get_note(id, &state)
 }
 
 #[tauri::command]
@@ -342,48 +78,16 @@ async fn create_note(
     note_type: Option<String>,
     color: Option<String>,
     folder_id: Option<String>,
+    tags: Option<Vec<String>>,
     state: State<'_, AppState>,
 ) -> Result<Note, String> {
-    state.ensure_dirs().map_err(|e| e.to_string())?;
-    
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().to_rfc3339();
-    let title = title.unwrap_or_else(|| "Untitled Thought".to_string());
-    let raw_text = raw_text.unwrap_or_default();
-    
-    // Get max position in the target folder/root
-    let all_notes = get_notes(state.clone()).await?;
-    let max_position = all_notes
-        .iter()
-        .filter(|n| n.folder_id == folder_id)
-        .map(|n| n.position)
-        .max()
-        .unwrap_or(-1);
-    
-    let note = Note {
-        id: id.clone(),
-        title: title.clone(),
-        raw_text: Some(raw_text.clone()),
-        content,
-        updated_at: now.clone(),
-        created_at: now.clone(),
-        sticky: sticky.unwrap_or(false),
-        ephemeral: ephemeral.unwrap_or(true),
-        archived: false,
-        note_type: note_type.unwrap_or_else(|| "text".to_string()),
-        color: color.unwrap_or_else(|| "#ffffff".to_string()),
-        folder_id,
-        position: max_position + 1,
-    };
-    
-    save_note(&note, &state)?;
-    
-    Ok(note)
+    let new = NewNote { title, raw_text, content, sticky, ephemeral, note_type, color, folder_id, tags };
+    messy_notes::create_note(new, &state)
 }
 
 #[tauri::command]
 async fn update_note(
-    id: String,
+    id: NoteId,
     title: Option<String>,
     raw_text: Option<String>,
     content: Option<serde_json::Value>,
@@ -392,145 +96,133 @@ async fn update_note(
     archived: Option<bool>,
     folder_id: Option<Option<String>>,
     position: Option<i32>,
+    aliases: Option<Vec<String>>,
+    tags: Option<Vec<String>>,
     state: State<'_, AppState>,
 ) -> Result<Note, String> {
-    let mut note = get_note(id.clone(), state.clone()).await?;
-    
-    if let Some(t) = title {
-        note.title = t;
-    }
-    if let Some(rt) = raw_text {
-        note.raw_text = Some(rt);
-    }
-    if let Some(c) = content {
-        note.content = Some(c);
-    }
-    if let Some(s) = sticky {
-        note.sticky = s;
-    }
-    if let Some(e) = ephemeral {
-        note.ephemeral = e;
-    }
-    if let Some(a) = archived {
-        note.archived = a;
-    }
-    if let Some(fid) = folder_id {
-        note.folder_id = fid;
-    }
-    if let Some(p) = position {
-        note.position = p;
-    }
-
-    note.updated_at = Utc::now().to_rfc3339();
-    
-    save_note(&note, &state)?;
-    
-    Ok(note)
+    let changes = NoteChanges { title, raw_text, content, sticky, ephemeral, archived, folder_id, position, aliases, tags };
+    messy_notes::update_note(id, changes, &state)
 }
 
 #[tauri::command]
 async fn reorder_notes(
-    note_id: String,
+    note_id: NoteId,
     target_folder_id: Option<String>,
     new_position: i32,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    // Get all notes
-    let mut all_notes = get_notes(state.clone()).await?;
-    
-    // Find the note being moved
-    let note_index = all_notes.iter().position(|n| n.id == note_id)
-        .ok_or("Note not found")?;
-    
-    let mut moved_note = all_notes.remove(note_index);
-    let old_folder_id = moved_note.folder_id.clone();
-    
-    // Update folder if changed
-    moved_note.folder_id = target_folder_id.clone();
-    
-    // Filter notes in the target folder
-    let mut folder_notes: Vec<Note> = all_notes
-        .iter()
-        .filter(|n| n.folder_id == target_folder_id)
-        .cloned()
-        .collect();
-    
-    // Insert at new position
-    let insert_pos = new_position.max(0).min(folder_notes.len() as i32) as usize;
-    folder_notes.insert(insert_pos, moved_note.clone());
-    
-    // Renumber all notes in target folder
-    for (idx, note) in folder_notes.iter_mut().enumerate() {
-        note.position = idx as i32;
-        save_note(note, &state)?;
-    }
-    
-    // If folder changed, renumber old folder too
-    if old_folder_id != target_folder_id {
-        let mut old_folder_notes: Vec<Note> = all_notes
-            .iter()
-            .filter(|n| n.folder_id == old_folder_id && n.id != note_id)
-            .cloned()
-            .collect();
-        
-        for (idx, note) in old_folder_notes.iter_mut().enumerate() {
-            note.position = idx as i32;
-            save_note(note, &state)?;
-        }
-    }
-    
-    Ok(())
+    messy_notes::reorder_notes(note_id, target_folder_id, new_position, &state)
 }
 
 #[tauri::command]
-async fn delete_note(id: String, state: State<'_, AppState>) -> Result<(), String> {
-    let path = state.notes_dir().join(format!("{}.md", id));
-    
-    if path.exists() {
-        fs::remove_file(&path).map_err(|e| e.to_string())?;
-    }
-    
-    // Also clean up from graph
-    if let Ok(mut graph) = get_graph(state.clone()).await {
-        // Remove edges connected to this node
-        graph.edges.retain(|e| e.source != id && e.target != id);
-        
-        // Remove node metadata
-        if let Some(obj) = graph.nodes.as_object_mut() {
-            obj.remove(&id);
-        }
-        
-        save_graph(&graph, &state)?;
-    }
-    
-    Ok(())
+async fn delete_note(id: NoteId, state: State<'_, AppState>) -> Result<(), String> {
+    messy_notes::delete_note(id, &state)
 }
 
 #[tauri::command]
 async fn delete_all_notes(state: State<'_, AppState>) -> Result<usize, String> {
-    let notes_dir = state.notes_dir();
-    let mut count = 0;
-    
-    if notes_dir.exists() {
-        for entry in fs::read_dir(&notes_dir).map_err(|e| e.to_string())? {
-            let entry = entry.map_err(|e| e.to_string())?;
-            let path = entry.path();
-            
-            if path.extension().and_then(|s| s.to_str()) == Some("md") {
-                fs::remove_file(&path).map_err(|e| e.to_string())?;
-                count += 1;
-            }
-        }
-    }
-    
-    // Clear graph
-    let graph = GraphMetadata {
-        nodes: serde_json::json!({}),
-        edges: vec![],
-    };
-    save_graph(&graph, &state)?;
-    
-    Ok(count)
+    messy_notes::delete_all_notes(&state)
+}
+
+// ==================== TRASH OPERATIONS ====================
+// Deleted notes live in: ~/Documents/MessyNotes/.trash/{note_id}/
+
+#[tauri::command]
+async fn list_trash(state: State<'_, AppState>) -> Result<Vec<TrashEntry>, String> {
+    messy_notes::list_trash(&state)
+}
+
+#[tauri::command]
+async fn restore_note(id: NoteId, state: State<'_, AppState>) -> Result<Note, String> {
+    messy_notes::restore_note(id, &state)
+}
+
+#[tauri::command]
+async fn empty_trash(state: State<'_, AppState>) -> Result<usize, String> {
+    messy_notes::empty_trash(&state)
+}
+
+#[tauri::command]
+async fn set_trash_retention(days: u32, state: State<'_, AppState>) -> Result<usize, String> {
+    messy_notes::set_trash_retention(days, &state)
+}
+
+// ==================== EPHEMERAL NOTES ====================
+
+#[tauri::command]
+async fn get_ephemeral_policy(state: State<'_, AppState>) -> Result<lifecycle::Policy, String> {
+    messy_notes::get_ephemeral_policy(&state)
+}
+
+/// Changes when ephemeral notes expire or become permanent and applies
+/// the new rules right away
+#[tauri::command]
+async fn set_ephemeral_policy(
+    policy: lifecycle::Policy,
+    app_handle: tauri::AppHandle,
+) -> Result<LifecycleReport, String> {
+    messy_notes::set_ephemeral_policy(policy, &app_handle.state::<AppState>())?;
+    apply_lifecycle(&app_handle)
+}
+
+#[tauri::command]
+async fn get_expiring_notes(within_days: Option<u32>, state: State<'_, AppState>) -> Result<Vec<ExpiringNote>, String> {
+    messy_notes::get_expiring_notes(within_days, &state)
+}
+
+// ==================== SEARCH ====================
+// The index is stored as: ~/Documents/MessyNotes/.search/index.json
+
+#[tauri::command]
+async fn search_notes(
+    query: String,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchHit>, String> {
+    messy_notes::search_notes(query, limit, &state)
+}
+
+// ==================== REVISION HISTORY ====================
+// Revisions are stored as: ~/Documents/MessyNotes/.history/{note_id}/
+
+#[tauri::command]
+async fn list_revisions(
+    note_id: NoteId,
+    kind: Option<RevisionKind>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Revision>, String> {
+    messy_notes::list_revisions(note_id, kind, &state)
+}
+
+#[tauri::command]
+async fn get_revision(
+    note_id: NoteId,
+    kind: RevisionKind,
+    revision_id: String,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    messy_notes::get_revision(note_id, kind, revision_id, &state)
+}
+
+#[tauri::command]
+async fn diff_revisions(
+    note_id: NoteId,
+    kind: RevisionKind,
+    from: String,
+    to: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DiffLine>, String> {
+    messy_notes::diff_revisions(note_id, kind, from, to, &state)
+}
+
+#[tauri::command]
+async fn restore_revision(
+    note_id: NoteId,
+    kind: RevisionKind,
+    revision_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    messy_notes::restore_revision(note_id, kind, revision_id, &state)
 }
 
 // ==================== FOLDER OPERATIONS ====================
@@ -538,17 +230,7 @@ async fn delete_all_notes(state: State<'_, AppState>) -> Result<usize, String> {
 
 #[tauri::command]
 async fn get_folders(state: State<'_, AppState>) -> Result<Vec<Folder>, String> {
-    let path = state.folders_file();
-    
-    if !path.exists() {
-        return Ok(vec![]);
-    }
-    
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let folders: Vec<Folder> = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse folders.json: {}", e))?;
-    
-    Ok(folders)
+    messy_notes::get_folders(&state)
 }
 
 #[tauri::command]
@@ -557,26 +239,7 @@ async fn create_folder(
     parent_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Folder, String> {
-    state.ensure_dirs().map_err(|e| e.to_string())?;
-    
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().to_rfc3339();
-    
-    let folder = Folder {
-        id: id.clone(),
-        name,
-        parent_id,
-        created_at: now.clone(),
-        updated_at: now,
-        expanded: true,
-    };
-    
-    let mut folders = get_folders(state.clone()).await?;
-    folders.push(folder.clone());
-    
-    save_folders(&folders, &state)?;
-    
-    Ok(folder)
+    messy_notes::create_folder(name, parent_id, &state)
 }
 
 #[tauri::command]
@@ -587,67 +250,12 @@ async fn update_folder(
     expanded: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<Folder, String> {
-    let mut folders = get_folders(state.clone()).await?;
-    
-    let folder = folders.iter_mut()
-        .find(|f| f.id == id)
-        .ok_or("Folder not found")?;
-    
-    if let Some(n) = name {
-        folder.name = n;
-    }
-    if parent_id.is_some() {
-        folder.parent_id = parent_id;
-    }
-    if let Some(e) = expanded {
-        folder.expanded = e;
-    }
-    
-    folder.updated_at = Utc::now().to_rfc3339();
-    
-    let updated_folder = folder.clone();
-    save_folders(&folders, &state)?;
-    
-    Ok(updated_folder)
+    messy_notes::update_folder(id, name, parent_id, expanded, &state)
 }
 
 #[tauri::command]
 async fn delete_folder(id: String, state: State<'_, AppState>) -> Result<(), String> {
-    let mut folders = get_folders(state.clone()).await?;
-    
-    // Move all notes in this folder to root (null folderId)
-    let notes = get_notes(state.clone()).await?;
-    for note in notes {
-        if note.folder_id.as_ref() == Some(&id) {
-            update_note(
-                note.id,
-                None,
-                None,
-                None,
-                None,
-                None,
-                None,
-                Some(None),
-                None,
-                state.clone()
-            ).await?;
-        }
-    }
-    
-    // Remove the folder
-    folders.retain(|f| f.id != id);
-    
-    // Move child folders to parent
-    let parent_id = folders.iter().find(|f| f.id == id).and_then(|f| f.parent_id.clone());
-    for folder in folders.iter_mut() {
-        if folder.parent_id.as_ref() == Some(&id) {
-            folder.parent_id = parent_id.clone();
-        }
-    }
-    
-    save_folders(&folders, &state)?;
-    
-    Ok(())
+    messy_notes::delete_folder(id, &state)
 }
 
 // ==================== GRAPH OPERATIONS ====================
@@ -655,20 +263,7 @@ async fn delete_folder(id: String, state: State<'_, AppState>) -> Result<(), Str
 
 #[tauri::command]
 async fn get_graph(state: State<'_, AppState>) -> Result<GraphMetadata, String> {
-    let path = state.graph_file();
-    
-    if !path.exists() {
-        return Ok(GraphMetadata {
-            nodes: serde_json::json!({}),
-            edges: vec![],
-        });
-    }
-    
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let graph: GraphMetadata = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse graph.json: {}", e))?;
-    
-    Ok(graph)
+    messy_notes::get_graph(&state)
 }
 
 #[tauri::command]
@@ -676,136 +271,308 @@ async fn save_graph_data(
     nodes: serde_json::Value,
     edges: Vec<Edge>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let graph = GraphMetadata { nodes, edges };
-    save_graph(&graph, &state)
+) -> Result<graph::Pruned, String> {
+    messy_notes::save_graph_data(nodes, edges, &state)
+}
+
+#[tauri::command]
+async fn get_links(note_id: Option<NoteId>, state: State<'_, AppState>) -> Result<Vec<NoteLink>, String> {
+    messy_notes::get_links(note_id, &state)
+}
+
+#[tauri::command]
+async fn rename_note(
+    note_id: NoteId,
+    title: String,
+    update_links: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<RenameReport, String> {
+    messy_notes::rename_note(note_id, title, update_links, &state)
+}
+
+#[tauri::command]
+async fn get_backlinks(note_id: NoteId, state: State<'_, AppState>) -> Result<Vec<Backlink>, String> {
+    messy_notes::get_backlinks(note_id, &state)
+}
+
+#[tauri::command]
+async fn get_unlinked_mentions(note_id: NoteId, state: State<'_, AppState>) -> Result<Vec<UnlinkedMention>, String> {
+    messy_notes::get_unlinked_mentions(note_id, &state)
+}
+
+// ==================== TAG OPERATIONS ====================
+// Tags live in each note's frontmatter and body; there is no separate file
+
+#[tauri::command]
+async fn list_tags(state: State<'_, AppState>) -> Result<Vec<TagCount>, String> {
+    messy_notes::list_tags(&state)
+}
+
+#[tauri::command]
+async fn get_notes_by_tag(tag: String, state: State<'_, AppState>) -> Result<Vec<Note>, String> {
+    messy_notes::get_notes_by_tag(tag, &state)
+}
+
+#[tauri::command]
+async fn rename_tag(from: String, to: String, state: State<'_, AppState>) -> Result<RetagReport, String> {
+    messy_notes::rename_tag(from, to, &state)
+}
+
+#[tauri::command]
+async fn merge_tags(sources: Vec<String>, target: String, state: State<'_, AppState>) -> Result<RetagReport, String> {
+    messy_notes::merge_tags(sources, target, &state)
 }
 
 // ==================== CANVAS OPERATIONS ====================
 // Canvas is stored as: ~/Documents/MessyNotes/canvas/{note_id}.json
 
 #[tauri::command]
-async fn get_canvas(note_id: String, state: State<'_, AppState>) -> Result<CanvasData, String> {
-    let path = state.canvas_file(&note_id);
-    
-    if !path.exists() {
-        return Ok(CanvasData {
-            nodes: serde_json::json!([]),
-            edges: serde_json::json!([]),
-        });
-    }
-    
-    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
-    let canvas: CanvasData = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse canvas: {}", e))?;
-    
-    Ok(canvas)
+async fn get_canvas(note_id: NoteId, state: State<'_, AppState>) -> Result<Canvas, String> {
+    messy_notes::get_canvas(note_id, &state)
 }
 
 #[tauri::command]
 async fn save_canvas_data(
-    note_id: String,
+    note_id: NoteId,
     nodes: serde_json::Value,
     edges: serde_json::Value,
+    repair: Option<bool>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let canvas = CanvasData { nodes, edges };
-    let json = serde_json::to_string_pretty(&canvas)
-        .map_err(|e| format!("Failed to serialize canvas: {}", e))?;
-    
-    let path = state.canvas_file(&note_id);
-    fs::write(&path, json).map_err(|e| e.to_string())?;
-    
-    Ok(())
+) -> Result<Vec<canvas::Problem>, String> {
+    messy_notes::save_canvas_data(note_id, nodes, edges, repair, &state)
 }
 
-// ==================== HELPER FUNCTIONS ====================
+#[tauri::command]
+async fn validate_canvas(nodes: serde_json::Value, edges: serde_json::Value) -> Result<Vec<canvas::Problem>, String> {
+    messy_notes::validate_canvas(nodes, edges)
+}
 
-/// Saves a note to disk as a .md file with YAML frontmatter
-/// Stores BOTH the TipTap content structure AND rawText for compatibility
-fn save_note(note: &Note, state: &AppState) -> Result<(), String> {
-    let mut metadata = serde_json::json!({
-        "title": note.title,
-        "updatedAt": note.updated_at,
-        "createdAt": note.created_at,
-        "sticky": note.sticky,
-        "ephemeral": note.ephemeral,
-        "archived": note.archived,
-        "type": note.note_type,
-        "color": note.color,
-        "content": note.content,
-        "position": note.position,
-    });
-    
-    if let Some(ref folder_id) = note.folder_id {
-        metadata["folderId"] = serde_json::json!(folder_id);
+#[tauri::command]
+async fn repair_canvas(note_id: NoteId, state: State<'_, AppState>) -> Result<Vec<canvas::Problem>, String> {
+    messy_notes::repair_canvas(note_id, &state)
+}
+
+// ==================== ATTACHMENTS ====================
+// See attachments.rs
+
+#[tauri::command]
+async fn import_attachment(path: String, state: State<'_, AppState>) -> Result<Attachment, String> {
+    messy_notes::import_attachment(path, &state)
+}
+
+#[tauri::command]
+async fn import_attachment_data(name: String, data: String, state: State<'_, AppState>) -> Result<Attachment, String> {
+    messy_notes::import_attachment_data(name, data, &state)
+}
+
+#[tauri::command]
+async fn get_attachment_preview(hash: String, max_size: Option<u32>, state: State<'_, AppState>) -> Result<Preview, String> {
+    messy_notes::get_attachment_preview(hash, max_size, &state)
+}
+
+#[tauri::command]
+async fn list_attachments(state: State<'_, AppState>) -> Result<Vec<Attachment>, String> {
+    messy_notes::list_attachments(&state)
+}
+
+#[tauri::command]
+async fn delete_attachment(hash: String, state: State<'_, AppState>) -> Result<(), String> {
+    messy_notes::delete_attachment(hash, &state)
+}
+
+#[tauri::command]
+async fn collect_attachment_garbage(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    messy_notes::collect_attachment_garbage(&state)
+}
+
+// ==================== IMPORT ====================
+// See obsidian.rs
+
+#[tauri::command]
+async fn import_obsidian_vault(path: String, dry_run: Option<bool>, state: State<'_, AppState>) -> Result<obsidian::Report, String> {
+    messy_notes::import_obsidian_vault(path, dry_run, &state)
+}
+
+// ==================== VAULT CHECK ====================
+// See fsck.rs
+
+#[tauri::command]
+async fn check_vault(state: State<'_, AppState>) -> Result<Vec<fsck::Issue>, String> {
+    messy_notes::check_vault(&state)
+}
+
+#[tauri::command]
+async fn repair_vault(kinds: Option<Vec<fsck::IssueKind>>, state: State<'_, AppState>) -> Result<fsck::Repair, String> {
+    messy_notes::repair_vault(kinds, &state)
+}
+
+// ==================== VAULTS ====================
+// Known vaults are listed in vaults.json in the app's config dir
+
+#[tauri::command]
+async fn list_vaults(state: State<'_, AppState>) -> Result<Vec<vaults::VaultInfo>, String> {
+    let settings = vaults::load(&vaults::settings_file()?)?;
+    Ok(settings.list(&state.data_dir()))
+}
+
+/// Adds an existing folder to the known vaults and switches to it
+#[tauri::command]
+async fn open_vault(path: String, app_handle: tauri::AppHandle) -> Result<String, String> {
+    let path = PathBuf::from(path);
+    if !path.is_dir() {
+        return Err(format!("No folder at {}", path.display()));
+    }
+    // Opening isn't a way to turn any folder (like ~) into a vault
+    if !vaults::is_vault(&path) && !vaults::is_empty(&path) {
+        return Err(format!(
+            "{} is not a vault (it has no notes/ folder or vault.json); create a new vault to start one",
+            path.display()
+        ));
     }
     
-    let frontmatter = serde_json::to_string_pretty(&metadata)
-        .map_err(|e| e.to_string())?;
-    
-    let content = format!(
-        "---\n{}\n---\n\n{}",
-        frontmatter,
-        note.raw_text.as_deref().unwrap_or("")
-    );
-    
-    let path = state.notes_dir().join(format!("{}.md", note.id));
-    fs::write(&path, content).map_err(|e| e.to_string())?;
-    
-    Ok(())
+    switch_to_vault(path, &app_handle).await
 }
 
-/// Saves folders to disk as JSON
-fn save_folders(folders: &[Folder], state: &AppState) -> Result<(), String> {
-    let json = serde_json::to_string_pretty(folders)
-        .map_err(|e| format!("Failed to serialize folders: {}", e))?;
+/// Makes a new, empty vault and switches to it
+#[tauri::command]
+async fn create_vault(path: String, app_handle: tauri::AppHandle) -> Result<String, String> {
+    let path = PathBuf::from(path);
+    if path.exists() && !vaults::is_empty(&path) {
+        return Err(format!("{} already exists and isn't an empty folder", path.display()));
+    }
     
-    let path = state.folders_file();
-    fs::write(&path, json).map_err(|e| e.to_string())?;
+    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+    switch_to_vault(path, &app_handle).await
+}
+
+/// Switches to one of the known vaults
+#[tauri::command]
+async fn switch_vault(path: String, app_handle: tauri::AppHandle) -> Result<String, String> {
+    let settings = vaults::load(&vaults::settings_file()?)?;
+    let vault = settings.find(Path::new(&path))
+        .ok_or_else(|| format!("{} is not a known vault", path))?;
+    if !vault.path.is_dir() {
+        return Err(format!("Vault \"{}\" is not available at {}", vault.name, vault.path.display()));
+    }
     
-    Ok(())
+    switch_to_vault(vault.path.clone(), &app_handle).await
 }
 
-/// Saves graph data to disk as JSON
-fn save_graph(graph: &GraphMetadata, state: &AppState) -> Result<(), String> {
-    let json = serde_json::to_string_pretty(graph)
-        .map_err(|e| format!("Failed to serialize graph: {}", e))?;
+// ==================== HELPER FUNCTIONS ====================
+
+/// run_lifecycle, telling the webview about every note it changed
+fn apply_lifecycle(app: &tauri::AppHandle) -> Result<LifecycleReport, String> {
+    let state = app.state::<AppState>();
+    let report = messy_notes::run_lifecycle(&state)?;
     
-    let path = state.graph_file();
-    fs::write(&path, json).map_err(|e| e.to_string())?;
+    for note in report.promoted.iter().chain(&report.archived) {
+        app.emit_all("note-changed", note).map_err(|e| e.to_string())?;
+    }
+    for id in &report.trashed {
+        app.emit_all("note-deleted", serde_json::json!({ "id": id })).map_err(|e| e.to_string())?;
+    }
     
+    Ok(report)
+}
+
+/// Starts watching the open vault for outside edits, replacing any
+/// previous watcher
+fn start_watcher(app: &tauri::AppHandle) -> Result<(), String> {
+    let vault_watcher = watcher::start(app.clone()).map_err(|e| e.to_string())?;
+    let slot = app.state::<WatcherSlot>();
+    let mut slot = slot.0.lock().map_err(|e| e.to_string())?;
+    *slot = Some(vault_watcher);
     Ok(())
 }
 
-/// Parses a markdown file with YAML frontmatter
-fn parse_markdown_with_frontmatter(content: &str) -> (serde_json::Value, String) {
-    let parts: Vec<&str> = content.split("---").collect();
+/// Stops watching the open vault
+fn stop_watcher(app: &tauri::AppHandle) {
+    if let Ok(mut slot) = app.state::<WatcherSlot>().0.lock() {
+        *slot = None;
+    }
+}
+
+/// Points the app at another vault: the old one's caches and watcher are
+/// dropped and the new one gets the same startup pass as init_app. If that
+/// fails the app stays on the old vault. The webview is told through a
+/// vault-changed event carrying the new path.
+async fn switch_to_vault(path: PathBuf, app_handle: &tauri::AppHandle) -> Result<String, String> {
+    let path = vaults::canonical(&path);
+    let state = app_handle.state::<AppState>();
+    let previous = state.data_dir();
     
-    if parts.len() >= 3 && parts[0].trim().is_empty() {
-        // Has frontmatter
-        let metadata: serde_json::Value = serde_json::from_str(parts[1].trim())
-            .unwrap_or(serde_json::json!({}));
-        let text = parts[2..].join("---").trim().to_string();
-        (metadata, text)
-    } else {
-        // No frontmatter
-        (serde_json::json!({}), content.to_string())
+    stop_watcher(app_handle);
+    state.set_data_dir(path.clone())?;
+    let opened = init_app(app_handle.clone()).await;
+    if opened.is_err() {
+        state.set_data_dir(previous)?;
+    }
+    if let Err(e) = start_watcher(app_handle) {
+        eprintln!("Not watching the vault for outside changes: {}", e);
     }
+    let data_dir = opened.map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    
+    let settings_file = vaults::settings_file()?;
+    let mut settings = vaults::load(&settings_file)?;
+    settings.remember(&path, &Utc::now().to_rfc3339());
+    vaults::save(&settings_file, &settings)?;
+    
+    app_handle.emit_all("vault-changed", &data_dir).map_err(|e| e.to_string())?;
+    Ok(data_dir)
 }
 
 fn main() {
     tauri::Builder::default()
         .setup(|app| {
-            let document_dir = tauri::api::path::document_dir()
-                .context("Failed to get documents directory")?;
+            let data_dir = vaults::startup_dir()?;
             
-            let data_dir = document_dir.join("MessyNotes");
+            // List the vault, so a first run knows about the default one
+            let remembered = vaults::settings_file().and_then(|file| {
+                let mut settings = vaults::load(&file)?;
+                settings.remember(&data_dir, &Utc::now().to_rfc3339());
+                vaults::save(&file, &settings)
+            });
+            if let Err(e) = remembered {
+                eprintln!("Not saving the vault list: {}", e);
+            }
+            
+            app.manage(AppState::new(data_dir));
+            app.manage(WatcherSlot(Mutex::new(None)));
+            
+            // Pick up edits made to the vault by other programs. The app
+            // still works without it, just like before the watcher existed.
+            let watching = app.state::<AppState>().ensure_dirs()
+                .map_err(|e| e.to_string())
+                .and_then(|_| start_watcher(&app.handle()));
+            if let Err(e) = watching {
+                eprintln!("Not watching the vault for outside changes: {}", e);
+            }
             
-            app.manage(AppState { data_dir });
+            // init_app runs the lifecycle at startup; this keeps it going
+            let handle = app.handle();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(LIFECYCLE_INTERVAL);
+                if let Err(e) = apply_lifecycle(&handle) {
+                    eprintln!("Ephemeral note lifecycle failed: {}", e);
+                }
+            });
             
             Ok(())
         })
+        // messy://attachment/{hash}, see protocol.rs
+        .register_uri_scheme_protocol(protocol::SCHEME, |app, request| {
+            let range = request.headers().get("range").and_then(|value| value.to_str().ok());
+            let response = match app.try_state::<AppState>() {
+                Some(state) => protocol::serve(request.uri(), range, &*state.store()),
+                None => return ResponseBuilder::new().status(503u16).body(Vec::new()),
+            };
+            
+            let mut builder = ResponseBuilder::new().status(response.status);
+            for (name, value) in response.headers {
+                builder = builder.header(name, value);
+            }
+            builder.body(response.body)
+        })
         .invoke_handler(tauri::generate_handler![
             init_app,
             get_notes,
@@ -821,8 +588,43 @@ fn main() {
             delete_folder,
             get_graph,
             save_graph_data,
+            get_links,
+            rename_note,
+            get_backlinks,
+            get_unlinked_mentions,
+            list_tags,
+            get_notes_by_tag,
+            rename_tag,
+            merge_tags,
             get_canvas,
             save_canvas_data,
+            validate_canvas,
+            repair_canvas,
+            import_attachment,
+            import_attachment_data,
+            get_attachment_preview,
+            list_attachments,
+            delete_attachment,
+            collect_attachment_garbage,
+            import_obsidian_vault,
+            check_vault,
+            repair_vault,
+            list_vaults,
+            open_vault,
+            create_vault,
+            switch_vault,
+            list_trash,
+            restore_note,
+            empty_trash,
+            set_trash_retention,
+            get_ephemeral_policy,
+            set_ephemeral_policy,
+            get_expiring_notes,
+            search_notes,
+            list_revisions,
+            get_revision,
+            diff_revisions,
+            restore_revision,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,891 @@
+//! ====== TIPTAP <-> MARKDOWN ======
+//!
+//! The body of a note file is CommonMark and is the single source of truth
+//! for its content. The editor works on TipTap JSON, so every save converts
+//! the document to Markdown (`from_doc`) and every load converts it back
+//! (`to_doc`).
+//!
+//! Nodes (StarterKit): paragraph, heading, bulletList, orderedList,
+//! listItem, blockquote, codeBlock, horizontalRule, hardBreak.
+//!
+//! Marks:
+//!   bold **x**   italic *x*   strike ~~x~~   code `x`
+//!   underline    <u>x</u>
+//!   highlight    <mark>x</mark> / <mark style="background-color: #hex">x</mark>
+//!   textStyle    <span style="color: #hex; font-family: Name">x</span>
+//!
+//! Conventions that keep the round trip stable:
+//!   - a hardBreak is a plain newline inside the paragraph (as Obsidian
+//!     displays it); any single newline in the Markdown reads back as one
+//!   - an empty paragraph is written as `&nbsp;` so blank lines survive
+//!   - Markdown constructs the editor has no node for (links, images, raw
+//!     HTML blocks) are kept as literal text rather than dropped, and
+//!     links, images and autolinks in text are written back unescaped
+//!
+//! =================================
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use serde_json::{json, Map, Value};
+use std::ops::Range;
+
+const NBSP: &str = "\u{a0}";
+
+// ==================== TIPTAP -> MARKDOWN ====================
+
+/// Converts a TipTap document to Markdown
+pub fn from_doc(doc: &Value) -> String {
+    let mut out = write_blocks(children(doc));
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+fn children(node: &Value) -> &[Value] {
+    node.get("content")
+        .and_then(|c| c.as_array())
+        .map(|c| c.as_slice())
+        .unwrap_or(&[])
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(|t| t.as_str()).unwrap_or("")
+}
+
+fn attr<'a>(node: &'a Value, name: &str) -> Option<&'a Value> {
+    node.get("attrs").and_then(|a| a.get(name)).filter(|v| !v.is_null())
+}
+
+fn write_blocks(nodes: &[Value]) -> String {
+    nodes.iter()
+        .filter_map(write_block)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn write_block(node: &Value) -> Option<String> {
+    match node_type(node) {
+        "paragraph" => {
+            let text = write_inline(children(node), false);
+            Some(if text.is_empty() { "&nbsp;".to_string() } else { text })
+        }
+        "heading" => {
+            let level = attr(node, "level").and_then(|l| l.as_u64()).unwrap_or(1).clamp(1, 6);
+            let text = write_inline(children(node), true);
+            Some(format!("{} {}", "#".repeat(level as usize), text).trim_end().to_string())
+        }
+        "blockquote" => {
+            let inner = write_blocks(children(node));
+            Some(prefix_lines(&inner, "> ", "> "))
+        }
+        "bulletList" => Some(write_list(node, |_| "- ".to_string())),
+        "orderedList" => {
+            let start = attr(node, "start").and_then(|s| s.as_u64()).unwrap_or(1);
+            Some(write_list(node, |i| format!("{}. ", start + i as u64)))
+        }
+        "codeBlock" => {
+            let code: String = children(node).iter()
+                .filter_map(|n| n.get("text").and_then(|t| t.as_str()))
+                .collect();
+            let fence = "`".repeat(longest_run(&code, '`').max(2) + 1);
+            let language = attr(node, "language").and_then(|l| l.as_str()).unwrap_or("");
+            Some(format!("{}{}\n{}\n{}", fence, language, code, fence))
+        }
+        "horizontalRule" => Some("---".to_string()),
+        // Unknown containers: keep whatever text they hold
+        _ if !children(node).is_empty() => Some(write_blocks(children(node))),
+        _ => None,
+    }
+}
+
+fn write_list(node: &Value, marker: impl Fn(usize) -> String) -> String {
+    children(node).iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let marker = marker(i);
+            let indent = " ".repeat(marker.len());
+            prefix_lines(&write_list_item(item), &marker, &indent)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn write_list_item(item: &Value) -> String {
+    let mut out = String::new();
+    let mut previous: Option<&str> = None;
+
+    for child in children(item) {
+        if let Some(block) = write_block(child) {
+            let interrupts = match node_type(child) {
+                "paragraph" => true,
+                // Only a list starting at 1 may interrupt a paragraph
+                "orderedList" => attr(child, "start").and_then(|s| s.as_u64()).unwrap_or(1) != 1,
+                _ => false,
+            };
+            match previous {
+                // Otherwise these would read back as part of the paragraph above
+                Some("paragraph") if interrupts => out.push_str("\n\n"),
+                Some(_) => out.push('\n'),
+                None => {}
+            }
+            out.push_str(&block);
+            previous = Some(node_type(child));
+        }
+    }
+
+    out
+}
+
+/// Prefixes the first line with `first` and every following line with `rest`
+fn prefix_lines(text: &str, first: &str, rest: &str) -> String {
+    text.split('\n')
+        .enumerate()
+        .map(|(i, line)| {
+            let prefix = if i == 0 { first } else { rest };
+            if line.is_empty() { prefix.trim_end().to_string() } else { format!("{}{}", prefix, line) }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn longest_run(text: &str, ch: char) -> usize {
+    let (mut longest, mut current) = (0, 0);
+    for c in text.chars() {
+        current = if c == ch { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+    longest
+}
+
+/// Nesting order of marks, outermost first. `code` is handled separately
+/// because nothing can nest inside a code span.
+fn mark_rank(mark: &Value) -> Option<u8> {
+    match node_type(mark) {
+        "textStyle" => Some(0),
+        "highlight" => Some(1),
+        "underline" => Some(2),
+        "strike" => Some(3),
+        "bold" => Some(4),
+        "italic" => Some(5),
+        _ => None,
+    }
+}
+
+fn open_mark(mark: &Value) -> String {
+    match node_type(mark) {
+        "bold" => "**".to_string(),
+        "italic" => "*".to_string(),
+        "strike" => "~~".to_string(),
+        "underline" => "<u>".to_string(),
+        "highlight" => match attr(mark, "color").and_then(|c| c.as_str()) {
+            Some(color) => format!("<mark style=\"background-color: {}\">", escape_attr(color)),
+            None => "<mark>".to_string(),
+        },
+        "textStyle" => {
+            let mut style = Vec::new();
+            if let Some(color) = attr(mark, "color").and_then(|c| c.as_str()) {
+                style.push(format!("color: {}", color));
+            }
+            if let Some(font) = attr(mark, "fontFamily").and_then(|f| f.as_str()) {
+                style.push(format!("font-family: {}", font));
+            }
+            format!("<span style=\"{}\">", escape_attr(&style.join("; ")))
+        }
+        _ => String::new(),
+    }
+}
+
+fn close_mark(mark: &Value) -> &'static str {
+    match node_type(mark) {
+        "bold" => "**",
+        "italic" => "*",
+        "strike" => "~~",
+        "underline" => "</u>",
+        "highlight" => "</mark>",
+        "textStyle" => "</span>",
+        _ => "",
+    }
+}
+
+/// A textStyle mark with neither color nor font is a no-op in the editor
+fn is_meaningful(mark: &Value) -> bool {
+    node_type(mark) != "textStyle" || attr(mark, "color").is_some() || attr(mark, "fontFamily").is_some()
+}
+
+fn write_inline(nodes: &[Value], single_line: bool) -> String {
+    let mut out = String::new();
+    let mut open: Vec<&Value> = Vec::new();
+    // Trailing whitespace is held back so it can land outside a closing
+    // delimiter: `**bold** next`, never `**bold **next`
+    let mut pending = String::new();
+    let mut line_start = true;
+
+    let close_from = |out: &mut String, open: &mut Vec<&Value>, keep: usize| {
+        while open.len() > keep {
+            out.push_str(close_mark(open.pop().unwrap()));
+        }
+    };
+
+    for node in nodes {
+        match node_type(node) {
+            "text" => {
+                let text = node.get("text").and_then(|t| t.as_str()).unwrap_or("");
+                let all_marks: Vec<&Value> = node.get("marks")
+                    .and_then(|m| m.as_array())
+                    .map(|m| m.iter().collect())
+                    .unwrap_or_default();
+                let is_code = all_marks.iter().any(|m| node_type(m) == "code");
+                let mut marks: Vec<&Value> = all_marks.into_iter()
+                    .filter(|m| mark_rank(m).is_some() && is_meaningful(m))
+                    .collect();
+                marks.sort_by_key(|m| mark_rank(m));
+
+                let keep = open.iter().zip(&marks).take_while(|(a, b)| a == b).count();
+                let core = text.trim();
+
+                if core.is_empty() {
+                    // Whitespace-only run: never opens a mark
+                    close_from(&mut out, &mut open, keep);
+                    out.push_str(&pending);
+                    pending = text.to_string();
+                    continue;
+                }
+
+                close_from(&mut out, &mut open, keep);
+                out.push_str(&pending);
+                pending.clear();
+
+                let lead = &text[..text.len() - text.trim_start().len()];
+                let trail = &text[text.trim_end().len()..];
+
+                out.push_str(&escape_text(lead, line_start));
+                if !lead.is_empty() {
+                    line_start = false;
+                }
+                for mark in &marks[keep..] {
+                    out.push_str(&open_mark(mark));
+                    open.push(mark);
+                }
+
+                if is_code {
+                    let ticks = "`".repeat(longest_run(core, '`') + 1);
+                    let pad = if core.starts_with('`') || core.ends_with('`') { " " } else { "" };
+                    out.push_str(&format!("{}{}{}{}{}", ticks, pad, core, pad, ticks));
+                } else {
+                    out.push_str(&escape_inline(core, line_start));
+                }
+                line_start = false;
+                pending = trail.to_string();
+            }
+            "hardBreak" => {
+                close_from(&mut out, &mut open, 0);
+                pending.clear();
+                if single_line {
+                    out.push(' ');
+                } else {
+                    // An otherwise empty line must not become a paragraph break
+                    out.push_str(if line_start { "\\\n" } else { "\n" });
+                    line_start = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    close_from(&mut out, &mut open, 0);
+    out.trim_end().to_string()
+}
+
+/// escape_text, except that links, images and autolinks are written as
+/// they are: to_doc keeps their source as literal text, and escaping it
+/// would break them for every other Markdown reader
+fn escape_inline(text: &str, line_start: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut end = 0;
+
+    for span in link_spans(text) {
+        out.push_str(&escape_text(&text[end..span.start], line_start && end == 0));
+        out.push_str(&text[span.clone()]);
+        end = span.end;
+    }
+    out.push_str(&escape_text(&text[end..], line_start && end == 0));
+
+    out
+}
+
+/// Byte ranges of the outermost links, images and autolinks in a run of text
+fn link_spans(text: &str) -> Vec<Range<usize>> {
+    // A leading letter keeps the text from reading as a heading, list or
+    // code block; offsets are shifted back past it
+    let source = format!("x{}", text);
+    let mut spans = Vec::new();
+    let mut depth = 0;
+
+    for (event, range) in Parser::new_ext(&source, Options::ENABLE_STRIKETHROUGH).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link { .. }) | Event::Start(Tag::Image { .. }) => {
+                if depth == 0 {
+                    spans.push(range.start - 1..range.end - 1);
+                }
+                depth += 1;
+            }
+            Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => depth -= 1,
+            _ => {}
+        }
+    }
+
+    spans
+}
+
+/// Backslash-escapes what would otherwise be read back as Markdown syntax.
+/// Deliberately conservative so the file stays readable: `[[links]]`,
+/// `#tags` and `snake_case` are left alone.
+fn escape_text(text: &str, line_start: bool) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = if i == 0 { None } else { Some(chars[i - 1]) };
+        let next = chars.get(i + 1).copied();
+        let at_start = line_start && i == 0;
+
+        let escape = match c {
+            '\\' | '*' | '`' | '~' | '<' => true,
+            '_' => !prev.is_some_and(|p| p.is_alphanumeric()) || !next.is_some_and(|n| n.is_alphanumeric()),
+            ']' => matches!(next, Some('(') | Some(':')),
+            '&' => next.is_some_and(|n| n.is_alphanumeric() || n == '#'),
+            '-' | '+' | '=' | '>' => at_start,
+            '#' => at_start && {
+                let run = chars.iter().take_while(|&&h| h == '#').count();
+                run <= 6 && chars.get(run).is_none_or(|n| n.is_whitespace())
+            },
+            '.' | ')' => line_start && prev.is_some_and(|p| p.is_ascii_digit())
+                && chars[..i].iter().all(|d| d.is_ascii_digit()),
+            _ => false,
+        };
+
+        if escape {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+fn escape_attr(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;")
+}
+
+// ==================== MARKDOWN -> TIPTAP ====================
+
+/// Converts Markdown to a TipTap document
+pub fn to_doc(markdown: &str) -> Value {
+    let mut builder = DocBuilder::default();
+    let mut literal_depth = 0;
+    let mut literal_start = 0;
+
+    for (event, range) in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH).into_offset_iter() {
+        // Links and images have no editor node: keep their source verbatim
+        if literal_depth > 0 {
+            match event {
+                Event::Start(Tag::Link { .. }) | Event::Start(Tag::Image { .. }) => literal_depth += 1,
+                Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => {
+                    literal_depth -= 1;
+                    if literal_depth == 0 {
+                        builder.text(&markdown[literal_start..range.end]);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => builder.open(json!({ "type": "paragraph" })),
+                Tag::Heading { level, .. } => {
+                    builder.open(json!({ "type": "heading", "attrs": { "level": level as u8 } }))
+                }
+                Tag::BlockQuote(_) => builder.open(json!({ "type": "blockquote" })),
+                Tag::CodeBlock(kind) => {
+                    let language = match kind {
+                        CodeBlockKind::Fenced(lang) if !lang.is_empty() => json!(lang.to_string()),
+                        _ => Value::Null,
+                    };
+                    builder.open(json!({ "type": "codeBlock", "attrs": { "language": language } }));
+                }
+                Tag::List(Some(start)) => {
+                    builder.open(json!({ "type": "orderedList", "attrs": { "start": start } }))
+                }
+                Tag::List(None) => builder.open(json!({ "type": "bulletList" })),
+                Tag::Item => builder.open(json!({ "type": "listItem" })),
+                Tag::Emphasis => builder.marks.push(json!({ "type": "italic" })),
+                Tag::Strong => builder.marks.push(json!({ "type": "bold" })),
+                Tag::Strikethrough => builder.marks.push(json!({ "type": "strike" })),
+                Tag::Link { .. } | Tag::Image { .. } => {
+                    literal_depth = 1;
+                    literal_start = range.start;
+                }
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::BlockQuote(_)
+                | TagEnd::CodeBlock | TagEnd::List(_) | TagEnd::Item => builder.close(),
+                TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                    builder.marks.pop();
+                }
+                _ => {}
+            },
+            Event::Text(text) => builder.text(&text),
+            Event::Code(code) => {
+                builder.marks.push(json!({ "type": "code" }));
+                builder.text(&code);
+                builder.marks.pop();
+            }
+            Event::InlineHtml(html) => builder.inline_html(&html),
+            Event::Html(html) => {
+                // Raw HTML blocks become literal text, one hardBreak per line
+                for (i, line) in html.trim_end_matches('\n').split('\n').enumerate() {
+                    if i > 0 {
+                        builder.hard_break();
+                    }
+                    builder.text(line);
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => builder.hard_break(),
+            Event::Rule => builder.leaf(json!({ "type": "horizontalRule" })),
+            _ => {}
+        }
+    }
+
+    builder.finish()
+}
+
+struct DocBuilder {
+    /// Open nodes; the bottom one is the doc
+    stack: Vec<Value>,
+    /// Whether the node at the same depth was opened implicitly (tight list
+    /// items and raw HTML carry inline content with no paragraph around it)
+    implicit: Vec<bool>,
+    marks: Vec<Value>,
+}
+
+impl Default for DocBuilder {
+    fn default() -> Self {
+        DocBuilder {
+            stack: vec![json!({ "type": "doc", "content": [] })],
+            implicit: vec![false],
+            marks: Vec::new(),
+        }
+    }
+}
+
+impl DocBuilder {
+    fn open(&mut self, node: Value) {
+        self.close_implicit();
+        self.stack.push(node);
+        self.implicit.push(false);
+    }
+
+    fn close(&mut self) {
+        self.close_implicit();
+        self.pop();
+    }
+
+    fn leaf(&mut self, node: Value) {
+        self.close_implicit();
+        self.append(node);
+    }
+
+    fn pop(&mut self) {
+        if self.stack.len() < 2 {
+            return;
+        }
+        let mut node = self.stack.pop().unwrap();
+        self.implicit.pop();
+
+        if node_type(&node) == "codeBlock" {
+            // The parser hands over the code with its final newline
+            if let Some(text) = node.pointer_mut("/content/0/text") {
+                let trimmed = text.as_str().unwrap_or("").strip_suffix('\n').map(|s| s.to_string());
+                if let Some(trimmed) = trimmed {
+                    *text = json!(trimmed);
+                }
+            }
+            if node.pointer("/content/0/text").and_then(|t| t.as_str()) == Some("") {
+                node.as_object_mut().unwrap().remove("content");
+            }
+        }
+        if node_type(&node) == "paragraph" && is_nbsp_only(&node) {
+            node.as_object_mut().unwrap().remove("content");
+        }
+
+        self.append(node);
+    }
+
+    fn close_implicit(&mut self) {
+        if self.implicit.last() == Some(&true) {
+            self.pop();
+        }
+    }
+
+    fn append(&mut self, node: Value) {
+        let parent = self.stack.last_mut().unwrap();
+        let content = parent.as_object_mut().unwrap()
+            .entry("content")
+            .or_insert_with(|| json!([]));
+        content.as_array_mut().unwrap().push(node);
+    }
+
+    /// Makes sure inline content has a textblock to go into
+    fn ensure_textblock(&mut self) {
+        let top = node_type(self.stack.last().unwrap());
+        if !matches!(top, "paragraph" | "heading" | "codeBlock") {
+            self.stack.push(json!({ "type": "paragraph" }));
+            self.implicit.push(true);
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.ensure_textblock();
+
+        let in_code_block = node_type(self.stack.last().unwrap()) == "codeBlock";
+        let mut marks = if in_code_block { Vec::new() } else { self.marks.clone() };
+        // Canonical order, so equal formatting always compares equal
+        marks.sort_by_key(|m| mark_rank(m).unwrap_or(u8::MAX));
+
+        // Merge with the previous text node when the marks match
+        let top = self.stack.last_mut().unwrap();
+        if let Some(last) = top.get_mut("content").and_then(|c| c.as_array_mut()).and_then(|c| c.last_mut()) {
+            let same_marks = last.get("marks").cloned().unwrap_or_else(|| json!([])) == json!(marks);
+            if node_type(last) == "text" && same_marks {
+                let merged = format!("{}{}", last["text"].as_str().unwrap_or(""), text);
+                last["text"] = json!(merged);
+                return;
+            }
+        }
+
+        let mut node = Map::new();
+        node.insert("type".into(), json!("text"));
+        node.insert("text".into(), json!(text));
+        if !marks.is_empty() {
+            node.insert("marks".into(), json!(marks));
+        }
+        self.append(Value::Object(node));
+    }
+
+    fn hard_break(&mut self) {
+        self.ensure_textblock();
+        if node_type(self.stack.last().unwrap()) == "heading" {
+            self.text(" ");
+        } else {
+            self.append(json!({ "type": "hardBreak" }));
+        }
+    }
+
+    /// The HTML-backed marks; any other tag is kept as literal text
+    fn inline_html(&mut self, html: &str) {
+        let tag = html.trim_start_matches('<').trim_start_matches('/');
+        let name: String = tag.chars().take_while(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase();
+        let closing = html.starts_with("</");
+
+        let mark_type = match name.as_str() {
+            "u" => "underline",
+            "mark" => "highlight",
+            "span" => "textStyle",
+            "br" => {
+                self.hard_break();
+                return;
+            }
+            _ => {
+                self.text(html);
+                return;
+            }
+        };
+
+        if closing {
+            if let Some(i) = self.marks.iter().rposition(|m| node_type(m) == mark_type) {
+                self.marks.remove(i);
+            }
+            return;
+        }
+
+        let style = parse_style(html);
+        let mark = match mark_type {
+            "highlight" => match style.iter().find(|(k, _)| k == "background-color") {
+                Some((_, color)) => json!({ "type": "highlight", "attrs": { "color": color } }),
+                None => json!({ "type": "highlight" }),
+            },
+            "textStyle" => {
+                let get = |key: &str| style.iter().find(|(k, _)| k == key).map(|(_, v)| json!(v)).unwrap_or(Value::Null);
+                json!({ "type": "textStyle", "attrs": { "color": get("color"), "fontFamily": get("font-family") } })
+            }
+            _ => json!({ "type": mark_type }),
+        };
+        self.marks.push(mark);
+    }
+
+    fn finish(mut self) -> Value {
+        while self.stack.len() > 1 {
+            self.close();
+        }
+        self.stack.pop().unwrap()
+    }
+}
+
+fn is_nbsp_only(paragraph: &Value) -> bool {
+    let content = children(paragraph);
+    content.len() == 1 && content[0].get("text").and_then(|t| t.as_str()) == Some(NBSP)
+}
+
+/// Reads the declarations out of a tag's `style="..."` attribute
+fn parse_style(tag: &str) -> Vec<(String, String)> {
+    let Some(start) = tag.find("style=") else {
+        return Vec::new();
+    };
+    let rest = &tag[start + 6..];
+    let quote = match rest.chars().next() {
+        Some(q @ ('"' | '\'')) => q,
+        _ => return Vec::new(),
+    };
+    let value = rest[1..].split(quote).next().unwrap_or("");
+    let value = value.replace("&quot;", "\"").replace("&amp;", "&");
+
+    value.split(';')
+        .filter_map(|decl| {
+            let (key, val) = decl.split_once(':')?;
+            Some((key.trim().to_lowercase(), val.trim().to_string()))
+        })
+        .filter(|(k, v)| !k.is_empty() && !v.is_empty())
+        .collect()
+}
+
+// ==================== PLAIN TEXT ====================
+
+/// Plain text of a document, the way the editor's getText() reports it:
+/// blocks separated by a blank line, hard breaks as newlines
+pub fn plain_text(doc: &Value) -> String {
+    let mut blocks = Vec::new();
+    collect_text_blocks(doc, &mut blocks);
+    blocks.join("\n\n")
+}
+
+fn collect_text_blocks(node: &Value, blocks: &mut Vec<String>) {
+    let is_textblock = children(node).iter()
+        .any(|c| matches!(node_type(c), "text" | "hardBreak"));
+
+    if is_textblock || matches!(node_type(node), "paragraph" | "heading" | "codeBlock") {
+        let text: String = children(node).iter()
+            .map(|c| match node_type(c) {
+                "hardBreak" => "\n",
+                _ => c.get("text").and_then(|t| t.as_str()).unwrap_or(""),
+            })
+            .collect();
+        blocks.push(text);
+    } else {
+        for child in children(node) {
+            collect_text_blocks(child, blocks);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(t: &str) -> Value {
+        json!({ "type": "text", "text": t })
+    }
+
+    fn marked(t: &str, marks: Value) -> Value {
+        json!({ "type": "text", "text": t, "marks": marks })
+    }
+
+    fn paragraph(content: Value) -> Value {
+        json!({ "type": "paragraph", "content": content })
+    }
+
+    fn doc(content: Value) -> Value {
+        json!({ "type": "doc", "content": content })
+    }
+
+    fn assert_round_trip(original: Value) {
+        let markdown = from_doc(&original);
+        assert_eq!(to_doc(&markdown), original, "markdown was:\n{}", markdown);
+    }
+
+    #[test]
+    fn headings_and_paragraphs() {
+        let d = doc(json!([
+            { "type": "heading", "attrs": { "level": 2 }, "content": [text("Title")] },
+            paragraph(json!([text("Some text.")])),
+        ]));
+        assert_eq!(from_doc(&d), "## Title\n\nSome text.\n");
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn nested_lists() {
+        let item = |t: &str, nested: Option<Value>| {
+            let mut content = vec![paragraph(json!([text(t)]))];
+            content.extend(nested);
+            json!({ "type": "listItem", "content": content })
+        };
+        let d = doc(json!([
+            { "type": "bulletList", "content": [
+                item("one", Some(json!({ "type": "orderedList", "attrs": { "start": 3 }, "content": [item("three", None), item("four", None)] }))),
+                item("two", None),
+            ]},
+        ]));
+        assert_eq!(from_doc(&d), "- one\n\n  3. three\n  4. four\n- two\n");
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn basic_marks() {
+        let d = doc(json!([paragraph(json!([
+            text("plain "),
+            marked("bold", json!([{ "type": "bold" }])),
+            text(" and "),
+            marked("both", json!([{ "type": "bold" }, { "type": "italic" }])),
+            text(" "),
+            marked("gone", json!([{ "type": "strike" }])),
+            text(" "),
+            marked("x = 1", json!([{ "type": "code" }])),
+        ]))]));
+        assert_eq!(from_doc(&d), "plain **bold** and ***both*** ~~gone~~ `x = 1`\n");
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn html_backed_marks() {
+        let d = doc(json!([paragraph(json!([
+            marked("under", json!([{ "type": "underline" }])),
+            text(" "),
+            marked("lit", json!([{ "type": "highlight", "attrs": { "color": "#ffcc00" } }])),
+            text(" "),
+            marked("plain mark", json!([{ "type": "highlight" }])),
+            text(" "),
+            marked("red", json!([{ "type": "textStyle", "attrs": { "color": "#ff0000", "fontFamily": null } }])),
+        ]))]));
+        let md = from_doc(&d);
+        assert!(md.contains("<u>under</u>"));
+        assert!(md.contains("<mark style=\"background-color: #ffcc00\">lit</mark>"));
+        assert!(md.contains("<mark>plain mark</mark>"));
+        assert!(md.contains("<span style=\"color: #ff0000\">red</span>"));
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn marks_spanning_several_text_nodes() {
+        let d = doc(json!([paragraph(json!([
+            marked("bold ", json!([{ "type": "bold" }])),
+            marked("and italic", json!([{ "type": "bold" }, { "type": "italic" }])),
+            text(" after"),
+        ]))]));
+        assert_eq!(from_doc(&d), "**bold *and italic*** after\n");
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn trailing_whitespace_moves_outside_delimiters() {
+        let d = doc(json!([paragraph(json!([
+            marked("bold ", json!([{ "type": "bold" }])),
+            text("next"),
+        ]))]));
+        assert_eq!(from_doc(&d), "**bold** next\n");
+    }
+
+    #[test]
+    fn blockquote_code_block_and_rule() {
+        let d = doc(json!([
+            { "type": "blockquote", "content": [paragraph(json!([text("quoted")])), paragraph(json!([text("twice")]))] },
+            { "type": "codeBlock", "attrs": { "language": "rust" }, "content": [text("fn main() {\n    // ```\n}")] },
+            { "type": "horizontalRule" },
+            paragraph(json!([text("end")])),
+        ]));
+        assert_eq!(
+            from_doc(&d),
+            "> quoted\n>\n> twice\n\n````rust\nfn main() {\n    // ```\n}\n````\n\n---\n\nend\n"
+        );
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn hard_breaks_and_empty_paragraphs() {
+        let d = doc(json!([
+            paragraph(json!([text("line one"), { "type": "hardBreak" }, { "type": "hardBreak" }, text("line three")])),
+            { "type": "paragraph" },
+            paragraph(json!([text("after a blank")])),
+        ]));
+        assert_eq!(from_doc(&d), "line one\n\\\nline three\n\n&nbsp;\n\nafter a blank\n");
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn markdown_syntax_in_text_is_escaped() {
+        let d = doc(json!([
+            paragraph(json!([text("# not a heading")])),
+            paragraph(json!([text("- not a list, 2 * 3, <b>, a_b, _x_")])),
+            paragraph(json!([text("1. not ordered")])),
+            paragraph(json!([text("---")])),
+        ]));
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn wiki_links_and_tags_stay_readable() {
+        let d = doc(json!([paragraph(json!([text("see [[Other Note]] and [[abc|alias]] #project/alpha")]))]));
+        assert_eq!(from_doc(&d), "see [[Other Note]] and [[abc|alias]] #project/alpha\n");
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn external_markdown_keeps_links_as_text() {
+        let d = to_doc("Read [the docs](https://example.com) now\nand ![img](a.png)");
+        assert_eq!(d, doc(json!([paragraph(json!([
+            text("Read [the docs](https://example.com) now"),
+            { "type": "hardBreak" },
+            text("and ![img](a.png)"),
+        ]))])));
+    }
+
+    #[test]
+    fn links_images_and_autolinks_are_written_back_as_they_were() {
+        let markdown = "See [the docs](https://example.com/a_b?x=1&y=2) and **[bold](b.md)**\n\
+                        ![cat *photo*](messy://attachment/ab12.png \"A cat\")\n\
+                        <https://example.com/~me> or [[Note]], not a\\](link)\n";
+        let d = to_doc(markdown);
+        assert_eq!(from_doc(&d), markdown);
+        assert_round_trip(d);
+    }
+
+    #[test]
+    fn tight_list_items_get_paragraphs() {
+        let d = to_doc("* a\n* b\n");
+        assert_eq!(d, doc(json!([{ "type": "bulletList", "content": [
+            { "type": "listItem", "content": [paragraph(json!([text("a")]))] },
+            { "type": "listItem", "content": [paragraph(json!([text("b")]))] },
+        ]}])));
+    }
+
+    #[test]
+    fn empty_document() {
+        assert_eq!(from_doc(&doc(json!([]))), "");
+        assert_eq!(to_doc(""), doc(json!([])));
+    }
+
+    #[test]
+    fn plain_text_matches_editor() {
+        let d = doc(json!([
+            { "type": "heading", "attrs": { "level": 1 }, "content": [text("Title")] },
+            paragraph(json!([text("a"), { "type": "hardBreak" }, marked("b", json!([{ "type": "bold" }]))])),
+            { "type": "bulletList", "content": [{ "type": "listItem", "content": [paragraph(json!([text("item")]))] }] },
+        ]));
+        assert_eq!(plain_text(&d), "Title\n\na\nb\n\nitem");
+    }
+}
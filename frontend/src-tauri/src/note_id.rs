@@ -0,0 +1,192 @@
+//! ====== NOTE IDS ======
+//!
+//! A note id doubles as a file name (notes/{id}.md, canvas/{id}.json), and
+//! ids arrive from the webview as plain strings. `NoteId` is the only way
+//! to turn one into a path, and it only accepts:
+//!   - a canonical UUID (lowercase, hyphenated), which is what the app
+//!     generates, or
+//!   - a slug: 1-64 of [a-z0-9_-], starting with a letter or digit
+//!
+//! Anything else (`..`, separators, absolute paths, NUL bytes, Windows
+//! device names) is rejected before a path is ever built. Commands take
+//! `NoteId` arguments directly, so bad ids fail at deserialization.
+//!
+//! ======================
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const MAX_SLUG_LEN: usize = 64;
+
+/// Names Windows reserves for devices, even with an extension
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul",
+    "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9",
+    "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct NoteId(String);
+
+impl NoteId {
+    /// A fresh random id
+    pub fn new() -> Self {
+        NoteId(Uuid::new_v4().to_string())
+    }
+
+    pub fn parse(id: &str) -> Result<Self, String> {
+        if is_canonical_uuid(id) || is_slug(id) {
+            Ok(NoteId(id.to_string()))
+        } else {
+            Err(format!("Invalid note id: {:?}", id))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for NoteId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_canonical_uuid(id: &str) -> bool {
+    Uuid::parse_str(id)
+        .map(|u| u.hyphenated().to_string() == id)
+        .unwrap_or(false)
+}
+
+fn is_slug(id: &str) -> bool {
+    let mut chars = id.chars();
+    let starts_ok = chars.next().is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+
+    starts_ok
+        && id.len() <= MAX_SLUG_LEN
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        && !RESERVED_NAMES.contains(&id)
+}
+
+impl TryFrom<String> for NoteId {
+    type Error = String;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        NoteId::parse(&id)
+    }
+}
+
+impl FromStr for NoteId {
+    type Err = String;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        NoteId::parse(id)
+    }
+}
+
+impl From<NoteId> for String {
+    fn from(id: NoteId) -> Self {
+        id.0
+    }
+}
+
+impl AsRef<str> for NoteId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for NoteId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PartialEq<str> for NoteId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<String> for NoteId {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_generated_uuids() {
+        let id = NoteId::new();
+        assert_eq!(NoteId::parse(id.as_str()), Ok(id));
+        assert!(NoteId::parse("67e55044-10b1-426f-9247-bb680e5fe0c8").is_ok());
+    }
+
+    #[test]
+    fn accepts_slugs() {
+        for id in ["daily", "2026-10-17", "project_alpha", "a"] {
+            assert!(NoteId::parse(id).is_ok(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn rejects_non_canonical_uuids() {
+        for id in [
+            "67E55044-10B1-426F-9247-BB680E5FE0C8",
+            "{67e55044-10b1-426f-9247-bb680e5fe0c8}",
+            "urn:uuid:67e55044-10b1-426f-9247-bb680e5fe0c8",
+        ] {
+            assert!(NoteId::parse(id).is_err(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn rejects_path_traversal() {
+        for id in ["..", ".", "../x", "../../.ssh/x", "notes/../../x", "..\\..\\x", "a/b", "a\\b", ".hidden"] {
+            assert!(NoteId::parse(id).is_err(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        for id in ["/etc/passwd", "\\\\server\\share", "C:\\Windows", "c:"] {
+            assert!(NoteId::parse(id).is_err(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn rejects_nul_and_control_bytes() {
+        for id in ["abc\0", "\0", "abc\0../x", "a\nb", "a\tb"] {
+            assert!(NoteId::parse(id).is_err(), "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_long_and_odd_slugs() {
+        let long = "a".repeat(MAX_SLUG_LEN + 1);
+        for id in ["", " ", "-leading", "_leading", "Upper", "with space", "dot.md", "ünïcode", long.as_str()] {
+            assert!(NoteId::parse(id).is_err(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn rejects_windows_device_names() {
+        for id in ["con", "nul", "com1", "lpt9"] {
+            assert!(NoteId::parse(id).is_err(), "{}", id);
+        }
+    }
+
+    #[test]
+    fn deserialization_validates() {
+        assert!(serde_json::from_str::<NoteId>("\"../../etc/passwd\"").is_err());
+        let id: NoteId = serde_json::from_str("\"daily\"").unwrap();
+        assert_eq!(serde_json::to_string(&id).unwrap(), "\"daily\"");
+    }
+}
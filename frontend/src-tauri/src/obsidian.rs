@@ -0,0 +1,677 @@
+//! ====== OBSIDIAN IMPORT ======
+//!
+//! Reads an Obsidian vault into folders, notes and attachments:
+//!
+//!   MyVault/                     Messy Notes
+//!   ├── .obsidian/          →    skipped, like every hidden folder
+//!   ├── Projects/           →    folder "Projects", inside a folder "MyVault"
+//!   │   └── Plan.md         →    note "Plan" (frontmatter, tags and file
+//!   │                            times kept)
+//!   └── images/cat.png      →    attachments/{sha256}.png, if a note embeds it
+//!
+//! Links are rewritten into the forms this app reads (see links.rs and
+//! attachments.rs), after which [[links]] become graph edges as usual:
+//!
+//!   [[Projects/Plan]], [[Plan.md]]      →  [[Plan]]
+//!   ![[Plan]]  (an embedded note)       →  [[Plan]]
+//!   ![[cat.png]], ![](images/cat.png)   →  ![cat.png](messy://attachment/{sha256})
+//!
+//! Like Obsidian, a link by file name goes to the file with that name
+//! closest to the linking note. Where notes in different folders share a
+//! title, links to them name the note's id instead: [[{id}|Plan]]. Only
+//! files found in the vault can be linked, so nothing outside it is ever
+//! copied. Frontmatter keys the app uses itself for something else (type,
+//! color, position, ...) are kept with an "obsidian" prefix: `type: book`
+//! becomes `obsidianType: book`.
+//!
+//! `plan` only reads the Obsidian vault. The caller writes what it
+//! planned, or for a dry run only reports it.
+//!
+//! =============================
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::attachments;
+use crate::frontmatter;
+use crate::links;
+use crate::note_id::NoteId;
+use crate::FRONTMATTER_KEYS;
+
+/// Everything an import will create
+#[derive(Debug, Default)]
+pub struct Plan {
+    /// Name of the vault folder, which becomes the folder holding the rest
+    pub name: String,
+    /// Parents before their children
+    pub folders: Vec<FolderPlan>,
+    pub notes: Vec<NotePlan>,
+    pub attachments: Vec<AttachmentPlan>,
+    /// Distinct links from one imported note to another
+    pub links: usize,
+    pub unresolved: Vec<String>,
+    pub skipped: Vec<Skipped>,
+}
+
+#[derive(Debug)]
+pub struct FolderPlan {
+    /// Relative to the vault, with '/' separators
+    pub path: String,
+    pub name: String,
+    /// The parent folder's path; None for one at the top of the vault
+    pub parent: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct NotePlan {
+    pub id: NoteId,
+    /// The Markdown file, relative to the vault
+    pub path: String,
+    /// Path of the folder it is in; None at the top of the vault
+    pub folder: Option<String>,
+    pub title: String,
+    pub aliases: Vec<String>,
+    pub tags: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    /// The rest of its frontmatter
+    pub extra: Map<String, Value>,
+    /// The body with links rewritten
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct AttachmentPlan {
+    /// Relative to the vault
+    pub path: String,
+    pub source: PathBuf,
+    pub hash: String,
+}
+
+/// A file left out of the import, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Skipped {
+    pub path: String,
+    pub reason: String,
+}
+
+/// What an import did, or would do for a dry run
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    /// The folder everything was put in
+    pub name: String,
+    /// Folder paths, relative to the Obsidian vault
+    pub folders: Vec<String>,
+    /// Markdown files that became notes
+    pub notes: Vec<String>,
+    /// Embedded files copied into attachments/
+    pub attachments: Vec<String>,
+    /// [[links]] between imported notes, each now a graph edge
+    pub links: usize,
+    /// Links and embeds naming nothing in the vault, as written
+    pub unresolved: Vec<String>,
+    pub skipped: Vec<Skipped>,
+}
+
+impl Plan {
+    pub fn report(&self, dry_run: bool) -> Report {
+        Report {
+            dry_run,
+            name: self.name.clone(),
+            folders: self.folders.iter().map(|f| f.path.clone()).collect(),
+            notes: self.notes.iter().map(|n| n.path.clone()).collect(),
+            attachments: self.attachments.iter().map(|a| a.path.clone()).collect(),
+            links: self.links,
+            unresolved: self.unresolved.clone(),
+            skipped: self.skipped.clone(),
+        }
+    }
+}
+
+/// Reads the Obsidian vault at `dir` and plans its import
+pub fn plan(dir: &Path) -> Result<Plan, String> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a folder", dir.display()));
+    }
+    let dir = fs::canonicalize(dir).map_err(|e| e.to_string())?;
+
+    let mut files = Files::default();
+    walk(&dir, "", &mut files)?;
+
+    let mut plan = Plan {
+        name: dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "Obsidian".to_string()),
+        folders: files.folders.iter().map(|path| FolderPlan {
+            path: path.clone(),
+            name: file_name(path).to_string(),
+            parent: parent(path).map(|p| p.to_string()),
+        }).collect(),
+        ..Plan::default()
+    };
+
+    // Titles first: links are rewritten to them
+    let mut read: Vec<(String, String)> = Vec::new();
+    for path in &files.notes {
+        let text = match fs::read(dir.join(path)).map(String::from_utf8) {
+            Ok(Ok(text)) => text,
+            Ok(Err(_)) => {
+                plan.skipped.push(Skipped { path: path.clone(), reason: "Not UTF-8 text".to_string() });
+                continue;
+            }
+            Err(e) => {
+                plan.skipped.push(Skipped { path: path.clone(), reason: e.to_string() });
+                continue;
+            }
+        };
+        match note(&dir, path, &text) {
+            Ok((note, body)) => {
+                plan.notes.push(note);
+                read.push((path.clone(), body));
+            }
+            Err(reason) => plan.skipped.push(Skipped { path: path.clone(), reason }),
+        }
+    }
+
+    let notes: HashMap<&str, (&NoteId, &str)> = plan.notes.iter()
+        .map(|n| (n.path.as_str(), (&n.id, n.title.as_str())))
+        .collect();
+    let mut seen = HashSet::new();
+    let shared_titles = plan.notes.iter()
+        .map(|n| links::normalize_title(&n.title))
+        .filter(|title| !seen.insert(title.clone()))
+        .collect();
+    let mut converter = Converter {
+        dir: &dir,
+        files: &files,
+        notes: &notes,
+        shared_titles,
+        attachments: BTreeMap::new(),
+        unresolved: BTreeSet::new(),
+        skipped: Vec::new(),
+    };
+    let bodies: Vec<String> = read.iter().map(|(path, body)| converter.convert(path, body)).collect();
+
+    let Converter { attachments, unresolved, skipped, .. } = converter;
+    plan.attachments = attachments.into_iter().map(|(path, hash)| AttachmentPlan { source: dir.join(&path), path, hash }).collect();
+    plan.unresolved = unresolved.into_iter().collect();
+    plan.skipped.extend(skipped);
+    for (note, body) in plan.notes.iter_mut().zip(bodies) {
+        note.body = body;
+    }
+
+    plan.links = count_links(&plan.notes);
+    Ok(plan)
+}
+
+/// The folders and files of a vault, paths relative to it, in name order
+#[derive(Default)]
+struct Files {
+    folders: Vec<String>,
+    notes: Vec<String>,
+    others: Vec<String>,
+}
+
+fn walk(root: &Path, relative: &str, files: &mut Files) -> Result<(), String> {
+    let mut entries: Vec<fs::DirEntry> = fs::read_dir(root.join(relative))
+        .map_err(|e| format!("Failed to read {}: {}", root.join(relative).display(), e))?
+        .flatten()
+        .collect();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        // .obsidian/, .trash/, .git/ ... and links that could lead out
+        let Ok(file_type) = entry.file_type() else { continue };
+        if name.starts_with('.') || file_type.is_symlink() {
+            continue;
+        }
+
+        let path = if relative.is_empty() { name.clone() } else { format!("{}/{}", relative, name) };
+        if file_type.is_dir() {
+            files.folders.push(path.clone());
+            walk(root, &path, files)?;
+        } else if is_markdown(&name) {
+            files.notes.push(path);
+        } else {
+            files.others.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// A note from a Markdown file, with its body as it was
+fn note(dir: &Path, path: &str, text: &str) -> Result<(NotePlan, String), String> {
+    let (metadata, body) = frontmatter::parse(text)?;
+    let Value::Object(mut fields) = metadata else {
+        return Err("Frontmatter must be a key/value mapping".to_string());
+    };
+
+    let stem = note_name(path);
+    let title = match fields.remove("title") {
+        Some(Value::String(title)) if !title.trim().is_empty() => title.trim().to_string(),
+        Some(other) if !other.is_null() => {
+            fields.insert("title".to_string(), other);
+            stem.to_string()
+        }
+        _ => stem.to_string(),
+    };
+
+    // A list, or a single name; Obsidian also reads the singular keys
+    let mut aliases = strings(fields.remove("aliases").or_else(|| fields.remove("alias")));
+    if links::normalize_title(stem) != links::normalize_title(&title) {
+        // Links in the vault name the file
+        aliases.push(stem.to_string());
+    }
+    let tags = strings(fields.remove("tags").or_else(|| fields.remove("tag")))
+        .iter()
+        .flat_map(|t| t.split(|c: char| c == ',' || c.is_whitespace()))
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect();
+
+    // Keys the app reads itself would be taken for its own
+    let extra = fields.into_iter()
+        .map(|(key, value)| match FRONTMATTER_KEYS.contains(&key.as_str()) {
+            true => (format!("obsidian{}{}", key[..1].to_uppercase(), &key[1..]), value),
+            false => (key, value),
+        })
+        .collect();
+
+    let metadata = fs::metadata(dir.join(path)).map_err(|e| e.to_string())?;
+    let modified = metadata.modified().ok();
+    let created = metadata.created().ok().or(modified);
+
+    Ok((NotePlan {
+        id: NoteId::new(),
+        path: path.to_string(),
+        folder: parent(path).map(|p| p.to_string()),
+        title,
+        aliases,
+        tags,
+        created_at: timestamp(created),
+        updated_at: timestamp(modified),
+        extra,
+        body: String::new(),
+    }, body))
+}
+
+/// Strings from a frontmatter value that is a list or a single one
+fn strings(value: Option<Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => vec![s],
+        Some(Value::Array(items)) => items.into_iter()
+            .filter_map(|v| v.as_str().map(|s| s.trim().to_string()))
+            .filter(|s| !s.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn timestamp(time: Option<SystemTime>) -> String {
+    time.map(DateTime::<Utc>::from).unwrap_or_else(Utc::now).to_rfc3339()
+}
+
+/// Rewrites note bodies, collecting the attachments they embed
+struct Converter<'a> {
+    dir: &'a Path,
+    files: &'a Files,
+    /// Note ids and titles by path
+    notes: &'a HashMap<&'a str, (&'a NoteId, &'a str)>,
+    /// Titles (normalized) more than one note has
+    shared_titles: HashSet<String>,
+    /// Hashes of embedded files by path
+    attachments: BTreeMap<String, String>,
+    unresolved: BTreeSet<String>,
+    skipped: Vec<Skipped>,
+}
+
+impl<'a> Converter<'a> {
+    /// The body of the note at `path`, with links and embeds rewritten,
+    /// code blocks and inline code left alone
+    fn convert(&mut self, path: &str, body: &str) -> String {
+        let mut out = String::with_capacity(body.len());
+        let mut fence: Option<&str> = None;
+
+        for line in body.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            let marker = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m));
+            match (fence, marker) {
+                (None, Some(marker)) => fence = Some(marker),
+                (Some(open), Some(marker)) if open == marker => fence = None,
+                (None, None) => {
+                    out.push_str(&self.convert_line(path, line));
+                    continue;
+                }
+                _ => {}
+            }
+            out.push_str(line);
+        }
+
+        out
+    }
+
+    fn convert_line(&mut self, path: &str, line: &str) -> String {
+        let mut out = String::with_capacity(line.len());
+        let mut rest = line;
+
+        while !rest.is_empty() {
+            // Each step takes some text, rewritten or else kept as it is
+            let (len, rewritten) = if let Some(code) = rest.strip_prefix('`') {
+                // Inline code, up to the closing backtick
+                (code.find('`').map(|i| i + 2).unwrap_or(rest.len()), None)
+            } else if let Some((len, inner)) = bracketed(rest, "![[") {
+                (len, self.embed(path, inner))
+            } else if let Some((len, inner)) = bracketed(rest, "[[") {
+                (len, self.wikilink(path, inner))
+            } else if let Some((len, alt, target)) = markdown_image(rest) {
+                (len, self.image(path, alt, target))
+            } else {
+                (rest.chars().next().map(char::len_utf8).unwrap_or(1), None)
+            };
+            out.push_str(rewritten.as_deref().unwrap_or(&rest[..len]));
+            rest = &rest[len..];
+        }
+
+        out
+    }
+
+    /// ![[file]] or ![[note]]
+    fn embed(&mut self, path: &str, inner: &str) -> Option<String> {
+        let (target, _) = split_target(inner);
+        if let Some(file) = self.resolve(path, target, false) {
+            return self.attachment_link(file, true);
+        }
+        // Notes can't be embedded here; link to them instead
+        self.wikilink(path, inner)
+    }
+
+    /// [[note]] or [[file]]
+    fn wikilink(&mut self, path: &str, inner: &str) -> Option<String> {
+        let (target, suffix) = split_target(inner);
+
+        if let Some(note) = self.resolve(path, target, true) {
+            let (id, title) = self.notes.get(note)?;
+            if !self.shared_titles.contains(&links::normalize_title(title)) {
+                return Some(format!("[[{}{}]]", title, suffix));
+            }
+            let shown = if suffix.contains('|') { String::new() } else { format!("|{}", title) };
+            return Some(format!("[[{}{}{}]]", id, suffix, shown));
+        }
+        if let Some(file) = self.resolve(path, target, false) {
+            return self.attachment_link(file, false);
+        }
+
+        self.unresolved.insert(format!("[[{}]]", inner));
+        None
+    }
+
+    /// ![alt](relative/path.png)
+    fn image(&mut self, path: &str, alt: &str, target: &str) -> Option<String> {
+        if target.contains("://") || target.starts_with("data:") || target.starts_with('#') {
+            return None;
+        }
+        let target = target.split_whitespace().next()?.trim_matches(['<', '>']);
+        let decoded = target.replace("%20", " ");
+
+        let Some(file) = self.resolve(path, &decoded, false) else {
+            self.unresolved.insert(format!("![{}]({})", alt, target));
+            return None;
+        };
+        let hash = self.attach(file)?;
+        let alt = if alt.is_empty() { file_name(file) } else { alt };
+        Some(format!("![{}]({})", alt, attachments::uri(&hash)))
+    }
+
+    /// A link to an embedded file: an image for pictures, else a plain link
+    fn attachment_link(&mut self, file: &str, embedded: bool) -> Option<String> {
+        let hash = self.attach(file)?;
+        let name = file_name(file);
+        let is_image = mime_guess::from_path(file).first().is_some_and(|m| m.type_() == "image");
+        let bang = if embedded && is_image { "!" } else { "" };
+        Some(format!("{}[{}]({})", bang, name, attachments::uri(&hash)))
+    }
+
+    /// Hashes a file to be copied into attachments/, once
+    fn attach(&mut self, file: &str) -> Option<String> {
+        if let Some(hash) = self.attachments.get(file) {
+            return Some(hash.clone());
+        }
+
+        let source = self.dir.join(file);
+        let read = fs::metadata(&source)
+            .map_err(|e| e.to_string())
+            .and_then(|m| match m.len() > attachments::MAX_SIZE {
+                true => Err(format!("Larger than {} MB", attachments::MAX_SIZE / 1024 / 1024)),
+                false => fs::read(&source).map_err(|e| e.to_string()),
+            });
+        match read {
+            Ok(bytes) => {
+                let hash = attachments::hash(&bytes);
+                self.attachments.insert(file.to_string(), hash.clone());
+                Some(hash)
+            }
+            Err(reason) => {
+                if !self.skipped.iter().any(|s| s.path == file) {
+                    self.skipped.push(Skipped { path: file.to_string(), reason });
+                }
+                None
+            }
+        }
+    }
+
+    /// The vault file a link from the note at `from` names: a note if
+    /// `note`, else any other file. A path is read relative to the note,
+    /// then to the vault; a bare name finds the file closest to the note.
+    fn resolve(&self, from: &str, target: &str, note: bool) -> Option<&'a str> {
+        let target = target.trim().trim_start_matches("./");
+        if target.is_empty() {
+            return None;
+        }
+        let target = if note && !is_markdown(target) { format!("{}.md", target) } else { target.to_string() };
+        let candidates = if note { &self.files.notes } else { &self.files.others };
+        let find = |path: &str| candidates.iter().find(|c| c.eq_ignore_ascii_case(path)).map(|c| c.as_str());
+
+        if target.contains('/') {
+            let from_note = parent(from).map(|dir| format!("{}/{}", dir, target));
+            return from_note.iter()
+                .chain([&target])
+                .filter_map(|path| normalize(path))
+                .find_map(|path| find(&path));
+        }
+
+        let dir = parent(from).unwrap_or("");
+        candidates.iter()
+            .filter(|c| file_name(c).eq_ignore_ascii_case(&target))
+            .min_by_key(|c| (parent(c).unwrap_or("") != dir, c.matches('/').count(), c.as_str()))
+            .map(|c| c.as_str())
+    }
+}
+
+/// `[[inner]]` (or `![[inner]]`) at the start of `text`: the length taken
+/// and the inner text
+fn bracketed<'t>(text: &'t str, open: &str) -> Option<(usize, &'t str)> {
+    let after = text.strip_prefix(open)?;
+    let close = after.find("]]")?;
+    let inner = &after[..close];
+    (!inner.is_empty() && !inner.contains(['[', ']'])).then_some((open.len() + close + 2, inner))
+}
+
+/// `![alt](target)` at the start of `text`: the length taken, alt, target
+fn markdown_image(text: &str) -> Option<(usize, &str, &str)> {
+    let after = text.strip_prefix("![")?;
+    let alt_end = after.find("](")?;
+    let alt = &after[..alt_end];
+    let target_start = alt_end + 2;
+    let target_end = after[target_start..].find(')')? + target_start;
+    if alt.contains(['[', ']']) {
+        return None;
+    }
+    Some((2 + target_end + 1, alt, &after[target_start..target_end]))
+}
+
+/// A link's target and what follows it (#heading, |alias)
+fn split_target(inner: &str) -> (&str, &str) {
+    match inner.find(['|', '#']) {
+        Some(i) => (&inner[..i], &inner[i..]),
+        None => (inner, ""),
+    }
+}
+
+/// A path with `.` and `..` resolved; None if it leads out of the vault
+fn normalize(path: &str) -> Option<String> {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+fn parent(path: &str) -> Option<&str> {
+    path.rsplit_once('/').map(|(dir, _)| dir)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit_once('/').map(|(_, name)| name).unwrap_or(path)
+}
+
+/// A note's file name without .md, which Obsidian uses as its title
+fn note_name(path: &str) -> &str {
+    let name = file_name(path);
+    &name[..name.len() - 3]
+}
+
+fn is_markdown(name: &str) -> bool {
+    Path::new(name).extension().is_some_and(|e| e.eq_ignore_ascii_case("md"))
+}
+
+/// Links between the planned notes that become edges: one per pair of
+/// notes, none to the note itself
+fn count_links(notes: &[NotePlan]) -> usize {
+    let titles = notes.iter().map(|n| (&n.id, n.title.as_str()));
+    let aliases = notes.iter().flat_map(|n| n.aliases.iter().map(move |a| (&n.id, a.as_str())));
+    let resolver = links::Resolver::new(titles.chain(aliases));
+
+    let mut pairs = BTreeSet::new();
+    for note in notes {
+        for link in links::parse(&note.body) {
+            if let Some(target) = resolver.resolve(&link.target).filter(|t| **t != note.id) {
+                pairs.insert((&note.id, target));
+            }
+        }
+    }
+    pairs.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn planned<'p>(plan: &'p Plan, path: &str) -> &'p NotePlan {
+        plan.notes.iter().find(|n| n.path == path).unwrap()
+    }
+
+    #[test]
+    fn plans_folders_notes_and_frontmatter() {
+        let vault = TempDir::new("obsidian");
+        vault.write(".obsidian/app.json", b"{}");
+        vault.write("Inbox.md", b"Loose thought");
+        vault.write("Projects/Plan.md", b"---\ntitle: The Plan\ntags: [work, \"#q3\"]\naliases: Roadmap\ntype: project\nrating: 5\n---\nSteps");
+        vault.write("Projects/Archive/Old.md", b"---\nbroken: [\n---\nbody");
+
+        let plan = plan(&vault.0).unwrap();
+
+        assert_eq!(plan.folders.iter().map(|f| f.path.as_str()).collect::<Vec<_>>(), ["Projects", "Projects/Archive"]);
+        assert_eq!(plan.folders[1].parent.as_deref(), Some("Projects"));
+        assert_eq!(plan.notes.len(), 2);
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].path, "Projects/Archive/Old.md");
+
+        let inbox = planned(&plan, "Inbox.md");
+        assert_eq!((inbox.title.as_str(), inbox.folder.as_deref()), ("Inbox", None));
+
+        let note = planned(&plan, "Projects/Plan.md");
+        assert_eq!(note.title, "The Plan");
+        assert_eq!(note.folder.as_deref(), Some("Projects"));
+        assert_eq!(note.aliases, ["Roadmap", "Plan"]);
+        assert_eq!(note.tags, ["work", "#q3"]);
+        assert_eq!(note.extra.get("obsidianType"), Some(&Value::from("project")));
+        assert_eq!(note.extra.get("rating"), Some(&Value::from(5)));
+        assert_eq!(note.body, "Steps");
+    }
+
+    #[test]
+    fn rewrites_links_and_embeds() {
+        let vault = TempDir::new("obsidian");
+        vault.write("images/cat.png", b"cat picture");
+        vault.write("docs/spec.pdf", b"spec");
+        vault.write("A/Plan.md", b"In A");
+        vault.write("B/Plan.md", b"In B");
+        vault.write("B/Notes.md", concat!(
+            "[[Plan]] [[A/Plan|the other]] [[Plan.md#Goals]] [[Missing]]\n",
+            "![[cat.png]] ![[spec.pdf]] ![[Plan]] ![photo](../images/cat.png)\n",
+            "`[[Plan]]` ![web](https://example.com/x.png)\n",
+            "```\n[[A/Plan]]\n```\n",
+        ).as_bytes());
+
+        let plan = plan(&vault.0).unwrap();
+        let cat = attachments::uri(&attachments::hash(b"cat picture"));
+        let spec = attachments::uri(&attachments::hash(b"spec"));
+
+        // Both Plans are titled "Plan", so links name them by id
+        let a = &planned(&plan, "A/Plan.md").id;
+        let b = &planned(&plan, "B/Plan.md").id;
+        assert_eq!(planned(&plan, "B/Notes.md").body, format!(concat!(
+            "[[{b}|Plan]] [[{a}|the other]] [[{b}#Goals|Plan]] [[Missing]]\n",
+            "![cat.png]({cat}) [spec.pdf]({spec}) [[{b}|Plan]] ![photo]({cat})\n",
+            "`[[Plan]]` ![web](https://example.com/x.png)\n",
+            "```\n[[A/Plan]]\n```\n",
+        ), a = a, b = b, cat = cat, spec = spec));
+
+        assert_eq!(plan.attachments.iter().map(|a| a.path.as_str()).collect::<Vec<_>>(), ["docs/spec.pdf", "images/cat.png"]);
+        assert_eq!(plan.unresolved, ["[[Missing]]"]);
+        assert_eq!(plan.links, 2);
+    }
+
+    #[test]
+    fn reads_names_and_links_that_are_not_ascii() {
+        let vault = TempDir::new("obsidian");
+        vault.write("Crème.md", "See [[naïve]] and [[Über]]".as_bytes());
+        vault.write("Recettes/naïve.md", "Back to [[Crème]]".as_bytes());
+
+        let plan = plan(&vault.0).unwrap();
+
+        assert_eq!(planned(&plan, "Crème.md").title, "Crème");
+        assert_eq!(planned(&plan, "Recettes/naïve.md").title, "naïve");
+        assert_eq!(plan.unresolved, ["[[Über]]"]);
+        assert_eq!(plan.links, 2);
+    }
+
+    #[test]
+    fn paths_cannot_leave_the_vault() {
+        assert_eq!(normalize("a/../b/./c"), Some("b/c".to_string()));
+        assert_eq!(normalize("../secret"), None);
+        assert_eq!(normalize("a/../../secret"), None);
+
+        let vault = TempDir::new("obsidian");
+        vault.write("Note.md", b"![x](../../etc/passwd) ![[../outside.png]]");
+        let plan = plan(&vault.0).unwrap();
+
+        assert!(plan.attachments.is_empty());
+        assert_eq!(plan.notes[0].body, "![x](../../etc/passwd) ![[../outside.png]]");
+        assert_eq!(plan.unresolved.len(), 2);
+    }
+}
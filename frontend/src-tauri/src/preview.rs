@@ -0,0 +1,223 @@
+//! ====== ATTACHMENT PREVIEWS ======
+//!
+//! Image attachments are shown on canvases and in notes at a fraction of
+//! their size, so the webview gets a downscaled thumbnail instead of the
+//! full photo. Thumbnails are turned the right way up using the EXIF
+//! orientation the camera stored, and cached in the vault:
+//!
+//!   .previews/
+//!   └── {sha256}-{size}.json   ← thumbnail (as a data: URL) plus the
+//!                                 original's dimensions and orientation
+//!
+//! Attachments never change, so a cached preview is good until the
+//! attachment is removed, which removes its previews too. The folder is
+//! safe to delete; previews are made again when next asked for.
+//!
+//! ================================
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use crate::durable;
+
+/// Longest side of a thumbnail unless asked otherwise
+pub const DEFAULT_SIZE: u32 = 512;
+
+const MIN_SIZE: u32 = 32;
+const MAX_SIZE: u32 = 2048;
+
+const JPEG_QUALITY: u8 = 80;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preview {
+    pub hash: String,
+    /// Size of the full image, the right way up
+    pub width: u32,
+    pub height: u32,
+    /// EXIF orientation the image was stored with (1 is upright)
+    pub orientation: u8,
+    /// The thumbnail, as a data: URL the webview can show directly
+    pub thumbnail: String,
+    #[serde(rename = "thumbnailWidth")]
+    pub thumbnail_width: u32,
+    #[serde(rename = "thumbnailHeight")]
+    pub thumbnail_height: u32,
+}
+
+/// The thumbnail size to use for a requested one
+pub fn size(requested: Option<u32>) -> u32 {
+    requested.unwrap_or(DEFAULT_SIZE).clamp(MIN_SIZE, MAX_SIZE)
+}
+
+/// Makes a preview of the image in `bytes`, no larger than `size` pixels
+/// on its longest side. Fails for anything that isn't an image.
+pub fn generate(hash: &str, bytes: &[u8], size: u32) -> Result<Preview, String> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(|e| e.to_string())?;
+    if reader.format().is_none() {
+        return Err("Attachment is not an image".to_string());
+    }
+
+    let mut decoder = reader.into_decoder().map_err(|e| format!("Failed to read image: {}", e))?;
+    let orientation = decoder.orientation().map_err(|e| format!("Failed to read image: {}", e))?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| format!("Failed to read image: {}", e))?;
+    image.apply_orientation(orientation);
+
+    let (width, height) = (image.width(), image.height());
+    let thumbnail = if width > size || height > size {
+        image.thumbnail(size, size)
+    } else {
+        image
+    };
+
+    Ok(Preview {
+        hash: hash.to_string(),
+        width,
+        height,
+        orientation: orientation.to_exif(),
+        thumbnail_width: thumbnail.width(),
+        thumbnail_height: thumbnail.height(),
+        thumbnail: encode(&thumbnail)?,
+    })
+}
+
+/// A thumbnail as a data: URL: JPEG, or PNG if it has transparency
+fn encode(image: &DynamicImage) -> Result<String, String> {
+    let mut bytes = Vec::new();
+
+    let mime_type = if image.color().has_alpha() {
+        image.write_with_encoder(PngEncoder::new(&mut bytes)).map(|_| "image/png")
+    } else {
+        let rgb = DynamicImage::ImageRgb8(image.to_rgb8());
+        rgb.write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)).map(|_| "image/jpeg")
+    }
+    .map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+
+    Ok(format!("data:{};base64,{}", mime_type, BASE64.encode(bytes)))
+}
+
+/// Where the preview of `hash` at `size` is cached
+pub fn cache_file(dir: &Path, hash: &str, size: u32) -> PathBuf {
+    dir.join(format!("{}-{}.json", hash, size))
+}
+
+/// A cached preview, if there is a readable one
+pub fn load(file: &Path) -> Option<Preview> {
+    let json = fs::read_to_string(file).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+pub fn save(file: &Path, preview: &Preview) -> Result<(), String> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    let json = serde_json::to_string(preview)
+        .map_err(|e| format!("Failed to serialize preview: {}", e))?;
+    durable::write(file, json).map_err(|e| e.to_string())
+}
+
+/// Removes every cached preview of `hash`
+pub fn forget(dir: &Path, hash: &str) -> Result<(), String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+
+    let prefix = format!("{}-", hash);
+    for entry in entries.flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            fs::remove_file(entry.path()).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use image::{ImageFormat, Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn png(image: DynamicImage) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png).unwrap();
+        bytes
+    }
+
+    /// A JPEG of `width` x `height` with an EXIF orientation tag
+    fn jpeg_with_orientation(width: u32, height: u32, orientation: u8) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let image = RgbImage::from_pixel(width, height, Rgb([200, 30, 30]));
+        DynamicImage::ImageRgb8(image).write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg).unwrap();
+
+        // APP1 "Exif" segment holding a little-endian TIFF with one tag,
+        // Orientation (0x0112), inserted right after the SOI marker
+        let mut tiff = b"II*\0\x08\0\0\0\x01\0\x12\x01\x03\0\x01\0\0\0".to_vec();
+        tiff.extend_from_slice(&[orientation, 0, 0, 0, 0, 0, 0, 0]);
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend_from_slice(&tiff);
+        let len = (app1.len() + 2) as u16;
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&len.to_be_bytes());
+        segment.extend_from_slice(&app1);
+
+        bytes.splice(2..2, segment);
+        bytes
+    }
+
+    #[test]
+    fn thumbnails_keep_the_aspect_ratio_and_transparency() {
+        let bytes = png(DynamicImage::ImageRgba8(RgbaImage::from_pixel(1000, 500, Rgba([0, 0, 255, 128]))));
+
+        let preview = generate("h", &bytes, 100).unwrap();
+        assert_eq!((preview.width, preview.height), (1000, 500));
+        assert_eq!((preview.thumbnail_width, preview.thumbnail_height), (100, 50));
+        assert_eq!(preview.orientation, 1);
+        assert!(preview.thumbnail.starts_with("data:image/png;base64,"));
+
+        // Small images aren't blown up
+        let small = png(DynamicImage::ImageRgb8(RgbImage::new(20, 10)));
+        let preview = generate("h", &small, 100).unwrap();
+        assert_eq!((preview.thumbnail_width, preview.thumbnail_height), (20, 10));
+        assert!(preview.thumbnail.starts_with("data:image/jpeg;base64,"));
+
+        assert!(generate("h", b"%PDF-1.7", 100).is_err());
+        assert_eq!(size(None), DEFAULT_SIZE);
+        assert_eq!(size(Some(1)), MIN_SIZE);
+    }
+
+    #[test]
+    fn exif_orientation_turns_the_image_upright() {
+        // Stored sideways: 6 means rotate 90° clockwise to display
+        let preview = generate("h", &jpeg_with_orientation(400, 200, 6), 100).unwrap();
+
+        assert_eq!(preview.orientation, 6);
+        assert_eq!((preview.width, preview.height), (200, 400));
+        assert_eq!((preview.thumbnail_width, preview.thumbnail_height), (50, 100));
+    }
+
+    #[test]
+    fn previews_are_cached_and_forgotten_per_hash() {
+        let temp = TempDir::new("previews");
+        let dir = temp.0.join(".previews");
+        let bytes = png(DynamicImage::ImageRgb8(RgbImage::new(64, 64)));
+        let preview = generate("abc", &bytes, 32).unwrap();
+
+        save(&cache_file(&dir, "abc", 32), &preview).unwrap();
+        save(&cache_file(&dir, "abd", 32), &preview).unwrap();
+        assert_eq!(load(&cache_file(&dir, "abc", 32)), Some(preview));
+        assert_eq!(load(&cache_file(&dir, "abc", 64)), None);
+
+        forget(&dir, "abc").unwrap();
+        assert!(load(&cache_file(&dir, "abc", 32)).is_none());
+        assert!(load(&cache_file(&dir, "abd", 32)).is_some());
+    }
+}
@@ -0,0 +1,221 @@
+//! ====== ATTACHMENT PROTOCOL ======
+//!
+//! The app registers the messy: URI scheme with the webview, so notes and
+//! media cards can show attachments (see attachments.rs) directly:
+//!
+//!   messy://attachment/{sha256}                   ← macOS, Linux
+//!   https://messy.localhost/attachment/{sha256}   ← Windows, where the
+//!                                                   webview only allows
+//!                                                   custom schemes this way
+//!
+//! Only files in the open vault's attachments/ are served, looked up by
+//! hash, so a URI can't name any other file; one that is a link leading
+//! out of attachments/ is refused too. Range requests are answered with
+//! 206 and at most CHUNK bytes, which is what audio and video players need
+//! to seek without the whole file being read into memory.
+//!
+//! =================================
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::attachments;
+use crate::store::{Item, VaultStore, ATTACHMENTS_DIR};
+
+pub const SCHEME: &str = "messy";
+
+/// Most bytes answered to one range request
+const CHUNK: u64 = 4 * 1024 * 1024;
+
+/// What the webview gets back; main.rs turns it into Tauri's response
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn error(status: u16, message: &str) -> Self {
+        Response {
+            status,
+            headers: vec![("Content-Type", "text/plain".to_string())],
+            body: message.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// The part of a file a Range header asks for
+#[derive(Debug, PartialEq)]
+pub enum Range {
+    /// No (usable) range: the whole file
+    Full,
+    /// First and last byte, inclusive
+    Part(u64, u64),
+    /// Starts past the end of the file
+    Unsatisfiable,
+}
+
+/// The hash a messy: URI asks for, in either form
+pub fn hash_in(uri: &str) -> Option<&str> {
+    let rest = [
+        "messy://attachment/",
+        "https://messy.localhost/attachment/",
+        "http://messy.localhost/attachment/",
+    ]
+    .iter()
+    .find_map(|prefix| uri.strip_prefix(prefix))?;
+
+    let hash = rest.split(['?', '#']).next()?;
+    attachments::is_hash(hash).then_some(hash)
+}
+
+/// Reads a Range header (`bytes=0-499`, `bytes=500-`, `bytes=-500`) for a
+/// file of `len` bytes. Only the first range of several is served; one
+/// that doesn't parse is ignored, as HTTP allows.
+pub fn range(header: Option<&str>, len: u64) -> Range {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return Range::Full;
+    };
+    let Some((start, end)) = spec.split(',').next().and_then(|r| r.trim().split_once('-')) else {
+        return Range::Full;
+    };
+
+    let (start, end) = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+        (Ok(start), Err(_)) if end.is_empty() => (start, len.saturating_sub(1)),
+        // The last `suffix` bytes
+        (Err(_), Ok(suffix)) if start.is_empty() && suffix > 0 => (len.saturating_sub(suffix), len.saturating_sub(1)),
+        _ => return Range::Full,
+    };
+
+    if start >= len {
+        Range::Unsatisfiable
+    } else {
+        Range::Part(start, end.min(start + CHUNK - 1))
+    }
+}
+
+/// Answers a request for `uri` from the vault's attachments
+pub fn serve(uri: &str, range_header: Option<&str>, store: &dyn VaultStore) -> Response {
+    let name = match hash_in(uri).map(|hash| attachments::find(hash, store)) {
+        Some(Ok(Some(name))) => name,
+        Some(Err(e)) => return Response::error(500, &e),
+        _ => return Response::error(404, "Attachment not found"),
+    };
+
+    match respond(&name, range_header, store) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Failed to serve attachment {}: {}", name, e);
+            Response::error(500, "Failed to read attachment")
+        }
+    }
+}
+
+fn respond(name: &str, range_header: Option<&str>, store: &dyn VaultStore) -> Result<Response, String> {
+    let item = Item::Attachment(name.to_string());
+    let len = store.size(&item)?.ok_or("Attachment disappeared")?;
+
+    let mut headers = vec![
+        ("Content-Type", mime_guess::from_path(name).first_or_octet_stream().to_string()),
+        ("Accept-Ranges", "bytes".to_string()),
+        // Named by their contents, so they never change
+        ("Cache-Control", "public, max-age=31536000, immutable".to_string()),
+    ];
+
+    let (status, start, end) = match range(range_header, len) {
+        Range::Full => (200, 0, len.saturating_sub(1)),
+        Range::Part(start, end) => {
+            headers.push(("Content-Range", format!("bytes {}-{}/{}", start, end, len)));
+            (206, start, end)
+        }
+        Range::Unsatisfiable => {
+            headers.push(("Content-Range", format!("bytes */{}", len)));
+            return Ok(Response { status: 416, headers, body: Vec::new() });
+        }
+    };
+
+    let body = if len == 0 { Vec::new() } else { read(&item, start, end, store)? };
+    headers.push(("Content-Length", body.len().to_string()));
+    Ok(Response { status, headers, body })
+}
+
+/// Bytes `start..=end` of an attachment. From a vault folder only they are
+/// read, after making sure the file really is inside attachments/.
+fn read(item: &Item, start: u64, end: u64, store: &dyn VaultStore) -> Result<Vec<u8>, String> {
+    let Some(files) = store.files() else {
+        let bytes = store.read(item)?.ok_or("Attachment disappeared")?;
+        return Ok(bytes.get(start as usize..=end as usize).unwrap_or_default().to_vec());
+    };
+
+    let path = fs::canonicalize(files.file(item)).map_err(|e| e.to_string())?;
+    let dir = fs::canonicalize(files.dir().join(ATTACHMENTS_DIR)).map_err(|e| e.to_string())?;
+    if !path.starts_with(&dir) {
+        return Err(format!("{} leads outside the vault", path.display()));
+    }
+
+    let mut file = File::open(&path).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(start)).map_err(|e| e.to_string())?;
+    let mut body = Vec::with_capacity((end - start + 1) as usize);
+    file.take(end - start + 1).read_to_end(&mut body).map_err(|e| e.to_string())?;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{FsStore, MemoryStore};
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn reads_uris_and_ranges() {
+        let hash = attachments::hash(b"x");
+        assert_eq!(hash_in(&attachments::uri(&hash)), Some(hash.as_str()));
+        assert_eq!(hash_in(&format!("https://messy.localhost/attachment/{}?t=1", hash)), Some(hash.as_str()));
+        assert_eq!(hash_in("messy://attachment/../../etc/passwd"), None);
+        assert_eq!(hash_in(&format!("messy://other/{}", hash)), None);
+
+        assert_eq!(range(None, 100), Range::Full);
+        assert_eq!(range(Some("bytes=0-9"), 100), Range::Part(0, 9));
+        assert_eq!(range(Some("bytes=90-"), 100), Range::Part(90, 99));
+        assert_eq!(range(Some("bytes=-10"), 100), Range::Part(90, 99));
+        assert_eq!(range(Some("bytes=50-500, 0-1"), 100), Range::Part(50, 99));
+        assert_eq!(range(Some("bytes=100-"), 100), Range::Unsatisfiable);
+        assert_eq!(range(Some("lines=1-2"), 100), Range::Full);
+        assert_eq!(range(Some("bytes=0-"), 10 * CHUNK), Range::Part(0, CHUNK - 1));
+    }
+
+    #[test]
+    fn serves_attachments_with_types_and_ranges() {
+        let store = MemoryStore::default();
+        let (hash, _) = attachments::import(b"0123456789", "clip.mp4", &store).unwrap();
+        let uri = attachments::uri(&hash);
+
+        let whole = serve(&uri, None, &store);
+        assert_eq!(whole.status, 200);
+        assert_eq!(whole.body, b"0123456789");
+        assert!(whole.headers.contains(&("Content-Type", "video/mp4".to_string())));
+
+        let part = serve(&uri, Some("bytes=2-4"), &store);
+        assert_eq!(part.status, 206);
+        assert_eq!(part.body, b"234");
+        assert!(part.headers.contains(&("Content-Range", "bytes 2-4/10".to_string())));
+
+        assert_eq!(serve(&uri, Some("bytes=10-"), &store).status, 416);
+        assert_eq!(serve(&attachments::uri(&attachments::hash(b"other")), None, &store).status, 404);
+    }
+
+    #[test]
+    fn reads_ranges_from_a_vault_folder() {
+        let dir = TempDir::new("protocol");
+        let store = FsStore::new(dir.0.clone());
+        store.ensure_dirs().unwrap();
+        let (hash, _) = attachments::import(b"hello world", "greeting.txt", &store).unwrap();
+
+        let part = serve(&attachments::uri(&hash), Some("bytes=6-"), &store);
+        assert_eq!(part.status, 206);
+        assert_eq!(part.body, b"world");
+        assert!(part.headers.contains(&("Content-Type", "text/plain".to_string())));
+    }
+}
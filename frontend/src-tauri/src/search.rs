@@ -0,0 +1,803 @@
+//! ====== FULL-TEXT SEARCH ======
+//!
+//! An inverted index over every note's title, plain text (rawText) and
+//! canvas card text (node `label`/`text`), kept in .search/index.json.
+//!
+//!   - text is split into lowercase alphanumeric words, and English words
+//!     are stemmed (Porter step 1) so "running" finds "run"
+//!   - a query is a list of words, all of which must match; "quoted
+//!     words" must appear next to each other; the last word also matches
+//!     as a prefix so results update while typing
+//!   - hits are ranked with BM25, title matches weighing more than body
+//!     and canvas matches
+//!   - each hit comes with a snippet around the first match, split into
+//!     highlighted and plain parts
+//!
+//! The on-disk index is a cache: every document carries a stamp of what
+//! it was built from, and only stale or missing documents are reindexed
+//! when the index is loaded.
+//!
+//! ==============================
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::canvas::Canvas;
+use crate::durable;
+use crate::note_id::NoteId;
+
+/// Bumped when the tokenizer or on-disk layout changes; older indexes are
+/// rebuilt from scratch
+const INDEX_VERSION: u32 = 1;
+
+/// How much more a word in the title counts than one in the body
+const TITLE_BOOST: f64 = 3.0;
+
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// Words shown before the first match, and in total, in a snippet
+const SNIPPET_CONTEXT: usize = 8;
+const SNIPPET_WORDS: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Title,
+    Body,
+    Canvas,
+}
+
+const FIELDS: [Field; 3] = [Field::Title, Field::Body, Field::Canvas];
+
+/// The searchable text of one note
+pub struct SearchDoc {
+    pub id: NoteId,
+    pub title: String,
+    pub body: String,
+    pub canvas: String,
+    /// Changes whenever the text does; unchanged documents aren't reindexed
+    pub stamp: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchHit {
+    pub id: NoteId,
+    pub title: String,
+    pub score: f64,
+    /// Where the snippet was taken from
+    pub field: Field,
+    pub snippet: Vec<SnippetPart>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlight: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DocEntry {
+    title: String,
+    body: String,
+    canvas: String,
+    stamp: String,
+    /// Number of words across all fields
+    length: u32,
+}
+
+impl DocEntry {
+    fn text(&self, field: Field) -> &str {
+        match field {
+            Field::Title => &self.title,
+            Field::Body => &self.body,
+            Field::Canvas => &self.canvas,
+        }
+    }
+}
+
+/// Word positions of one term in one note, per field
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Positions {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    title: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    body: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    canvas: Vec<u32>,
+}
+
+impl Positions {
+    fn field(&self, field: Field) -> &[u32] {
+        match field {
+            Field::Title => &self.title,
+            Field::Body => &self.body,
+            Field::Canvas => &self.canvas,
+        }
+    }
+
+    fn field_mut(&mut self, field: Field) -> &mut Vec<u32> {
+        match field {
+            Field::Title => &mut self.title,
+            Field::Body => &mut self.body,
+            Field::Canvas => &mut self.canvas,
+        }
+    }
+
+    fn counts(&self) -> Counts {
+        FIELDS.map(|f| self.field(f).len() as u32)
+    }
+}
+
+/// Matches of a query clause in one note, per field (title, body, canvas)
+type Counts = [u32; 3];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    docs: HashMap<NoteId, DocEntry>,
+    /// term -> note -> positions
+    postings: HashMap<String, HashMap<NoteId, Positions>>,
+    /// Changed since last written to disk
+    #[serde(skip)]
+    dirty: bool,
+    #[serde(skip)]
+    saved_at: Option<Instant>,
+}
+
+impl Default for SearchIndex {
+    fn default() -> Self {
+        SearchIndex {
+            version: INDEX_VERSION,
+            docs: HashMap::new(),
+            postings: HashMap::new(),
+            dirty: false,
+            saved_at: None,
+        }
+    }
+}
+
+impl SearchIndex {
+    /// Reads the index from disk; a missing, unreadable or outdated index
+    /// loads as empty and gets rebuilt
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SearchIndex>(&content).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+
+        let json = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize search index: {}", e))?;
+        durable::write(path, json).map_err(|e| e.to_string())?;
+
+        self.dirty = false;
+        self.saved_at = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Writes the index if it changed and wasn't written in the last
+    /// `interval`, so autosave doesn't rewrite it on every keystroke pause
+    pub fn save_if_due(&mut self, path: &Path, interval: Duration) -> Result<(), String> {
+        let due = self.saved_at.is_none_or(|t| t.elapsed() >= interval);
+        if self.dirty && due {
+            self.save(path)?;
+        }
+        Ok(())
+    }
+
+    pub fn ids(&self) -> Vec<NoteId> {
+        self.docs.keys().cloned().collect()
+    }
+
+    /// The stamp a note's document was indexed with
+    pub fn stamp(&self, id: &NoteId) -> Option<&str> {
+        self.docs.get(id).map(|d| d.stamp.as_str())
+    }
+
+    /// Adds or replaces a note's document
+    pub fn update(&mut self, doc: SearchDoc) {
+        if let Some(entry) = self.docs.get_mut(&doc.id) {
+            if entry.title == doc.title && entry.body == doc.body && entry.canvas == doc.canvas {
+                if entry.stamp != doc.stamp {
+                    entry.stamp = doc.stamp;
+                    self.dirty = true;
+                }
+                return;
+            }
+        }
+        self.remove(&doc.id);
+
+        let entry = DocEntry {
+            title: doc.title,
+            body: doc.body,
+            canvas: doc.canvas,
+            stamp: doc.stamp,
+            length: 0,
+        };
+
+        let mut length = 0;
+        for field in FIELDS {
+            for (position, token) in tokenize(entry.text(field)).into_iter().enumerate() {
+                self.postings
+                    .entry(token.term)
+                    .or_default()
+                    .entry(doc.id.clone())
+                    .or_default()
+                    .field_mut(field)
+                    .push(position as u32);
+                length += 1;
+            }
+        }
+
+        self.docs.insert(doc.id, DocEntry { length, ..entry });
+        self.dirty = true;
+    }
+
+    pub fn remove(&mut self, id: &NoteId) {
+        let Some(entry) = self.docs.remove(id) else {
+            return;
+        };
+
+        let terms: HashSet<String> = FIELDS.iter()
+            .flat_map(|f| tokenize(entry.text(*f)))
+            .map(|t| t.term)
+            .collect();
+
+        for term in terms {
+            if let Some(notes) = self.postings.get_mut(&term) {
+                notes.remove(id);
+                if notes.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Notes matching every clause of `query`, best first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let clauses = parse_query(query);
+        if clauses.is_empty() || self.docs.is_empty() {
+            return Vec::new();
+        }
+
+        let matches: Vec<HashMap<&NoteId, Counts>> = clauses.iter().map(|c| self.matches(c)).collect();
+
+        let total = self.docs.len() as f64;
+        let average_length = self.docs.values().map(|d| d.length as f64).sum::<f64>() / total;
+
+        // Every clause has to match, so the rarest one bounds the candidates
+        let Some(rarest) = matches.iter().min_by_key(|m| m.len()) else {
+            return Vec::new();
+        };
+
+        let mut hits: Vec<SearchHit> = rarest.keys()
+            .filter(|id| matches.iter().all(|m| m.contains_key(*id)))
+            .filter_map(|id| {
+                let doc = self.docs.get(*id)?;
+                let length_norm = 1.0 - B + B * doc.length as f64 / average_length.max(1.0);
+
+                let score = matches.iter()
+                    .map(|m| {
+                        let counts = m[*id];
+                        let tf = counts[0] as f64 * TITLE_BOOST + counts[1] as f64 + counts[2] as f64;
+                        let df = m.len() as f64;
+                        let idf = (1.0 + (total - df + 0.5) / (df + 0.5)).ln();
+                        idf * tf * (K1 + 1.0) / (tf + K1 * length_norm)
+                    })
+                    .sum();
+
+                // Snippet from the body if it matched there, else the canvas
+                let field = [Field::Body, Field::Canvas]
+                    .into_iter()
+                    .find(|f| matches.iter().any(|m| m[*id][*f as usize] > 0))
+                    .unwrap_or(Field::Title);
+                let snippet_text = match field {
+                    Field::Title => &doc.body,
+                    other => doc.text(other),
+                };
+
+                Some(SearchHit {
+                    id: (*id).clone(),
+                    title: doc.title.clone(),
+                    score,
+                    field,
+                    snippet: snippet(snippet_text, |token| clauses.iter().any(|c| c.highlights(token))),
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.score.partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.title.cmp(&b.title))
+        });
+        hits.truncate(limit);
+        hits
+    }
+
+    /// Per-note match counts of one query clause
+    fn matches(&self, clause: &Clause) -> HashMap<&NoteId, Counts> {
+        let mut found: HashMap<&NoteId, Counts> = HashMap::new();
+
+        match clause {
+            Clause::Term(term) => {
+                if let Some(notes) = self.postings.get(term) {
+                    for (id, positions) in notes {
+                        found.insert(id, positions.counts());
+                    }
+                }
+            }
+            Clause::Prefix { .. } => {
+                for (term, notes) in &self.postings {
+                    if !clause.matches_term(term) {
+                        continue;
+                    }
+                    for (id, positions) in notes {
+                        let counts = found.entry(id).or_default();
+                        for (total, n) in counts.iter_mut().zip(positions.counts()) {
+                            *total += n;
+                        }
+                    }
+                }
+            }
+            Clause::Phrase(terms) => {
+                let postings: Option<Vec<&HashMap<NoteId, Positions>>> =
+                    terms.iter().map(|t| self.postings.get(t)).collect();
+                let Some(postings) = postings else {
+                    return found;
+                };
+
+                for (id, first) in postings[0] {
+                    let Some(rest) = postings[1..].iter().map(|p| p.get(id)).collect::<Option<Vec<_>>>() else {
+                        continue;
+                    };
+
+                    let counts = FIELDS.map(|field| {
+                        first.field(field).iter()
+                            .filter(|&&start| {
+                                rest.iter().enumerate().all(|(offset, positions)| {
+                                    positions.field(field).binary_search(&(start + offset as u32 + 1)).is_ok()
+                                })
+                            })
+                            .count() as u32
+                    });
+                    if counts.iter().any(|&n| n > 0) {
+                        found.insert(id, counts);
+                    }
+                }
+            }
+        }
+
+        found
+    }
+}
+
+/// Searchable text of a canvas: the label and text of every node,
+/// including nodes nested in groups
+pub fn canvas_text(canvas: &Canvas) -> String {
+    canvas.all_nodes()
+        .iter()
+        .flat_map(|node| [node.label.as_str(), node.text.as_str()])
+        .filter(|text| !text.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// ==================== QUERIES ====================
+
+#[derive(Debug, PartialEq)]
+enum Clause {
+    Term(String),
+    /// The word being typed: matches terms starting with it
+    Prefix { word: String, stem: String },
+    Phrase(Vec<String>),
+}
+
+impl Clause {
+    fn matches_term(&self, term: &str) -> bool {
+        match self {
+            Clause::Term(t) => t == term,
+            Clause::Prefix { word, stem } => term.starts_with(word.as_str()) || term.starts_with(stem.as_str()),
+            Clause::Phrase(terms) => terms.iter().any(|t| t == term),
+        }
+    }
+
+    fn highlights(&self, token: &Token) -> bool {
+        self.matches_term(&token.term)
+    }
+}
+
+fn parse_query(query: &str) -> Vec<Clause> {
+    let mut clauses = Vec::new();
+
+    // Odd segments are inside quotes; an unclosed quote runs to the end
+    for (i, segment) in query.split('"').enumerate() {
+        let tokens = tokenize(segment);
+        if i % 2 == 1 && tokens.len() > 1 {
+            clauses.push(Clause::Phrase(tokens.into_iter().map(|t| t.term).collect()));
+        } else {
+            clauses.extend(tokens.into_iter().map(|t| Clause::Term(t.term)));
+        }
+    }
+
+    // Still typing the last word unless it was followed by a space or quote
+    let typing = query.chars().last().is_some_and(|c| c.is_alphanumeric());
+    if typing && query.matches('"').count().is_multiple_of(2) {
+        if let Some(Clause::Term(stem)) = clauses.pop() {
+            let word = tokenize(query).pop().map(|t| t.word).unwrap_or_default();
+            clauses.push(Clause::Prefix { word, stem });
+        }
+    }
+
+    clauses
+}
+
+// ==================== SNIPPETS ====================
+
+/// About SNIPPET_WORDS words of `text` starting a little before the first
+/// highlighted word, whitespace collapsed, with "…" where text was cut
+fn snippet(text: &str, highlight: impl Fn(&Token) -> bool) -> Vec<SnippetPart> {
+    let tokens = tokenize(text);
+    if tokens.is_empty() {
+        return Vec::new();
+    }
+
+    let first = tokens.iter().position(&highlight).unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_CONTEXT);
+    let end = (start + SNIPPET_WORDS).min(tokens.len());
+    let from = if start == 0 { 0 } else { tokens[start].start };
+    let to = if end == tokens.len() { text.len() } else { tokens[end - 1].end };
+
+    let mut parts: Vec<SnippetPart> = Vec::new();
+    let mut push = |text: &str, highlight: bool| {
+        let text = collapse_whitespace(text);
+        if text.is_empty() {
+            return;
+        }
+        match parts.last_mut() {
+            Some(last) if last.highlight == highlight => last.text.push_str(&text),
+            _ => parts.push(SnippetPart { text, highlight }),
+        }
+    };
+
+    if start > 0 {
+        push("…", false);
+    }
+    let mut cursor = from;
+    for token in &tokens[start..end] {
+        if highlight(token) {
+            push(&text[cursor..token.start], false);
+            push(&text[token.start..token.end], true);
+            cursor = token.end;
+        }
+    }
+    push(&text[cursor..to], false);
+    if end < tokens.len() {
+        push("…", false);
+    }
+
+    if let Some(first) = parts.first_mut() {
+        first.text = first.text.trim_start().to_string();
+    }
+    if let Some(last) = parts.last_mut() {
+        last.text = last.text.trim_end().to_string();
+    }
+    parts.retain(|p| !p.text.is_empty());
+    parts
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_space = false;
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_space {
+                collapsed.push(' ');
+            }
+            in_space = true;
+        } else {
+            collapsed.push(c);
+            in_space = false;
+        }
+    }
+
+    collapsed
+}
+
+// ==================== TOKENIZER ====================
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    /// Stemmed, lowercase form that goes into the index
+    term: String,
+    /// Lowercase word as written
+    word: String,
+    /// Byte range in the source text
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word_start = None;
+
+    let mut push = |start: usize, end: usize| {
+        let word = text[start..end].to_lowercase();
+        tokens.push(Token { term: stem(&word), word, start, end });
+    };
+
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            word_start.get_or_insert(i);
+        } else if let Some(start) = word_start.take() {
+            push(start, i);
+        }
+    }
+    if let Some(start) = word_start {
+        push(start, text.len());
+    }
+
+    tokens
+}
+
+/// Porter stemmer, step 1 (plurals, -ed, -ing, -y): enough to fold the
+/// common inflections of English words without over-merging. Words that
+/// aren't plain ASCII letters are left alone.
+fn stem(word: &str) -> String {
+    if word.len() <= 2 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+        return word.to_string();
+    }
+    let mut w = word.as_bytes().to_vec();
+
+    // Step 1a
+    if w.ends_with(b"sses") || w.ends_with(b"ies") {
+        w.truncate(w.len() - 2);
+    } else if !w.ends_with(b"ss") && w.ends_with(b"s") {
+        w.pop();
+    }
+
+    // Step 1b
+    if w.ends_with(b"eed") {
+        if measure(&w[..w.len() - 3]) > 0 {
+            w.pop();
+        }
+    } else {
+        let suffix = [&b"ed"[..], &b"ing"[..]]
+            .into_iter()
+            .find(|s| w.ends_with(s) && has_vowel(&w[..w.len() - s.len()]));
+
+        if let Some(suffix) = suffix {
+            w.truncate(w.len() - suffix.len());
+
+            if w.ends_with(b"at") || w.ends_with(b"bl") || w.ends_with(b"iz") {
+                w.push(b'e');
+            } else if ends_with_double_consonant(&w) && !matches!(w.last(), Some(b'l' | b's' | b'z')) {
+                w.pop();
+            } else if measure(&w) == 1 && ends_cvc(&w) {
+                w.push(b'e');
+            }
+        }
+    }
+
+    // Step 1c
+    if w.ends_with(b"y") && has_vowel(&w[..w.len() - 1]) {
+        let last = w.len() - 1;
+        w[last] = b'i';
+    }
+
+    String::from_utf8(w).unwrap_or_else(|_| word.to_string())
+}
+
+fn is_consonant(w: &[u8], i: usize) -> bool {
+    match w[i] {
+        b'a' | b'e' | b'i' | b'o' | b'u' => false,
+        b'y' => i == 0 || !is_consonant(w, i - 1),
+        _ => true,
+    }
+}
+
+fn has_vowel(w: &[u8]) -> bool {
+    (0..w.len()).any(|i| !is_consonant(w, i))
+}
+
+/// Number of vowel-consonant sequences: [C](VC){m}[V]
+fn measure(w: &[u8]) -> usize {
+    let mut m = 0;
+    let mut previous_vowel = false;
+
+    for i in 0..w.len() {
+        let vowel = !is_consonant(w, i);
+        if previous_vowel && !vowel {
+            m += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    m
+}
+
+fn ends_with_double_consonant(w: &[u8]) -> bool {
+    let n = w.len();
+    n >= 2 && w[n - 1] == w[n - 2] && is_consonant(w, n - 1)
+}
+
+/// Consonant-vowel-consonant ending, the last not w, x or y ("hop", "fil")
+fn ends_cvc(w: &[u8]) -> bool {
+    let n = w.len();
+    n >= 3
+        && is_consonant(w, n - 3)
+        && !is_consonant(w, n - 2)
+        && is_consonant(w, n - 1)
+        && !matches!(w[n - 1], b'w' | b'x' | b'y')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(id: &str, title: &str, body: &str, canvas: &str) -> SearchDoc {
+        SearchDoc {
+            id: NoteId::parse(id).unwrap(),
+            title: title.to_string(),
+            body: body.to_string(),
+            canvas: canvas.to_string(),
+            stamp: format!("{}{}{}", title, body, canvas),
+        }
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter().map(|h| h.id.as_str()).collect()
+    }
+
+    fn highlighted(hit: &SearchHit) -> Vec<&str> {
+        hit.snippet.iter().filter(|p| p.highlight).map(|p| p.text.as_str()).collect()
+    }
+
+    #[test]
+    fn stems_common_inflections() {
+        for (word, expected) in [
+            ("caresses", "caress"), ("ponies", "poni"), ("cats", "cat"), ("feed", "feed"),
+            ("agreed", "agree"), ("plastered", "plaster"), ("motoring", "motor"), ("sing", "sing"),
+            ("hopping", "hop"), ("filing", "file"), ("happy", "happi"), ("running", "run"),
+        ] {
+            assert_eq!(stem(word), expected, "{}", word);
+        }
+        assert_eq!(stem("café"), "café");
+        assert_eq!(stem("2026"), "2026");
+    }
+
+    #[test]
+    fn tokenizes_words_with_offsets() {
+        let tokens = tokenize("Hello, wörld! snake_case 42");
+        let words: Vec<&str> = tokens.iter().map(|t| t.word.as_str()).collect();
+        assert_eq!(words, ["hello", "wörld", "snake", "case", "42"]);
+        assert_eq!(&"Hello, wörld!"[tokens[1].start..tokens[1].end], "wörld");
+    }
+
+    #[test]
+    fn parses_terms_phrases_and_prefix() {
+        assert_eq!(parse_query("running dogs "), vec![
+            Clause::Term("run".into()),
+            Clause::Term("dog".into()),
+        ]);
+        assert_eq!(parse_query("\"big idea\" plan"), vec![
+            Clause::Phrase(vec!["big".into(), "idea".into()]),
+            Clause::Prefix { word: "plan".into(), stem: "plan".into() },
+        ]);
+        assert!(parse_query("  ,. ").is_empty());
+    }
+
+    #[test]
+    fn finds_body_and_canvas_text() {
+        let mut index = SearchIndex::default();
+        index.update(doc("a", "Groceries", "Buy apples and pears", ""));
+        index.update(doc("b", "Mindmap", "", "Apples grow on trees"));
+        index.update(doc("c", "Other", "Nothing here", ""));
+
+        let hits = index.search("apple ", 10);
+        let mut found = ids(&hits);
+        found.sort();
+        assert_eq!(found, ["a", "b"]);
+        assert_eq!(index.search("trees ", 10)[0].field, Field::Canvas);
+    }
+
+    #[test]
+    fn all_words_must_match() {
+        let mut index = SearchIndex::default();
+        index.update(doc("a", "One", "red apples", ""));
+        index.update(doc("b", "Two", "green apples", ""));
+
+        assert_eq!(ids(&index.search("green apples ", 10)), ["b"]);
+        assert!(index.search("blue apples ", 10).is_empty());
+    }
+
+    #[test]
+    fn title_matches_rank_first() {
+        let mut index = SearchIndex::default();
+        index.update(doc("body", "Notes", "something about rust and more words here", ""));
+        index.update(doc("title", "Rust", "something about other things and more words", ""));
+
+        assert_eq!(ids(&index.search("rust ", 10)), ["title", "body"]);
+    }
+
+    #[test]
+    fn phrases_need_adjacent_words() {
+        let mut index = SearchIndex::default();
+        index.update(doc("a", "A", "the big idea is simple", ""));
+        index.update(doc("b", "B", "a big and simple idea", ""));
+
+        assert_eq!(ids(&index.search("\"big idea\"", 10)), ["a"]);
+        assert_eq!(ids(&index.search("\"big ideas\"", 10)), ["a"]);
+    }
+
+    #[test]
+    fn last_word_matches_as_prefix() {
+        let mut index = SearchIndex::default();
+        index.update(doc("a", "Architecture", "", ""));
+
+        assert_eq!(ids(&index.search("archi", 10)), ["a"]);
+        assert!(index.search("archi ", 10).is_empty());
+    }
+
+    #[test]
+    fn snippets_highlight_matches() {
+        let mut index = SearchIndex::default();
+        let body = format!("{} the deadline moved to friday {}", "filler ".repeat(20), "tail ".repeat(20));
+        index.update(doc("a", "Plans", &body, ""));
+
+        let hit = &index.search("deadline friday", 10)[0];
+        assert_eq!(highlighted(hit), ["deadline", "friday"]);
+        assert_eq!(hit.snippet.first().unwrap().text.chars().next(), Some('…'));
+        assert!(hit.snippet.last().unwrap().text.ends_with('…'));
+    }
+
+    #[test]
+    fn updates_replace_and_remove_documents() {
+        let mut index = SearchIndex::default();
+        index.update(doc("a", "Note", "first draft", ""));
+        index.update(doc("a", "Note", "second version", ""));
+
+        assert!(index.search("draft ", 10).is_empty());
+        assert_eq!(ids(&index.search("version ", 10)), ["a"]);
+
+        index.remove(&NoteId::parse("a").unwrap());
+        assert!(index.search("version ", 10).is_empty());
+        assert!(index.postings.is_empty());
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let mut index = SearchIndex::default();
+        index.update(doc("a", "Saved", "persisted body", ""));
+
+        let loaded: SearchIndex = serde_json::from_str(&serde_json::to_string(&index).unwrap()).unwrap();
+        assert_eq!(ids(&loaded.search("persisted ", 10)), ["a"]);
+    }
+
+    #[test]
+    fn canvas_text_includes_nested_nodes() {
+        let node = |id: &str, label: &str, text: &str| serde_json::json!({
+            "id": id, "type": "card", "x": 0, "y": 0, "width": 10, "height": 10, "label": label, "text": text
+        });
+        let mut group = node("1", "Group", "");
+        group["children"] = serde_json::json!([node("2", "", "inner card")]);
+        group["children"][0]["parentId"] = serde_json::json!("1");
+        let canvas: Canvas = serde_json::from_value(serde_json::json!({
+            "nodes": [group, node("3", "Top", "level")]
+        })).unwrap();
+        assert_eq!(canvas_text(&canvas), "Group\ninner card\nTop\nlevel");
+    }
+}
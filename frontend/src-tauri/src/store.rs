@@ -0,0 +1,620 @@
+//! ====== VAULT STORE ======
+//!
+//! Where a vault's notes, folders, graph, canvases, attachments, settings,
+//! trash and revision history are kept. Commands go through a
+//! `VaultStore` instead of std::fs:
+//!
+//!   FsStore      the folder layout described in lib.rs; what the app and
+//!                messy-cli use
+//!   MemoryStore  everything in a map; for tests
+//!
+//! Items are named by where FsStore keeps them (`Item::path`), so reports
+//! read the same whichever store is behind them.
+//!
+//! The search index and image previews are caches kept as plain files
+//! next to what the store holds. Only a vault on disk has them; without
+//! one, search starts from scratch and previews are made every time.
+//!
+//! ========================
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::durable;
+use crate::history::RevisionKind;
+use crate::note_id::NoteId;
+use crate::trash::TrashFile;
+
+const NOTES_DIR: &str = "notes";
+const CANVAS_DIR: &str = "canvas";
+pub const ATTACHMENTS_DIR: &str = "attachments";
+const TRASH_DIR: &str = ".trash";
+const HISTORY_DIR: &str = ".history";
+/// The caches lib.rs keeps beside the store, also written durably
+const CACHE_DIRS: [&str; 2] = [".search", ".previews"];
+
+/// Something a vault stores
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Item {
+    /// notes/{id}.md
+    Note(NoteId),
+    /// canvas/{id}.json
+    Canvas(NoteId),
+    /// folders.json
+    Folders,
+    /// graph.json
+    Graph,
+    /// attachments/{name}
+    Attachment(String),
+    /// vault.json
+    Config,
+    /// .trash/{id}/note.md, canvas.json or entry.json
+    Trashed(NoteId, TrashFile),
+    /// .history/{id}/note/{revision}.md or canvas/{revision}.json
+    Revision(NoteId, RevisionKind, String),
+}
+
+impl Item {
+    /// Where the item is kept, relative to the vault folder
+    pub fn path(&self) -> PathBuf {
+        match self {
+            Item::Note(id) => Path::new(NOTES_DIR).join(format!("{}.md", id)),
+            Item::Canvas(id) => Path::new(CANVAS_DIR).join(format!("{}.json", id)),
+            Item::Folders => PathBuf::from("folders.json"),
+            Item::Graph => PathBuf::from("graph.json"),
+            Item::Attachment(name) => Path::new(ATTACHMENTS_DIR).join(name),
+            Item::Config => PathBuf::from("vault.json"),
+            Item::Trashed(id, file) => Path::new(TRASH_DIR).join(id.as_str()).join(file.file_name()),
+            Item::Revision(id, kind, revision) => Path::new(HISTORY_DIR)
+                .join(id.as_str())
+                .join(kind.dir_name())
+                .join(format!("{}.{}", revision, kind.extension())),
+        }
+    }
+}
+
+pub trait VaultStore: Send + Sync {
+    /// The item's contents, or None if it isn't there
+    fn read(&self, item: &Item) -> Result<Option<Vec<u8>>, String>;
+
+    fn exists(&self, item: &Item) -> bool;
+
+    /// The item's size in bytes, or None if it isn't there
+    fn size(&self, item: &Item) -> Result<Option<u64>, String>;
+
+    /// Writes several items as one change: if any write fails, the ones
+    /// already written are put back the way they were
+    fn write_all(&self, items: &[(Item, Vec<u8>)]) -> Result<(), String>;
+
+    /// Removes an item; removing one that isn't there is not an error
+    fn remove(&self, item: &Item) -> Result<(), String>;
+
+    /// Ids of the notes in the store
+    fn notes(&self) -> Result<Vec<NoteId>, String>;
+
+    /// Ids of the notes that have a canvas
+    fn canvases(&self) -> Result<Vec<NoteId>, String>;
+
+    /// Names of the attachments in the store
+    fn attachments(&self) -> Result<Vec<String>, String>;
+
+    /// Ids of the notes with anything in the trash
+    fn trashed(&self) -> Result<Vec<NoteId>, String>;
+
+    /// Ids of a note's revisions of one kind, oldest first
+    fn revisions(&self, id: &NoteId, kind: RevisionKind) -> Result<Vec<String>, String>;
+
+    /// When the item was last written, if the store knows
+    fn modified(&self, _item: &Item) -> Result<Option<DateTime<Utc>>, String> {
+        Ok(None)
+    }
+
+    /// Moves an item's contents to another item, e.g. a note into the
+    /// trash. Fails if there is nothing to move.
+    fn rename(&self, from: &Item, to: &Item) -> Result<(), String> {
+        let contents = self.read(from)?.ok_or_else(|| format!("{} is missing", from.path().display()))?;
+        self.write(to, &contents)?;
+        self.remove(from)
+    }
+
+    fn write(&self, item: &Item, contents: &[u8]) -> Result<(), String> {
+        self.write_all(&[(item.clone(), contents.to_vec())])
+    }
+
+    /// The item's contents as text
+    fn read_string(&self, item: &Item) -> Result<Option<String>, String> {
+        match self.read(item)? {
+            Some(bytes) => String::from_utf8(bytes)
+                .map(Some)
+                .map_err(|_| format!("{} is not valid UTF-8", item.path().display())),
+            None => Ok(None),
+        }
+    }
+
+    /// The store as files in a vault folder, if it is one
+    fn files(&self) -> Option<&FsStore> {
+        None
+    }
+}
+
+// ==================== FILES ====================
+
+/// A vault folder on disk. Every write is durable (see durable.rs) and
+/// remembered, so the watcher can tell the app's own changes from edits
+/// made outside it.
+pub struct FsStore {
+    dir: PathBuf,
+    /// What the app itself last left at each path it wrote or removed
+    /// (content fingerprint, None = removed)
+    own_changes: Mutex<HashMap<PathBuf, Option<u64>>>,
+}
+
+impl FsStore {
+    pub fn new(dir: PathBuf) -> Self {
+        FsStore { dir, own_changes: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Where an item is on disk
+    pub fn file(&self, item: &Item) -> PathBuf {
+        self.dir.join(item.path())
+    }
+
+    /// Ensures all required directories exist
+    pub fn ensure_dirs(&self) -> Result<()> {
+        for dir in ["", NOTES_DIR, ATTACHMENTS_DIR, CANVAS_DIR] {
+            fs::create_dir_all(self.dir.join(dir))?;
+        }
+        Ok(())
+    }
+
+    /// Cleans up temp files left behind by writes interrupted by a crash.
+    /// Returns how many were removed.
+    pub fn recover_interrupted_writes(&self) -> Result<usize> {
+        let mut dirs = self.item_dirs().map_err(anyhow::Error::msg)?;
+        dirs.extend(CACHE_DIRS.map(|d| self.dir.join(d)));
+
+        let mut removed = 0;
+        for dir in dirs {
+            removed += durable::recover(&dir)
+                .with_context(|| format!("Failed to recover {}", dir.display()))?
+                .len();
+        }
+        Ok(removed)
+    }
+
+    /// Every folder items are written to (see Item::path), including the
+    /// ones under .trash/ and .history/ for each note that has them
+    fn item_dirs(&self) -> Result<Vec<PathBuf>, String> {
+        let any = NoteId::new();
+        let mut items = vec![
+            Item::Config,
+            Item::Note(any.clone()),
+            Item::Canvas(any),
+            Item::Attachment("any".to_string()),
+        ];
+        for id in self.note_dirs(TRASH_DIR)? {
+            items.push(Item::Trashed(id, TrashFile::Entry));
+        }
+        for id in self.note_dirs(HISTORY_DIR)? {
+            for kind in [RevisionKind::Note, RevisionKind::Canvas] {
+                items.push(Item::Revision(id.clone(), kind, String::new()));
+            }
+        }
+
+        Ok(items.iter()
+            .filter_map(|item| self.file(item).parent().map(Path::to_path_buf))
+            .collect())
+    }
+
+    /// Ids of the per-note folders in `root` (.trash/ or .history/)
+    fn note_dirs(&self, root: &str) -> Result<Vec<NoteId>, String> {
+        let dir = self.dir.join(root);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut ids = Vec::new();
+        for entry in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if let Some(id) = path.file_name().and_then(|s| s.to_str()).and_then(|s| NoteId::parse(s).ok()) {
+                if path.is_dir() {
+                    ids.push(id);
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Durably writes a file in the vault, remembering it as the app's own
+    /// change
+    pub fn write_file(&self, path: &Path, contents: impl AsRef<[u8]>) -> Result<(), String> {
+        let contents = contents.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.exists()) {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        durable::write(path, contents).map_err(|e| e.to_string())?;
+        self.record_own_change(path, Some(fingerprint(contents)));
+        Ok(())
+    }
+
+    /// Moves a file within the vault (same filesystem), remembering both
+    /// ends as the app's own change
+    pub fn move_file(&self, from: &Path, to: &Path) -> Result<(), String> {
+        if let Some(dir) = to.parent().filter(|dir| !dir.exists()) {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::rename(from, to).map_err(|e| e.to_string())?;
+        self.record_own_change(from, None);
+        if let Ok(bytes) = fs::read(to) {
+            self.record_own_change(to, Some(fingerprint(&bytes)));
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<(), String> {
+        match fs::remove_file(path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.to_string()),
+        }
+        self.record_own_change(path, None);
+        Ok(())
+    }
+
+    /// Removes the folders a removal left empty, e.g. .trash/{id}/, up to
+    /// (not including) the vault's own top-level ones
+    fn remove_empty_dirs(&self, path: &Path) {
+        let mut dir = path.parent();
+        while let Some(current) = dir.filter(|d| d.parent().is_some_and(|p| p.starts_with(&self.dir) && p != self.dir)) {
+            if fs::remove_dir(current).is_err() {
+                break;
+            }
+            dir = current.parent();
+        }
+    }
+
+    fn record_own_change(&self, path: &Path, fingerprint: Option<u64>) {
+        if let Ok(mut changes) = self.own_changes.lock() {
+            changes.insert(path.to_path_buf(), fingerprint);
+        }
+    }
+
+    /// True if `path` still holds exactly what the app last wrote there
+    /// (or is still gone after the app removed it)
+    pub fn is_own_change(&self, path: &Path) -> bool {
+        let current = fs::read(path).ok().map(|bytes| fingerprint(&bytes));
+        self.own_changes.lock()
+            .map(|changes| changes.get(path) == Some(&current))
+            .unwrap_or(false)
+    }
+
+    /// Files in one of the vault's folders with the given extension (any if
+    /// None), by file stem; temp files from interrupted writes are skipped
+    fn list(&self, dir: impl AsRef<Path>, extension: Option<&str>) -> Result<Vec<String>, String> {
+        let dir = self.dir.join(dir);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            if !path.is_file() || durable::is_temp_file(&path) {
+                continue;
+            }
+
+            let name = match extension {
+                Some(ext) if path.extension().and_then(|s| s.to_str()) == Some(ext) => path.file_stem(),
+                Some(_) => None,
+                None => path.file_name(),
+            };
+            if let Some(name) = name.and_then(|n| n.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+impl VaultStore for FsStore {
+    fn read(&self, item: &Item) -> Result<Option<Vec<u8>>, String> {
+        match fs::read(self.file(item)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", item.path().display(), e)),
+        }
+    }
+
+    fn exists(&self, item: &Item) -> bool {
+        self.file(item).exists()
+    }
+
+    fn size(&self, item: &Item) -> Result<Option<u64>, String> {
+        match fs::metadata(self.file(item)) {
+            Ok(metadata) => Ok(Some(metadata.len())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", item.path().display(), e)),
+        }
+    }
+
+    fn write_all(&self, items: &[(Item, Vec<u8>)]) -> Result<(), String> {
+        let paths: Vec<PathBuf> = items.iter().map(|(item, _)| self.file(item)).collect();
+        let originals: Vec<Option<Vec<u8>>> = paths.iter().map(|path| fs::read(path).ok()).collect();
+
+        for (written, (path, (item, contents))) in paths.iter().zip(items).enumerate() {
+            let Err(e) = self.write_file(path, contents) else {
+                continue;
+            };
+
+            for (path, original) in paths[..written].iter().zip(&originals).rev() {
+                let rolled_back = match original {
+                    Some(bytes) => self.write_file(path, bytes),
+                    None => self.remove_file(path),
+                };
+                if let Err(e) = rolled_back {
+                    eprintln!("Failed to roll back {}: {}", path.display(), e);
+                }
+            }
+            return Err(format!("Failed to write {}: {}", item.path().display(), e));
+        }
+
+        Ok(())
+    }
+
+    fn remove(&self, item: &Item) -> Result<(), String> {
+        let path = self.file(item);
+        self.remove_file(&path)?;
+        self.remove_empty_dirs(&path);
+        Ok(())
+    }
+
+    fn notes(&self) -> Result<Vec<NoteId>, String> {
+        // Files whose name isn't a valid id are not notes the app can address
+        Ok(self.list(NOTES_DIR, Some("md"))?.iter().filter_map(|name| NoteId::parse(name).ok()).collect())
+    }
+
+    fn canvases(&self) -> Result<Vec<NoteId>, String> {
+        Ok(self.list(CANVAS_DIR, Some("json"))?.iter().filter_map(|name| NoteId::parse(name).ok()).collect())
+    }
+
+    fn attachments(&self) -> Result<Vec<String>, String> {
+        self.list(ATTACHMENTS_DIR, None)
+    }
+
+    fn trashed(&self) -> Result<Vec<NoteId>, String> {
+        self.note_dirs(TRASH_DIR)
+    }
+
+    fn revisions(&self, id: &NoteId, kind: RevisionKind) -> Result<Vec<String>, String> {
+        self.list(Path::new(HISTORY_DIR).join(id.as_str()).join(kind.dir_name()), Some(kind.extension()))
+    }
+
+    fn modified(&self, item: &Item) -> Result<Option<DateTime<Utc>>, String> {
+        match fs::metadata(self.file(item)).and_then(|metadata| metadata.modified()) {
+            Ok(time) => Ok(Some(time.into())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read {}: {}", item.path().display(), e)),
+        }
+    }
+
+    fn rename(&self, from: &Item, to: &Item) -> Result<(), String> {
+        let from_path = self.file(from);
+        self.move_file(&from_path, &self.file(to))
+            .map_err(|e| format!("Failed to move {}: {}", from.path().display(), e))?;
+        self.remove_empty_dirs(&from_path);
+        Ok(())
+    }
+
+    fn files(&self) -> Option<&FsStore> {
+        Some(self)
+    }
+}
+
+/// Cheap content hash used to recognize the app's own writes
+pub fn fingerprint(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+// ==================== MEMORY ====================
+
+/// A vault that lives only as long as the value does
+#[derive(Default)]
+pub struct MemoryStore {
+    items: Mutex<BTreeMap<Item, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    fn items(&self) -> Result<std::sync::MutexGuard<'_, BTreeMap<Item, Vec<u8>>>, String> {
+        self.items.lock().map_err(|e| e.to_string())
+    }
+}
+
+impl VaultStore for MemoryStore {
+    fn read(&self, item: &Item) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.items()?.get(item).cloned())
+    }
+
+    fn exists(&self, item: &Item) -> bool {
+        self.items().is_ok_and(|items| items.contains_key(item))
+    }
+
+    fn size(&self, item: &Item) -> Result<Option<u64>, String> {
+        Ok(self.items()?.get(item).map(|bytes| bytes.len() as u64))
+    }
+
+    fn write_all(&self, items: &[(Item, Vec<u8>)]) -> Result<(), String> {
+        self.items()?.extend(items.iter().cloned());
+        Ok(())
+    }
+
+    fn remove(&self, item: &Item) -> Result<(), String> {
+        self.items()?.remove(item);
+        Ok(())
+    }
+
+    fn notes(&self) -> Result<Vec<NoteId>, String> {
+        Ok(self.items()?.keys()
+            .filter_map(|item| match item {
+                Item::Note(id) => Some(id.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn canvases(&self) -> Result<Vec<NoteId>, String> {
+        Ok(self.items()?.keys()
+            .filter_map(|item| match item {
+                Item::Canvas(id) => Some(id.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn attachments(&self) -> Result<Vec<String>, String> {
+        Ok(self.items()?.keys()
+            .filter_map(|item| match item {
+                Item::Attachment(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn trashed(&self) -> Result<Vec<NoteId>, String> {
+        let mut ids: Vec<NoteId> = self.items()?.keys()
+            .filter_map(|item| match item {
+                Item::Trashed(id, _) => Some(id.clone()),
+                _ => None,
+            })
+            .collect();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    fn revisions(&self, id: &NoteId, kind: RevisionKind) -> Result<Vec<String>, String> {
+        Ok(self.items()?.keys()
+            .filter_map(|item| match item {
+                Item::Revision(note, k, revision) if note == id && *k == kind => Some(revision.clone()),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    /// What both stores must do the same way
+    fn exercise(store: &dyn VaultStore) {
+        let a = NoteId::parse("a").unwrap();
+        let b = NoteId::parse("b").unwrap();
+
+        assert_eq!(store.read(&Item::Note(a.clone())).unwrap(), None);
+        store.write_all(&[
+            (Item::Note(b.clone()), b"two".to_vec()),
+            (Item::Note(a.clone()), b"one".to_vec()),
+            (Item::Canvas(a.clone()), b"{}".to_vec()),
+            (Item::Attachment("cat.png".to_string()), vec![0x89, 0x50]),
+        ]).unwrap();
+        store.write(&Item::Folders, b"[]").unwrap();
+
+        assert_eq!(store.read_string(&Item::Note(a.clone())).unwrap().as_deref(), Some("one"));
+        assert_eq!(store.notes().unwrap(), [a.clone(), b.clone()]);
+        assert_eq!(store.canvases().unwrap(), std::slice::from_ref(&a));
+        assert_eq!(store.attachments().unwrap(), ["cat.png"]);
+        assert_eq!(store.size(&Item::Attachment("cat.png".to_string())).unwrap(), Some(2));
+        assert!(store.exists(&Item::Folders) && !store.exists(&Item::Graph));
+        assert!(store.read_string(&Item::Attachment("cat.png".to_string())).is_err());
+
+        store.remove(&Item::Note(b.clone())).unwrap();
+        store.remove(&Item::Note(b)).unwrap();
+        assert_eq!(store.notes().unwrap(), std::slice::from_ref(&a));
+
+        // Into the trash and back
+        let trashed = Item::Trashed(a.clone(), TrashFile::Note);
+        store.rename(&Item::Note(a.clone()), &trashed).unwrap();
+        assert!(store.notes().unwrap().is_empty());
+        assert_eq!(store.trashed().unwrap(), std::slice::from_ref(&a));
+        store.rename(&trashed, &Item::Note(a.clone())).unwrap();
+        assert!(store.trashed().unwrap().is_empty());
+        assert!(store.rename(&trashed, &Item::Note(a.clone())).is_err());
+
+        for revision in ["20261017T093000.000Z", "20261016T093000.000Z"] {
+            store.write(&Item::Revision(a.clone(), RevisionKind::Note, revision.to_string()), b"old").unwrap();
+        }
+        assert_eq!(store.revisions(&a, RevisionKind::Note).unwrap(), ["20261016T093000.000Z", "20261017T093000.000Z"]);
+        assert!(store.revisions(&a, RevisionKind::Canvas).unwrap().is_empty());
+    }
+
+    #[test]
+    fn memory_store_keeps_items() {
+        exercise(&MemoryStore::default());
+    }
+
+    #[test]
+    fn fs_store_keeps_items_in_the_vault_layout() {
+        let dir = TempDir::new("store");
+        let store = FsStore::new(dir.0.clone());
+        store.ensure_dirs().unwrap();
+        exercise(&store);
+
+        let note = dir.0.join("notes").join("a.md");
+        assert_eq!(fs::read_to_string(&note).unwrap(), "one");
+        assert!(store.is_own_change(&note));
+        assert!(store.is_own_change(&dir.0.join("notes").join("b.md")));
+
+        // Not an id, or not a note: not listed
+        fs::write(dir.0.join("notes").join("not an id.md"), "").unwrap();
+        fs::write(dir.0.join("notes").join("c.txt"), "").unwrap();
+        assert_eq!(store.notes().unwrap().len(), 1);
+        assert!(!store.is_own_change(&dir.0.join("notes").join("c.txt")));
+
+        // Writes interrupted anywhere the store or its caches write
+        let stray = format!(".x.{}.tmp", NoteId::new().as_str().replace('-', ""));
+        let dirs = ["", "notes", "canvas", "attachments", ".trash/a", ".history/a/note", ".history/a/canvas", ".search", ".previews"];
+        for sub in dirs {
+            fs::create_dir_all(dir.0.join(sub)).unwrap();
+            fs::write(dir.0.join(sub).join(&stray), "").unwrap();
+        }
+        assert_eq!(store.recover_interrupted_writes().unwrap(), dirs.len());
+        assert_eq!(store.revisions(&NoteId::parse("a").unwrap(), RevisionKind::Note).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn fs_store_rolls_back_a_failed_write() {
+        let dir = TempDir::new("store");
+        let store = FsStore::new(dir.0.clone());
+        store.ensure_dirs().unwrap();
+        let a = Item::Note(NoteId::parse("a").unwrap());
+        store.write(&a, b"before").unwrap();
+
+        // A directory where the graph file should be makes its write fail
+        fs::create_dir_all(dir.0.join("graph.json")).unwrap();
+        let canvas = Item::Canvas(NoteId::parse("a").unwrap());
+        let result = store.write_all(&[
+            (a.clone(), b"after".to_vec()),
+            (canvas.clone(), b"{}".to_vec()),
+            (Item::Graph, b"{}".to_vec()),
+        ]);
+
+        assert!(result.unwrap_err().contains("graph.json"));
+        assert_eq!(store.read(&a).unwrap().as_deref(), Some(&b"before"[..]));
+        assert!(!store.exists(&canvas));
+    }
+}
@@ -0,0 +1,175 @@
+//! ====== TAGS ======
+//!
+//! A note's tags are the `tags` list in its frontmatter plus the #hashtags
+//! in its body. Tags nest with slashes: a note tagged #project/alpha is
+//! also found under #project.
+//!
+//! Tags are stored without the '#' and compared case-insensitively. A tag
+//! is letters, digits, '_' and '-', not all digits, so "#1" in "issue #1"
+//! isn't one.
+//!
+//! ==================
+
+use std::ops::Range;
+
+/// A tag as written, cleaned up: no leading '#' and no empty segments.
+/// None if it isn't a valid tag.
+pub fn normalize(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#');
+    let segments: Vec<&str> = tag.split('/').filter(|s| !s.is_empty()).collect();
+
+    let valid = !segments.is_empty()
+        && segments.iter().all(|s| s.chars().all(is_tag_char))
+        && !segments.iter().all(|s| s.chars().all(|c| c.is_ascii_digit()));
+
+    valid.then(|| segments.join("/"))
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Comparison key for a tag
+pub fn key(tag: &str) -> String {
+    tag.to_lowercase()
+}
+
+/// True if `tag` is `parent` or nested somewhere under it
+pub fn is_within(tag: &str, parent: &str) -> bool {
+    let tag: Vec<String> = tag.split('/').map(key).collect();
+    let parent: Vec<String> = parent.split('/').map(key).collect();
+    tag.starts_with(&parent)
+}
+
+/// `tag` and every tag it is nested in: a/b/c gives a, a/b and a/b/c
+pub fn with_parents(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices('/')
+        .map(move |(i, _)| &tag[..i])
+        .chain(std::iter::once(tag))
+}
+
+/// `tag` moved from `from` to `to`, keeping whatever is nested below:
+/// renaming project to work turns project/alpha into work/alpha. None if
+/// `tag` isn't within `from`.
+pub fn renamed(tag: &str, from: &str, to: &str) -> Option<String> {
+    if !is_within(tag, from) {
+        return None;
+    }
+
+    let nested = tag.split('/').skip(from.split('/').count());
+    Some(std::iter::once(to).chain(nested).collect::<Vec<_>>().join("/"))
+}
+
+/// Every #hashtag in plain text with its byte range, '#' included
+pub fn spans(text: &str) -> Vec<(String, Range<usize>)> {
+    let mut found = Vec::new();
+    let mut previous: Option<char> = None;
+
+    for (i, c) in text.char_indices() {
+        // Not part of a word, a URL fragment, an entity or a [[link#heading]]
+        let at_boundary = !previous.is_some_and(|p| {
+            is_tag_char(p) || matches!(p, '#' | '&' | '/' | '[' | '\\')
+        });
+        previous = Some(c);
+
+        if c != '#' || !at_boundary {
+            continue;
+        }
+
+        let rest = &text[i + 1..];
+        let len = rest.char_indices()
+            .find(|(_, c)| !is_tag_char(*c) && *c != '/')
+            .map(|(j, _)| j)
+            .unwrap_or(rest.len());
+        let written = rest[..len].trim_end_matches('/');
+
+        if let Some(tag) = normalize(written) {
+            found.push((tag, i..i + 1 + written.len()));
+        }
+    }
+
+    found
+}
+
+/// The distinct #hashtags in plain text, in order of first appearance
+pub fn extract(text: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    for (tag, _) in spans(text) {
+        push_unique(&mut tags, tag);
+    }
+    tags
+}
+
+/// Adds `tag` unless a tag with the same key is already there
+pub fn push_unique(tags: &mut Vec<String>, tag: String) {
+    if !tags.iter().any(|t| key(t) == key(&tag)) {
+        tags.push(tag);
+    }
+}
+
+/// Replaces every #hashtag that `rename` maps to a new tag. Returns the
+/// new text and how many hashtags changed.
+pub fn rewrite(text: &str, rename: &dyn Fn(&str) -> Option<String>) -> (String, usize) {
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut count = 0;
+
+    for (tag, range) in spans(text) {
+        let Some(new_tag) = rename(&tag) else {
+            continue;
+        };
+        out.push_str(&text[copied..range.start]);
+        out.push('#');
+        out.push_str(&new_tag);
+        copied = range.end;
+        count += 1;
+    }
+
+    out.push_str(&text[copied..]);
+    (out, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(normalize("#Project/Alpha"), Some("Project/Alpha".to_string()));
+        assert_eq!(normalize(" a//b/ "), Some("a/b".to_string()));
+        assert_eq!(normalize("2024/10"), None);
+        assert_eq!(normalize("two words"), None);
+        assert_eq!(normalize("#"), None);
+    }
+
+    #[test]
+    fn extracts_hashtags_but_not_lookalikes() {
+        let text = "#idea and #project/alpha, also #Idea.\n\
+                    issue #12, C#, x.com/#anchor, &#39; [[Note#Heading]] [[#Heading]]\n\
+                    # Heading and #todo/";
+        assert_eq!(extract(text), ["idea", "project/alpha", "todo"]);
+    }
+
+    #[test]
+    fn nested_tags_are_within_their_parents() {
+        assert!(is_within("project/alpha", "Project"));
+        assert!(is_within("project", "project"));
+        assert!(!is_within("projects", "project"));
+        assert!(!is_within("project", "project/alpha"));
+        assert_eq!(with_parents("a/b/c").collect::<Vec<_>>(), ["a", "a/b", "a/b/c"]);
+    }
+
+    #[test]
+    fn renames_keep_nested_parts() {
+        assert_eq!(renamed("project/alpha", "project", "work"), Some("work/alpha".to_string()));
+        assert_eq!(renamed("Project", "project", "work/old"), Some("work/old".to_string()));
+        assert_eq!(renamed("other", "project", "work"), None);
+    }
+
+    #[test]
+    fn rewrites_hashtags_in_text() {
+        let (text, count) = rewrite("#project and #project/alpha but #projects", &|tag| renamed(tag, "project", "work"));
+        assert_eq!(text, "#work and #work/alpha but #projects");
+        assert_eq!(count, 2);
+    }
+}
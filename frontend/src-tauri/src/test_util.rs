@@ -0,0 +1,35 @@
+//! ====== TEST UTILITIES ======
+//!
+//! Helpers shared by the unit tests of several modules.
+//!
+//! ============================
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::note_id::NoteId;
+
+/// A fresh folder under the system's temp dir, removed when dropped
+pub(crate) struct TempDir(pub PathBuf);
+
+impl TempDir {
+    /// `name` only makes leftovers easier to tell apart
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("messy-{}-{}", name, NoteId::new()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    /// Writes `contents` to `path` inside the folder, making its parents
+    pub fn write(&self, path: &str, contents: &[u8]) {
+        let file = self.0.join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, contents).unwrap();
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,203 @@
+//! ====== TRASH ======
+//!
+//! Deleting a note moves it into the vault's trash instead of removing it:
+//!
+//!   .trash/
+//!   └── {id}/
+//!       ├── note.md        ← the note file, untouched
+//!       ├── canvas.json    ← its canvas, if it had one
+//!       └── entry.json     ← deletion time, plus the graph node and edges
+//!                            the note took with it
+//!
+//! Restoring moves the files back and puts the graph node and edges back.
+//! Entries older than the vault's retention period are purged on startup.
+//! Nothing under the hidden .trash/ is ever read as a live note. The files
+//! are items of the vault's store (see store.rs), like the notes were.
+//!
+//! ===================
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::note_id::NoteId;
+use crate::graph::Edge;
+use crate::store::{Item, VaultStore};
+
+/// The files of one trash entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TrashFile {
+    Note,
+    Canvas,
+    Entry,
+}
+
+impl TrashFile {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            TrashFile::Note => "note.md",
+            TrashFile::Canvas => "canvas.json",
+            TrashFile::Entry => "entry.json",
+        }
+    }
+}
+
+/// A trashed note, as stored in .trash/{id}/entry.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrashEntry {
+    pub id: NoteId,
+    pub title: String,
+    #[serde(rename = "deletedAt")]
+    pub deleted_at: String,
+    #[serde(rename = "folderId", skip_serializing_if = "Option::is_none")]
+    pub folder_id: Option<String>,
+    /// The note's node in graph.json, if it had one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<serde_json::Value>,
+    /// Graph edges that touched the note when it was deleted
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+}
+
+pub fn note_item(id: &NoteId) -> Item {
+    Item::Trashed(id.clone(), TrashFile::Note)
+}
+
+pub fn canvas_item(id: &NoteId) -> Item {
+    Item::Trashed(id.clone(), TrashFile::Canvas)
+}
+
+fn entry_item(id: &NoteId) -> Item {
+    Item::Trashed(id.clone(), TrashFile::Entry)
+}
+
+/// True if a note with this id is sitting in the trash
+pub fn contains(store: &dyn VaultStore, id: &NoteId) -> bool {
+    store.exists(&note_item(id))
+}
+
+pub fn read_entry(store: &dyn VaultStore, id: &NoteId) -> Result<TrashEntry, String> {
+    let content = store.read_string(&entry_item(id))
+        .map_err(|e| format!("Failed to read trash entry {}: {}", id, e))?
+        .ok_or_else(|| format!("Failed to read trash entry {}: it is missing", id))?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse trash entry {}: {}", id, e))
+}
+
+pub fn write_entry(store: &dyn VaultStore, entry: &TrashEntry) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(entry)
+        .map_err(|e| format!("Failed to serialize trash entry: {}", e))?;
+
+    store.write(&entry_item(&entry.id), json.as_bytes())
+}
+
+/// Every trashed note, most recently deleted first. Entries that can't be
+/// read are skipped rather than hiding the rest of the trash.
+pub fn list(store: &dyn VaultStore) -> Result<Vec<TrashEntry>, String> {
+    let mut entries = Vec::new();
+
+    for id in store.trashed()? {
+        // An entry without its note is a delete that never finished
+        if !contains(store, &id) {
+            continue;
+        }
+
+        match read_entry(store, &id) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => eprintln!("Skipping trashed note {}: {}", id, e),
+        }
+    }
+
+    entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(entries)
+}
+
+/// Permanently deletes one trashed note
+pub fn remove(store: &dyn VaultStore, id: &NoteId) -> Result<(), String> {
+    for file in [TrashFile::Note, TrashFile::Canvas, TrashFile::Entry] {
+        store.remove(&Item::Trashed(id.clone(), file))?;
+    }
+    Ok(())
+}
+
+/// Permanently deletes every entry deleted more than `retention_days` ago.
+/// Returns the ids that were purged.
+pub fn purge(store: &dyn VaultStore, retention_days: u32, now: DateTime<Utc>) -> Result<Vec<NoteId>, String> {
+    let cutoff = now - Duration::days(retention_days as i64);
+    let mut purged = Vec::new();
+
+    for entry in list(store)? {
+        let expired = DateTime::parse_from_rfc3339(&entry.deleted_at)
+            .map(|deleted_at| deleted_at < cutoff)
+            .unwrap_or(false);
+
+        if expired {
+            remove(store, &entry.id)?;
+            purged.push(entry.id);
+        }
+    }
+
+    Ok(purged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{FsStore, MemoryStore};
+    use crate::test_util::TempDir;
+
+    fn trash(store: &dyn VaultStore, id: &str, deleted_at: DateTime<Utc>) -> NoteId {
+        let id = NoteId::parse(id).unwrap();
+        write_entry(store, &TrashEntry {
+            id: id.clone(),
+            title: id.to_string(),
+            deleted_at: deleted_at.to_rfc3339(),
+            folder_id: None,
+            node: None,
+            edges: vec![],
+        }).unwrap();
+        store.write(&note_item(&id), b"---\ntitle: x\n---\n\n").unwrap();
+        id
+    }
+
+    #[test]
+    fn lists_most_recent_first() {
+        let store = MemoryStore::default();
+        let now = Utc::now();
+        trash(&store, "older", now - Duration::days(2));
+        trash(&store, "newer", now);
+
+        let ids: Vec<String> = list(&store).unwrap().into_iter().map(|e| e.id.to_string()).collect();
+        assert_eq!(ids, ["newer", "older"]);
+    }
+
+    #[test]
+    fn skips_entries_without_a_note() {
+        let store = MemoryStore::default();
+        let id = trash(&store, "half-deleted", Utc::now());
+        store.remove(&note_item(&id)).unwrap();
+
+        assert!(list(&store).unwrap().is_empty());
+    }
+
+    #[test]
+    fn purges_only_expired_entries() {
+        let dir = TempDir::new("trash");
+        let store = FsStore::new(dir.0.clone());
+        let now = Utc::now();
+        let old = trash(&store, "old", now - Duration::days(31));
+        let recent = trash(&store, "recent", now - Duration::days(29));
+
+        assert_eq!(purge(&store, 30, now).unwrap(), vec![old.clone()]);
+        assert!(!dir.0.join(".trash").join("old").exists());
+        assert!(contains(&store, &recent));
+    }
+
+    #[test]
+    fn missing_trash_is_empty() {
+        let dir = TempDir::new("trash");
+        let store = FsStore::new(dir.0.clone());
+        assert!(list(&store).unwrap().is_empty());
+        assert!(purge(&store, 0, Utc::now()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+//! ====== VAULTS ======
+//!
+//! A vault is a folder laid out as described in lib.rs. The app can know
+//! several (a work vault, a personal one, one on an encrypted volume) and
+//! has one open at a time. The list lives outside any vault, in the app's
+//! config dir:
+//!
+//!   vaults.json
+//!   {
+//!     "current": "/home/me/Documents/MessyNotes",
+//!     "vaults": [
+//!       { "path": "/home/me/Documents/MessyNotes", "name": "MessyNotes",
+//!         "lastOpened": "2026-10-17T09:30:00+00:00" }
+//!     ]
+//!   }
+//!
+//! Paths are stored canonicalized, so one folder is only listed once
+//! however it was picked.
+//!
+//! ===================
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::durable;
+
+pub const SETTINGS_FILE: &str = "vaults.json";
+
+/// The bundle identifier from tauri.conf.json, which names the app's
+/// config dir
+const APP_IDENTIFIER: &str = "com.messynotes.app";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Settings {
+    /// The vault opened last, and again at startup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<PathBuf>,
+    #[serde(default)]
+    pub vaults: Vec<Vault>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Vault {
+    pub path: PathBuf,
+    pub name: String,
+    #[serde(rename = "lastOpened", default, skip_serializing_if = "Option::is_none")]
+    pub last_opened: Option<String>,
+}
+
+/// A known vault, as list_vaults reports it
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultInfo {
+    #[serde(flatten)]
+    pub vault: Vault,
+    /// True for the vault the app has open
+    pub current: bool,
+    /// False if the folder is gone, e.g. on a volume that isn't mounted
+    pub available: bool,
+}
+
+impl Settings {
+    pub fn find(&self, path: &Path) -> Option<&Vault> {
+        let path = canonical(path);
+        self.vaults.iter().find(|v| v.path == path)
+    }
+
+    /// Adds the vault at `path` if it isn't listed yet and makes it the
+    /// current one
+    pub fn remember(&mut self, path: &Path, opened_at: &str) {
+        let path = canonical(path);
+
+        match self.vaults.iter_mut().find(|v| v.path == path) {
+            Some(vault) => vault.last_opened = Some(opened_at.to_string()),
+            None => self.vaults.push(Vault {
+                name: path.file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string()),
+                path: path.clone(),
+                last_opened: Some(opened_at.to_string()),
+            }),
+        }
+        self.current = Some(path);
+    }
+
+    /// Every known vault, most recently opened first
+    pub fn list(&self, open: &Path) -> Vec<VaultInfo> {
+        let open = canonical(open);
+        let mut vaults: Vec<VaultInfo> = self.vaults.iter()
+            .map(|vault| VaultInfo {
+                vault: vault.clone(),
+                current: vault.path == open,
+                available: vault.path.is_dir(),
+            })
+            .collect();
+        vaults.sort_by(|a, b| b.vault.last_opened.cmp(&a.vault.last_opened));
+        vaults
+    }
+}
+
+/// Where the list of known vaults is kept: vaults.json in the app's config
+/// dir (the same one Tauri gives the app)
+pub fn settings_file() -> Result<PathBuf, String> {
+    let config_dir = dirs_next::config_dir().ok_or("Failed to get config directory")?;
+    Ok(config_dir.join(APP_IDENTIFIER).join(SETTINGS_FILE))
+}
+
+/// The vault opened when none is known yet: ~/Documents/MessyNotes/
+pub fn default_dir() -> Result<PathBuf, String> {
+    let document_dir = dirs_next::document_dir().ok_or("Failed to get documents directory")?;
+    Ok(document_dir.join("MessyNotes"))
+}
+
+/// The vault to open at startup: the one open last, unless its folder has
+/// gone missing (e.g. an unmounted volume), then the default one
+pub fn startup_dir() -> Result<PathBuf, String> {
+    let settings = settings_file()
+        .and_then(|file| load(&file))
+        .unwrap_or_else(|e| {
+            eprintln!("Not reading the vault list: {}", e);
+            Settings::default()
+        });
+
+    match settings.current {
+        Some(dir) if dir.is_dir() => Ok(dir),
+        Some(dir) => {
+            eprintln!("Vault {} is not available, opening the default vault", dir.display());
+            default_dir()
+        }
+        None => default_dir(),
+    }
+}
+
+/// Whether `dir` holds a vault: one with a notes/ folder or a vault.json
+pub fn is_vault(dir: &Path) -> bool {
+    dir.join("notes").is_dir() || dir.join("vault.json").is_file()
+}
+
+/// Whether `dir` is a folder with nothing in it
+pub fn is_empty(dir: &Path) -> bool {
+    fs::read_dir(dir).map(|mut entries| entries.next().is_none()).unwrap_or(false)
+}
+
+/// The path as the filesystem resolves it, or as given if it doesn't exist
+pub fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Reads the settings, or none if there is no file yet
+pub fn load(file: &Path) -> Result<Settings, String> {
+    if !file.exists() {
+        return Ok(Settings::default());
+    }
+
+    let content = fs::read_to_string(file).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", file.display(), e))
+}
+
+pub fn save(file: &Path, settings: &Settings) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize vault settings: {}", e))?;
+
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    durable::write(file, json).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn remembering_a_vault_lists_it_once_and_makes_it_current() {
+        let work = std::env::temp_dir().join("messy-vaults-work");
+        let home = std::env::temp_dir().join("messy-vaults-home");
+        let mut settings = Settings::default();
+
+        settings.remember(&work, "2026-10-01T00:00:00Z");
+        settings.remember(&home, "2026-10-02T00:00:00Z");
+        settings.remember(&work.join("."), "2026-10-03T00:00:00Z");
+
+        assert_eq!(settings.vaults.len(), 2);
+        assert_eq!(settings.current, Some(canonical(&work)));
+        assert_eq!(settings.find(&home).map(|v| v.name.as_str()), Some("messy-vaults-home"));
+
+        let listed = settings.list(&work);
+        assert_eq!(listed[0].vault.path, canonical(&work));
+        assert!(listed[0].current && !listed[1].current);
+        assert!(!listed[1].available);
+    }
+
+    #[test]
+    fn only_vaults_and_empty_folders_count_as_vaults() {
+        let dir = TempDir::new("vaults");
+        assert!(is_empty(&dir.0) && !is_vault(&dir.0));
+
+        dir.write("Documents/report.pdf", b"");
+        assert!(!is_empty(&dir.0) && !is_vault(&dir.0));
+
+        dir.write("vault.json", b"{}");
+        assert!(is_vault(&dir.0));
+        assert!(!is_empty(&dir.0.join("missing")));
+    }
+
+    #[test]
+    fn settings_round_trip_through_the_file() {
+        let dir = TempDir::new("vaults");
+        let file = dir.0.join(SETTINGS_FILE);
+
+        assert!(load(&file).unwrap().vaults.is_empty());
+
+        let mut settings = Settings::default();
+        settings.remember(Path::new("/vaults/work"), "2026-10-01T00:00:00Z");
+        save(&file, &settings).unwrap();
+
+        let loaded = load(&file).unwrap();
+        assert_eq!(loaded.vaults, settings.vaults);
+        assert_eq!(loaded.current, Some(PathBuf::from("/vaults/work")));
+    }
+}
@@ -0,0 +1,134 @@
+//! ====== VAULT WATCHER ======
+//!
+//! The vault is plain files, so it also gets edited from outside the app
+//! (vim, Syncthing, scripts). A background watcher on notes/, canvas/ and
+//! the vault root picks those edits up, refreshes the note index and tells
+//! the webview through Tauri events:
+//!
+//!   note-changed     Note           a note file was created or edited
+//!   note-deleted     { id }         a note file disappeared
+//!   folders-changed  ()             folders.json was replaced
+//!   canvas-changed   { noteId }     canvas/{id}.json was replaced
+//!   graph-changed    ()             graph.json was replaced
+//!
+//! Events are debounced so an editor's save dance (temp file, rename,
+//! chmod) arrives as one change. Writes the app made itself are recognized
+//! by the content fingerprint `FsStore::write_file` records and skipped
+//! (`AppState::is_own_change`).
+//!
+//! ===========================
+
+use notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use messy_notes::durable;
+use messy_notes::note_id::NoteId;
+use messy_notes::{note_written, parse_note, sync_wikilinks, AppState};
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub type VaultWatcher = Debouncer<RecommendedWatcher>;
+
+/// Starts watching the vault of the app's `AppState`. Events stop when the
+/// returned watcher is dropped.
+pub fn start(app: AppHandle) -> notify::Result<VaultWatcher> {
+    let state = app.state::<AppState>();
+    let dirs = [
+        state.data_dir(),
+        state.notes_dir(),
+        state.canvas_dir(),
+    ];
+
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| {
+        match result {
+            Ok(events) => {
+                // A burst can report the same path several times
+                let paths: BTreeSet<PathBuf> = events.into_iter().map(|e| e.path).collect();
+                for path in paths {
+                    handle_change(&app, &path);
+                }
+            }
+            Err(e) => eprintln!("Vault watcher error: {}", e),
+        }
+    })?;
+
+    // Non-recursive: folders.json and graph.json sit in the vault root next
+    // to notes/ and canvas/, which get their own watches
+    for dir in dirs {
+        debouncer.watcher().watch(&dir, RecursiveMode::NonRecursive)?;
+    }
+
+    Ok(debouncer)
+}
+
+fn handle_change(app: &AppHandle, path: &Path) {
+    if durable::is_temp_file(path) {
+        return;
+    }
+
+    let state = app.state::<AppState>();
+    if state.is_own_change(path) {
+        return;
+    }
+
+    let parent = path.parent();
+    let extension = path.extension().and_then(|s| s.to_str());
+
+    let result = if parent == Some(state.notes_dir().as_path()) && extension == Some("md") {
+        match file_id(path) {
+            Some(id) => note_changed(app, &state, id, path),
+            None => Ok(()),
+        }
+    } else if parent == Some(state.canvas_dir().as_path()) && extension == Some("json") {
+        match file_id(path) {
+            Some(id) => {
+                state.canvas_changed(&id);
+                emit(app, "canvas-changed", json!({ "noteId": id }))
+            }
+            None => Ok(()),
+        }
+    } else if path == state.folders_file() {
+        emit(app, "folders-changed", ())
+    } else if path == state.graph_file() {
+        emit(app, "graph-changed", ())
+    } else {
+        Ok(())
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to handle change to {}: {}", path.display(), e);
+    }
+}
+
+/// Re-reads a note after an outside change and updates the index
+fn note_changed(app: &AppHandle, state: &AppState, id: NoteId, path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        // Links to it may now resolve to another note with the same title
+        state.unindex_note(&id);
+        sync_wikilinks(state, None)?;
+        return emit(app, "note-deleted", json!({ "id": id }));
+    }
+
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let note = parse_note(id, &content)?;
+    note_written(&note, state)?;
+
+    emit(app, "note-changed", note)
+}
+
+/// The note id a vault file is named after, if it is a valid one
+fn file_id(path: &Path) -> Option<NoteId> {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|s| NoteId::parse(s).ok())
+}
+
+fn emit<S: serde::Serialize + Clone>(app: &AppHandle, event: &str, payload: S) -> Result<(), String> {
+    app.emit_all(event, payload).map_err(|e| e.to_string())
+}
@@ -2,6 +2,7 @@ import React, { useEffect, useState } from 'react';
 import { useNavigate } from 'react-router-dom';
 import { Folder, FileText, Brain, Network, Sparkles, Trash2, AlertTriangle } from 'lucide-react';
 import { useNotes } from '../contexts/NotesContext';
+import FileService from '../services/FileService';
 
 export default function Dashboard() {
   const navigate = useNavigate();
@@ -18,6 +19,13 @@ export default function Dashboard() {
   const [showDeleteAllModal, setShowDeleteAllModal] = useState(false);
   const [deleteAllConfirm, setDeleteAllConfirm] = useState('');
   const [isDeletingAll, setIsDeletingAll] = useState(false);
+  const [totalLinks, setTotalLinks] = useState(0);
+
+  useEffect(() => {
+    FileService.getLinks().then(links => {
+      setTotalLinks(links.filter(link => link.target).length);
+    });
+  }, [lastSync]);
 
   useEffect(() => {
     const now = Date.now();
@@ -54,7 +62,7 @@ export default function Dashboard() {
       await deleteAllNotes();
       setShowDeleteAllModal(false);
       setDeleteAllConfirm('');
-      alert(`Successfully deleted all notes`);
+      alert(`Moved all notes to the trash`);
     } catch (error) {
       alert('Failed to delete all notes: ' + error.message);
     } finally {
@@ -63,7 +71,6 @@ export default function Dashboard() {
   };
 
   const recentNotes = notes.slice(0, 8);
-  const totalLinks = 0;
 
   return (
     <div className="min-h-screen theme-bg-primary theme-text-primary overflow-auto">
@@ -229,7 +236,7 @@ export default function Dashboard() {
               </div>
               <div>
                 <h3 className="text-xl font-bold theme-text-primary">Delete All Notes?</h3>
-                <p className="text-sm text-theme-secondary">This action cannot be undone</p>
+                <p className="text-sm text-theme-secondary">Notes are moved to the trash and can be restored</p>
               </div>
             </div>
 
@@ -348,6 +348,35 @@ const RichTextToolbar = ({ onCommand }) => {
   );
 };
 
+const ATTACHMENT_PREFIX = 'messy://attachment/';
+
+// Attached images show as a lightweight thumbnail made by the backend, so
+// big photos don't slow the canvas down. Other URLs load as they are, and
+// so does an attachment without a preview.
+const MediaImage = ({ url }) => {
+  const isAttachment = url.startsWith(ATTACHMENT_PREFIX);
+  const [src, setSrc] = useState(isAttachment ? null : url);
+
+  useEffect(() => {
+    if (!isAttachment) {
+      setSrc(url);
+      return;
+    }
+
+    let cancelled = false;
+    setSrc(null);
+    FileService.getAttachmentPreview(url.slice(ATTACHMENT_PREFIX.length))
+      .then(preview => { if (!cancelled) setSrc(preview.thumbnail); })
+      .catch(() => { if (!cancelled) setSrc(FileService.attachmentSrc(url)); });
+    return () => { cancelled = true; };
+  }, [url]);
+
+  if (!src) {
+    return <div className="w-full h-full rounded bg-black/10 animate-pulse" />;
+  }
+  return <img src={src} alt="Media" className="w-full h-full object-cover rounded pointer-events-none" />;
+};
+
 const NodeContentEditor = ({ initialHtml, onChange, className, editorRef }) => {
   useEffect(() => {
     if (editorRef.current) {
@@ -529,7 +558,7 @@ const detectLeidenCommunities = (nodes, edges) => {
 
 // --- GRAPH VIEW COMPONENT ---
 const GraphView = ({ onNoteClick }) => {
-  const { notes, createNote, deleteNote, updateNoteLocal } = useNotes();
+  const { notes, createNote, deleteNote, renameNote } = useNotes();
   
   const [graphData, setGraphData] = useState({ metadata: {}, edges: [] });
   const [viewport, setViewport] = useState(() => ViewportStorage.get());
@@ -1178,14 +1207,8 @@ const GraphView = ({ onNoteClick }) => {
                   onBlur={async (e) => {
                     const newLabel = e.target.value.trim();
                     if (newLabel && newLabel !== node.label) {
-                      try {
-                        // Update context immediately
-                        updateNoteLocal(node.id, { title: newLabel });
-                        // Save to file in background
-                        await FileService.updateNote(node.id, { title: newLabel });
-                      } catch (error) {
-                        console.error('Failed to rename note:', error);
-                      }
+                      // Also rewrites [[links]] to the old title
+                      await renameNote(node.id, newLabel);
                     }
                     setRenamingNodeId(null);
                   }}
@@ -1195,14 +1218,7 @@ const GraphView = ({ onNoteClick }) => {
                       e.stopPropagation();
                       const newLabel = e.target.value.trim();
                       if (newLabel && newLabel !== node.label) {
-                        try {
-                          // Update context immediately
-                          updateNoteLocal(node.id, { title: newLabel });
-                          // Save to file in background
-                          await FileService.updateNote(node.id, { title: newLabel });
-                        } catch (error) {
-                          console.error('Failed to rename note:', error);
-                        }
+                        await renameNote(node.id, newLabel);
                       }
                       setRenamingNodeId(null);
                     }
@@ -1660,7 +1676,33 @@ const CanvasView = ({ onBack, conceptName, conceptId, initialData, onSave }) =>
   };
   
   const updateNodeUrl = (id, mediaUrl) => {
-    setNodes(prev => updateNodeInTree(prev, id, n => ({ ...n, mediaUrl })));
+    setNodes(prev => updateNodeInTree(prev, id, n => ({ ...n, mediaUrl, mediaType: undefined })));
+  };
+
+  // Copies the file into the vault's attachments and shows it on the card
+  const attachNodeMedia = async (id, file) => {
+    try {
+      const attachment = await FileService.attachFile(file, file.name);
+      setNodes(prev => updateNodeInTree(prev, id, n => ({ ...n, mediaUrl: attachment.uri, mediaType: file.type || undefined })));
+    } catch (e) {
+      console.error('Failed to attach media:', e);
+      alert('Failed to attach file. Please try again.');
+    }
+  };
+
+  const renderMedia = (node) => {
+    const src = FileService.attachmentSrc(node.mediaUrl);
+    if (node.mediaType?.startsWith('video/')) {
+      return <video src={src} controls className="w-full h-full object-cover rounded" onMouseDown={(e) => e.stopPropagation()} />;
+    }
+    if (node.mediaType?.startsWith('audio/')) {
+      return (
+        <div className="w-full h-full flex items-center" onMouseDown={(e) => e.stopPropagation()}>
+          <audio src={src} controls className="w-full" />
+        </div>
+      );
+    }
+    return <MediaImage url={node.mediaUrl} />;
   };
 
   const updateNodeColor = (id, color) => {
@@ -1811,14 +1853,29 @@ const CanvasView = ({ onBack, conceptName, conceptId, initialData, onSave }) =>
                         onChange={(e) => updateNodeUrl(node.id, e.currentTarget.value)}
                         onMouseDown={(e) => e.stopPropagation()}
                       />
+                      <label
+                        className="bg-black/20 border border-white/10 rounded px-2 py-1 text-xs text-center cursor-pointer hover:bg-black/30"
+                        onMouseDown={(e) => e.stopPropagation()}
+                      >
+                        Attach file...
+                        <input
+                          type="file"
+                          accept="image/*,audio/*,video/*"
+                          className="hidden"
+                          onChange={(e) => {
+                            const file = e.currentTarget.files?.[0];
+                            if (file) attachNodeMedia(node.id, file);
+                          }}
+                        />
+                      </label>
                       <button onClick={() => setEditingNodeId(null)} className="bg-blue-600 text-xs px-2 py-1 rounded">Done</button>
                     </div>
                   ) : (
                     node.mediaUrl ? (
-                      <img src={node.mediaUrl} alt="Media" className="w-full h-full object-cover rounded pointer-events-none" />
+                      renderMedia(node)
                     ) : (
                       <div className="w-full h-full flex items-center justify-center bg-black/10 text-white/20 italic text-sm">
-                        Double click to add URL
+                        Double click to add a URL or file
                       </div>
                     )
                   )}
@@ -2114,7 +2171,11 @@ export default function MessyMap() {
     }));
     
     try {
-      await FileService.saveCanvas(id, data.nodes, data.edges);
+      // Autosave never loses work over a stray edge; it is dropped instead
+      const fixed = await FileService.saveCanvas(id, data.nodes, data.edges, true);
+      if (fixed.length > 0) {
+        console.warn('Repaired canvas while saving:', fixed.map(p => p.message));
+      }
     } catch (error) {
       console.error('Failed to save canvas:', error);
     }
@@ -1,6 +1,7 @@
 import React, { useState, useEffect, useRef } from 'react';
 import { Zap, X, Mic, MicOff, Image, Paperclip, Type } from 'lucide-react';
 import { useNotes } from '../contexts/NotesContext';
+import FileService from '../services/FileService';
 
 interface QuickCaptureProps {
   onClose: () => void;
@@ -8,6 +9,13 @@ interface QuickCaptureProps {
 
 type CaptureMode = 'text' | 'image' | 'audio' | 'link';
 
+// Pasted images are Blobs without a name
+function fileName(file: File | Blob): string {
+  if (file instanceof File) return file.name;
+  const extension = file.type.split('/')[1] || 'bin';
+  return `pasted.${extension}`;
+}
+
 // Extend the global Window interface for speech recognition
 declare global {
   interface Window {
@@ -98,7 +106,6 @@ export default function QuickCapture({ onClose }: QuickCaptureProps) {
       setMode('audio');
     } else {
       setMode('text');
-      setText(prev => prev + `\n[Attached: ${selectedFile.name}]`);
     }
   };
 
@@ -118,18 +125,27 @@ export default function QuickCapture({ onClose }: QuickCaptureProps) {
     setIsSaving(true);
 
     try {
-      const title = text.split('\n')[0].slice(0, 50) || 'Quick thought';
+      let body = text;
+      if (file) {
+        // Stored in the vault's attachments/ and linked from the note
+        const name = fileName(file);
+        const attachment = await FileService.attachFile(file, name);
+        const link = file.type.startsWith('image/') ? `![${name}](${attachment.uri})` : `[${name}](${attachment.uri})`;
+        body = body.trim() ? `${body}\n\n${link}` : link;
+      }
+
+      const title = text.split('\n')[0].slice(0, 50) || (file ? fileName(file) : 'Quick thought');
 
       // Use the context's createNote which handles everything properly
       await createNote({
         title,
-        rawText: text,
+        rawText: body,
         content: {
           type: 'doc',
           content: [
             {
               type: 'paragraph',
-              content: text ? [{ type: 'text', text }] : []
+              content: body ? [{ type: 'text', text: body }] : []
             }
           ]
         },
@@ -248,6 +264,21 @@ export default function QuickCapture({ onClose }: QuickCaptureProps) {
             </div>
           )}
 
+          {file && mode !== 'image' && (
+            <div className="mb-3 flex items-center gap-2 text-sm text-gray-600">
+              <Paperclip size={14} />
+              <span className="truncate">{fileName(file)}</span>
+              <button
+                type="button"
+                onClick={() => setFile(null)}
+                disabled={isSaving}
+                className="p-1 text-gray-400 hover:text-red-500 disabled:opacity-50"
+              >
+                <X size={12} />
+              </button>
+            </div>
+          )}
+
           <textarea
             ref={inputRef}
             value={text}
@@ -315,7 +346,7 @@ export default function QuickCapture({ onClose }: QuickCaptureProps) {
               <button
                 type="submit"
                 className="px-5 py-2 bg-gradient-to-r from-purple-600 to-blue-600 text-white rounded-lg hover:shadow-lg transition-all font-medium disabled:opacity-50 disabled:cursor-not-allowed text-sm"
-                disabled={!text.trim() || isSaving}
+                disabled={(!text.trim() && !file) || isSaving}
               >
                 {isSaving ? (
                   <span className="flex items-center gap-2">
@@ -24,6 +24,7 @@ import {
   ChevronsDownUp
 } from 'lucide-react';
 import { useNotes } from '../contexts/NotesContext';
+import FileService from '../services/FileService';
 import type { SidebarProps, FileTreeFolder, FileTreeItem, FileTreeNote } from '../types';
 
 const FOLDER_COLORS = ['#3b82f6', '#10b981', '#8b5cf6', '#f59e0b', '#ef4444', '#06b6d4'];
@@ -175,6 +176,8 @@ function Sidebar({ currentNoteId, onSelectNote, onNewNote }: SidebarProps) {
   const { notes, folders, loadNotes, loadFolders, deleteNote, createFolder, updateFolder, deleteFolder, moveNoteToFolder, updateNote, reorderNotes, lastSync, initialized } = useNotes();
 
   const [searchQuery, setSearchQuery] = useState('');
+  // Ids of notes whose title, text or canvas match, from the backend index
+  const [searchMatches, setSearchMatches] = useState<Set<string> | null>(null);
   const [showSearch, setShowSearch] = useState(false);
   const [isRefreshing, setIsRefreshing] = useState(false);
   const [expandedFolders, setExpandedFolders] = useState<Set<string>>(new Set());
@@ -226,6 +229,29 @@ function Sidebar({ currentNoteId, onSelectNote, onNewNote }: SidebarProps) {
     }
   }, [showSearch]);
 
+  useEffect(() => {
+    if (!searchQuery.trim()) {
+      setSearchMatches(null);
+      return;
+    }
+    let cancelled = false;
+    const timeout = setTimeout(async () => {
+      const hits = await FileService.searchNotes(searchQuery);
+      if (!cancelled) setSearchMatches(new Set(hits.map(h => h.id)));
+    }, 150);
+    return () => {
+      cancelled = true;
+      clearTimeout(timeout);
+    };
+  }, [searchQuery, lastSync]);
+
+  // Until the backend answers, fall back to matching titles
+  const matchesSearch = (note: { id: string; title: string }) => {
+    if (!searchQuery.trim()) return true;
+    if (searchMatches) return searchMatches.has(note.id);
+    return note.title.toLowerCase().includes(searchQuery.toLowerCase());
+  };
+
   useEffect(() => {
     const expanded = new Set<string>();
     folders.forEach(f => {
@@ -470,9 +496,7 @@ function Sidebar({ currentNoteId, onSelectNote, onNewNote }: SidebarProps) {
   }, []);
 
   const buildFileTree = (): FileTreeItem[] => {
-    let filteredNotes = notes.filter(n =>
-      n.title.toLowerCase().includes(searchQuery.toLowerCase())
-    );
+    let filteredNotes = notes.filter(matchesSearch);
     const filteredFolders = [...folders];
 
     const folderMap = new Map<string, FileTreeFolder>();
@@ -521,7 +545,7 @@ function Sidebar({ currentNoteId, onSelectNote, onNewNote }: SidebarProps) {
   };
 
   const fileTree = buildFileTree();
-  const pinnedNotes = notes.filter(n => n.sticky && n.title.toLowerCase().includes(searchQuery.toLowerCase()));
+  const pinnedNotes = notes.filter(n => n.sticky && matchesSearch(n));
 
   // Get all sortable IDs (flat list)
   const getAllIds = (items: FileTreeItem[]): string[] => {
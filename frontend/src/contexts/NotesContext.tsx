@@ -1,4 +1,5 @@
 import React, { createContext, useContext, useState, useCallback, useRef, useEffect, ReactNode } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import FileService from '../services/FileService';
 import type { Note, Folder, NotesContextType } from '../types';
 
@@ -64,6 +65,38 @@ export const NotesProvider: React.FC<NotesProviderProps> = ({ children }) => {
     }
   }, []);
 
+  // Keep in sync with edits made to the vault outside the app
+  useEffect(() => {
+    const unlisteners = [
+      listen<Note>('note-changed', ({ payload }) => {
+        setNotes(prev => {
+          const index = prev.findIndex(n => n.id === payload.id);
+          if (index === -1) return [payload, ...prev];
+
+          const updated = [...prev];
+          updated[index] = payload;
+          return updated;
+        });
+        setLastSync(Date.now());
+      }),
+      listen<{ id: string }>('note-deleted', ({ payload }) => {
+        setNotes(prev => prev.filter(n => n.id !== payload.id));
+        setLastSync(Date.now());
+      }),
+      listen('folders-changed', () => {
+        loadFolders();
+      }),
+      listen<string>('vault-changed', () => {
+        loadNotes(true);
+        loadFolders();
+      })
+    ];
+
+    return () => {
+      unlisteners.forEach(unlisten => unlisten.then(fn => fn()));
+    };
+  }, [loadNotes, loadFolders]);
+
   const getNote = useCallback((noteId: string): Note | undefined => {
     return notes.find(n => n.id === noteId);
   }, [notes]);
@@ -120,6 +153,29 @@ export const NotesProvider: React.FC<NotesProviderProps> = ({ children }) => {
     }
   }, [updateNoteLocal, loadNotes]);
 
+  const renameNote = useCallback(async (noteId: string, title: string) => {
+    // Optimistically update local state first
+    updateNoteLocal(noteId, { title });
+
+    try {
+      // Links can't name a title with [ ] | or #, so there are none to rewrite
+      const updateLinks = !/[[\]|#]/.test(title);
+      const report = await FileService.renameNote(noteId, title, updateLinks);
+
+      if (report.linksUpdated > 0) {
+        // Other notes' [[links]] were rewritten too
+        await loadNotes(false);
+      } else {
+        setNotes(prev => prev.map(n => (n.id === noteId ? report.note : n)));
+      }
+
+      setLastSync(Date.now());
+    } catch (error) {
+      console.error('Failed to rename note:', error);
+      await loadNotes(false);
+    }
+  }, [updateNoteLocal, loadNotes]);
+
   const createNote = useCallback(async (data: Partial<Note> = {}): Promise<Note> => {
     if (createNoteInProgressRef.current) {
       console.warn('Note creation already in progress');
@@ -171,6 +227,18 @@ export const NotesProvider: React.FC<NotesProviderProps> = ({ children }) => {
     }
   }, []);
 
+  const restoreNote = useCallback(async (noteId: string): Promise<Note> => {
+    try {
+      const restored = await FileService.restoreNote(noteId);
+      setNotes(prev => [restored, ...prev.filter(n => n.id !== noteId)]);
+      setLastSync(Date.now());
+      return restored;
+    } catch (error) {
+      console.error('Failed to restore note:', error);
+      throw error;
+    }
+  }, []);
+
   const createFolder = useCallback(async (name: string, parentId: string | null = null): Promise<Folder> => {
     try {
       const newFolder = await FileService.createFolder(name, parentId);
@@ -249,9 +317,11 @@ export const NotesProvider: React.FC<NotesProviderProps> = ({ children }) => {
     getNote,
     updateNoteLocal,
     updateNote,
+    renameNote,
     createNote,
     deleteNote,
     deleteAllNotes,
+    restoreNote,
     createFolder,
     updateFolder,
     deleteFolder,
@@ -1,5 +1,5 @@
 import { invoke } from '@tauri-apps/api/tauri';
-import type { Note, Folder, GraphData, GraphMetadata, Edge, CanvasData, CanvasNode } from '../types';
+import type { Note, Folder, GraphData, GraphMetadata, GraphPruned, Edge, CanvasData, CanvasNode, CanvasEdge, CanvasProblem, TrashEntry, Revision, RevisionKind, DiffLine, SearchHit, NoteLink, Backlink, UnlinkedMention, RenameReport, TagCount, RetagReport, EphemeralPolicy, ExpiringNote, LifecycleReport, VaultInfo, Attachment, AttachmentPreview, ObsidianImportReport, VaultIssue, VaultIssueKind, VaultRepair } from '../types';
 
 /**
  * ====== FILE SERVICE ======
@@ -11,6 +11,12 @@ import type { Note, Folder, GraphData, GraphMetadata, Edge, CanvasData, CanvasNo
  * - Folders: ~/Documents/MessyNotes/folders.json
  * - Graph: ~/Documents/MessyNotes/graph.json
  * - Canvas: ~/Documents/MessyNotes/canvas/{id}.json
+ * - Trash: ~/Documents/MessyNotes/.trash/{id}/
+ * - History: ~/Documents/MessyNotes/.history/{id}/
+ * - Attachments: ~/Documents/MessyNotes/attachments/{sha256}.{ext}
+ *
+ * (~/Documents/MessyNotes/ is the default vault; other vaults have the
+ * same layout in their own folder.)
  *
  * All operations are synchronous file I/O on the user's local disk.
  * No network requests, no cloud sync.
@@ -27,6 +33,7 @@ interface CreateNoteData {
   type?: string;
   color?: string;
   folderId?: string | null;
+  tags?: string[];
 }
 
 interface UpdateNoteData {
@@ -39,6 +46,8 @@ interface UpdateNoteData {
   archived?: boolean;
   folderId?: string | null;
   position?: number;
+  aliases?: string[];
+  tags?: string[];
 }
 
 interface UpdateFolderData {
@@ -90,6 +99,7 @@ class FileService {
         noteType: data.type,
         color: data.color,
         folderId: data.folderId || null,
+        tags: data.tags,
       });
     } catch (error) {
       console.error('Failed to create note:', error);
@@ -109,6 +119,8 @@ class FileService {
         archived: updates.archived,
         folderId: updates.folderId !== undefined ? updates.folderId : null,
         position: updates.position,
+        aliases: updates.aliases,
+        tags: updates.tags,
       });
     } catch (error) {
       console.error('Failed to update note:', error);
@@ -116,6 +128,15 @@ class FileService {
     }
   }
 
+  async renameNote(noteId: string, title: string, updateLinks = true): Promise<RenameReport> {
+    try {
+      return await invoke<RenameReport>('rename_note', { noteId, title, updateLinks });
+    } catch (error) {
+      console.error('Failed to rename note:', error);
+      throw error;
+    }
+  }
+
   async reorderNotes(noteId: string, targetFolderId: string | null, newPosition: number): Promise<void> {
     try {
       await invoke('reorder_notes', {
@@ -147,6 +168,122 @@ class FileService {
     }
   }
 
+  // ==================== TRASH ====================
+
+  async listTrash(): Promise<TrashEntry[]> {
+    try {
+      return await invoke<TrashEntry[]>('list_trash');
+    } catch (error) {
+      console.error('Failed to list trash:', error);
+      return [];
+    }
+  }
+
+  async restoreNote(id: string): Promise<Note> {
+    try {
+      return await invoke<Note>('restore_note', { id });
+    } catch (error) {
+      console.error('Failed to restore note:', error);
+      throw error;
+    }
+  }
+
+  async emptyTrash(): Promise<number> {
+    try {
+      return await invoke<number>('empty_trash');
+    } catch (error) {
+      console.error('Failed to empty trash:', error);
+      throw error;
+    }
+  }
+
+  async setTrashRetention(days: number): Promise<number> {
+    try {
+      return await invoke<number>('set_trash_retention', { days });
+    } catch (error) {
+      console.error('Failed to set trash retention:', error);
+      throw error;
+    }
+  }
+
+  // ==================== EPHEMERAL NOTES ====================
+
+  async getEphemeralPolicy(): Promise<EphemeralPolicy> {
+    try {
+      return await invoke<EphemeralPolicy>('get_ephemeral_policy');
+    } catch (error) {
+      console.error('Failed to get ephemeral policy:', error);
+      throw error;
+    }
+  }
+
+  async setEphemeralPolicy(policy: EphemeralPolicy): Promise<LifecycleReport> {
+    try {
+      return await invoke<LifecycleReport>('set_ephemeral_policy', { policy });
+    } catch (error) {
+      console.error('Failed to set ephemeral policy:', error);
+      throw error;
+    }
+  }
+
+  async getExpiringNotes(withinDays?: number): Promise<ExpiringNote[]> {
+    try {
+      return await invoke<ExpiringNote[]>('get_expiring_notes', { withinDays });
+    } catch (error) {
+      console.error('Failed to get expiring notes:', error);
+      return [];
+    }
+  }
+
+  // ==================== SEARCH ====================
+
+  async searchNotes(query: string, limit?: number): Promise<SearchHit[]> {
+    try {
+      return await invoke<SearchHit[]>('search_notes', { query, limit });
+    } catch (error) {
+      console.error('Failed to search notes:', error);
+      return [];
+    }
+  }
+
+  // ==================== HISTORY ====================
+
+  async listRevisions(noteId: string, kind?: RevisionKind): Promise<Revision[]> {
+    try {
+      return await invoke<Revision[]>('list_revisions', { noteId, kind });
+    } catch (error) {
+      console.error('Failed to list revisions:', error);
+      return [];
+    }
+  }
+
+  async getRevision(noteId: string, kind: RevisionKind, revisionId: string): Promise<string> {
+    try {
+      return await invoke<string>('get_revision', { noteId, kind, revisionId });
+    } catch (error) {
+      console.error('Failed to get revision:', error);
+      throw error;
+    }
+  }
+
+  async diffRevisions(noteId: string, kind: RevisionKind, from: string, to?: string): Promise<DiffLine[]> {
+    try {
+      return await invoke<DiffLine[]>('diff_revisions', { noteId, kind, from, to });
+    } catch (error) {
+      console.error('Failed to diff revisions:', error);
+      throw error;
+    }
+  }
+
+  async restoreRevision(noteId: string, kind: RevisionKind, revisionId: string): Promise<void> {
+    try {
+      await invoke('restore_revision', { noteId, kind, revisionId });
+    } catch (error) {
+      console.error('Failed to restore revision:', error);
+      throw error;
+    }
+  }
+
   // ==================== FOLDERS ====================
 
   async getFolders(): Promise<Folder[]> {
@@ -201,15 +338,80 @@ class FileService {
     }
   }
 
-  async saveGraph(nodes: GraphMetadata, edges: Edge[]): Promise<void> {
+  async saveGraph(nodes: GraphMetadata, edges: Edge[]): Promise<GraphPruned> {
     try {
-      await invoke('save_graph_data', { nodes, edges });
+      return await invoke<GraphPruned>('save_graph_data', { nodes, edges });
     } catch (error) {
       console.error('Failed to save graph:', error);
       throw error;
     }
   }
 
+  async getLinks(noteId?: string): Promise<NoteLink[]> {
+    try {
+      return await invoke<NoteLink[]>('get_links', { noteId });
+    } catch (error) {
+      console.error('Failed to get links:', error);
+      return [];
+    }
+  }
+
+  async getBacklinks(noteId: string): Promise<Backlink[]> {
+    try {
+      return await invoke<Backlink[]>('get_backlinks', { noteId });
+    } catch (error) {
+      console.error('Failed to get backlinks:', error);
+      return [];
+    }
+  }
+
+  async getUnlinkedMentions(noteId: string): Promise<UnlinkedMention[]> {
+    try {
+      return await invoke<UnlinkedMention[]>('get_unlinked_mentions', { noteId });
+    } catch (error) {
+      console.error('Failed to get unlinked mentions:', error);
+      return [];
+    }
+  }
+
+  // ==================== TAGS ====================
+
+  async listTags(): Promise<TagCount[]> {
+    try {
+      return await invoke<TagCount[]>('list_tags');
+    } catch (error) {
+      console.error('Failed to list tags:', error);
+      return [];
+    }
+  }
+
+  async getNotesByTag(tag: string): Promise<Note[]> {
+    try {
+      return await invoke<Note[]>('get_notes_by_tag', { tag });
+    } catch (error) {
+      console.error('Failed to get notes by tag:', error);
+      return [];
+    }
+  }
+
+  async renameTag(from: string, to: string): Promise<RetagReport> {
+    try {
+      return await invoke<RetagReport>('rename_tag', { from, to });
+    } catch (error) {
+      console.error('Failed to rename tag:', error);
+      throw error;
+    }
+  }
+
+  async mergeTags(sources: string[], target: string): Promise<RetagReport> {
+    try {
+      return await invoke<RetagReport>('merge_tags', { sources, target });
+    } catch (error) {
+      console.error('Failed to merge tags:', error);
+      throw error;
+    }
+  }
+
   // ==================== CANVAS ====================
 
   async getCanvas(noteId: string): Promise<CanvasData> {
@@ -221,14 +423,185 @@ class FileService {
     }
   }
 
-  async saveCanvas(noteId: string, nodes: CanvasNode[], edges: Edge[]): Promise<void> {
+  async saveCanvas(noteId: string, nodes: CanvasNode[], edges: CanvasEdge[], repair = false): Promise<CanvasProblem[]> {
     try {
-      await invoke('save_canvas_data', { noteId, nodes, edges });
+      return await invoke<CanvasProblem[]>('save_canvas_data', { noteId, nodes, edges, repair });
     } catch (error) {
       console.error('Failed to save canvas:', error);
       throw error;
     }
   }
+
+  async validateCanvas(nodes: CanvasNode[], edges: CanvasEdge[]): Promise<CanvasProblem[]> {
+    try {
+      return await invoke<CanvasProblem[]>('validate_canvas', { nodes, edges });
+    } catch (error) {
+      console.error('Failed to validate canvas:', error);
+      throw error;
+    }
+  }
+
+  async repairCanvas(noteId: string): Promise<CanvasProblem[]> {
+    try {
+      return await invoke<CanvasProblem[]>('repair_canvas', { noteId });
+    } catch (error) {
+      console.error('Failed to repair canvas:', error);
+      throw error;
+    }
+  }
+
+  // ==================== VAULTS ====================
+  // Opening, creating or switching a vault returns its path and fires
+  // vault-changed; everything loaded from the old vault is stale after that
+
+  async listVaults(): Promise<VaultInfo[]> {
+    try {
+      return await invoke<VaultInfo[]>('list_vaults');
+    } catch (error) {
+      console.error('Failed to list vaults:', error);
+      return [];
+    }
+  }
+
+  async openVault(path: string): Promise<string> {
+    try {
+      return await invoke<string>('open_vault', { path });
+    } catch (error) {
+      console.error('Failed to open vault:', error);
+      throw error;
+    }
+  }
+
+  async createVault(path: string): Promise<string> {
+    try {
+      return await invoke<string>('create_vault', { path });
+    } catch (error) {
+      console.error('Failed to create vault:', error);
+      throw error;
+    }
+  }
+
+  async switchVault(path: string): Promise<string> {
+    try {
+      return await invoke<string>('switch_vault', { path });
+    } catch (error) {
+      console.error('Failed to switch vault:', error);
+      throw error;
+    }
+  }
+
+  // ==================== ATTACHMENTS ====================
+
+  async importAttachment(path: string): Promise<Attachment> {
+    try {
+      return await invoke<Attachment>('import_attachment', { path });
+    } catch (error) {
+      console.error('Failed to import attachment:', error);
+      throw error;
+    }
+  }
+
+  // For files the webview holds (pasted, dropped, picked): data is base64
+  async importAttachmentData(name: string, data: string): Promise<Attachment> {
+    try {
+      return await invoke<Attachment>('import_attachment_data', { name, data });
+    } catch (error) {
+      console.error('Failed to import attachment:', error);
+      throw error;
+    }
+  }
+
+  // Imports a file or blob the webview holds (pasted, dropped, picked)
+  async attachFile(file: File | Blob, name: string): Promise<Attachment> {
+    const data = await new Promise<string>((resolve, reject) => {
+      const reader = new FileReader();
+      reader.onload = () => resolve(reader.result as string);
+      reader.onerror = () => reject(reader.error);
+      reader.readAsDataURL(file);
+    });
+    return this.importAttachmentData(name, data);
+  }
+
+  // The URL the webview loads an attachment's messy:// URI from. Windows
+  // webviews only serve custom schemes as https://{scheme}.localhost/.
+  attachmentSrc(uri: string): string {
+    if (uri.startsWith('messy://') && navigator.userAgent.includes('Windows')) {
+      return `https://messy.localhost/${uri.slice('messy://'.length)}`;
+    }
+    return uri;
+  }
+
+  // A downscaled, upright thumbnail of an image attachment
+  async getAttachmentPreview(hash: string, maxSize?: number): Promise<AttachmentPreview> {
+    try {
+      return await invoke<AttachmentPreview>('get_attachment_preview', { hash, maxSize });
+    } catch (error) {
+      console.error('Failed to get attachment preview:', error);
+      throw error;
+    }
+  }
+
+  async listAttachments(): Promise<Attachment[]> {
+    try {
+      return await invoke<Attachment[]>('list_attachments');
+    } catch (error) {
+      console.error('Failed to list attachments:', error);
+      return [];
+    }
+  }
+
+  // Fails while a note still uses the attachment
+  async deleteAttachment(hash: string): Promise<void> {
+    try {
+      await invoke('delete_attachment', { hash });
+    } catch (error) {
+      console.error('Failed to delete attachment:', error);
+      throw error;
+    }
+  }
+
+  // Removes every unused attachment; returns the removed file names
+  async collectAttachmentGarbage(): Promise<string[]> {
+    try {
+      return await invoke<string[]>('collect_attachment_garbage');
+    } catch (error) {
+      console.error('Failed to collect attachment garbage:', error);
+      throw error;
+    }
+  }
+
+  // ==================== IMPORT ====================
+
+  // Imports an Obsidian vault into a new folder; dryRun only reports
+  async importObsidianVault(path: string, dryRun?: boolean): Promise<ObsidianImportReport> {
+    try {
+      return await invoke<ObsidianImportReport>('import_obsidian_vault', { path, dryRun });
+    } catch (error) {
+      console.error('Failed to import Obsidian vault:', error);
+      throw error;
+    }
+  }
+
+  // ==================== VAULT CHECK ====================
+
+  async checkVault(): Promise<VaultIssue[]> {
+    try {
+      return await invoke<VaultIssue[]>('check_vault');
+    } catch (error) {
+      console.error('Failed to check vault:', error);
+      throw error;
+    }
+  }
+
+  // Repairs every kind of issue unless kinds is given
+  async repairVault(kinds?: VaultIssueKind[]): Promise<VaultRepair> {
+    try {
+      return await invoke<VaultRepair>('repair_vault', { kinds });
+    } catch (error) {
+      console.error('Failed to repair vault:', error);
+      throw error;
+    }
+  }
 }
 
 export default new FileService();
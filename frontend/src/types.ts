@@ -19,6 +19,47 @@ export interface Note {
   color: string;
   folderId?: string | null;
   position: number;
+  aliases?: string[];
+  tags: string[];
+  extra?: Record<string, unknown>; // frontmatter keys the app doesn't use, kept as written
+}
+
+export interface TrashEntry {
+  id: string;
+  title: string;
+  deletedAt: string;
+  folderId?: string | null;
+  node?: GraphNodeMetadata;
+  edges: Edge[];
+}
+
+export type RevisionKind = 'note' | 'canvas';
+
+export interface Revision {
+  id: string;
+  noteId: string;
+  kind: RevisionKind;
+  createdAt: string;
+  savedAt: string;
+  size: number;
+}
+
+export interface DiffLine {
+  op: 'equal' | 'insert' | 'delete';
+  text: string;
+}
+
+export interface SnippetPart {
+  text: string;
+  highlight: boolean;
+}
+
+export interface SearchHit {
+  id: string;
+  title: string;
+  score: number;
+  field: 'title' | 'body' | 'canvas';
+  snippet: SnippetPart[];
 }
 
 // ==================== FOLDER TYPES ====================
@@ -48,11 +89,80 @@ export interface GraphMetadata {
   [nodeId: string]: GraphNodeMetadata;
 }
 
+// Node entries and edges dropped on save because their notes are gone
+export interface GraphPruned {
+  nodes: string[];
+  edges: string[];
+}
+
 export interface Edge {
   id: string;
   source: string;
   target: string;
   label?: string;
+  // 'wikilink' edges are derived from [[links]] in note text by the backend
+  kind?: 'manual' | 'wikilink';
+}
+
+export interface NoteLink {
+  source: string;
+  target?: string;
+  targetText: string;
+  alias?: string;
+}
+
+export interface RenameReport {
+  note: Note;
+  oldTitle: string;
+  files: string[];
+  linksUpdated: number;
+}
+
+export interface EphemeralPolicy {
+  ttlDays: number;
+  expiry: 'archive' | 'trash';
+  promoteAfterEdits: number;
+  promoteAfterLinks: number;
+}
+
+export interface ExpiringNote {
+  noteId: string;
+  title: string;
+  expiresAt: string;
+  action: 'archive' | 'trash';
+}
+
+export interface LifecycleReport {
+  promoted: Note[];
+  archived: Note[];
+  trashed: string[];
+}
+
+export interface TagCount {
+  tag: string;
+  count: number;
+}
+
+export interface RetagReport {
+  files: string[];
+  notesUpdated: string[];
+}
+
+export type BacklinkSource = 'note' | 'canvas' | 'graph';
+
+export interface Backlink {
+  noteId: string;
+  title: string;
+  source: BacklinkSource;
+  cardId?: string;
+  contexts: string[];
+}
+
+export interface UnlinkedMention {
+  noteId: string;
+  title: string;
+  text: string;
+  context: string;
 }
 
 export interface GraphData {
@@ -77,13 +187,92 @@ export interface CanvasNode {
   text: string;
   color: string;
   mediaUrl?: string;
+  mediaType?: string; // MIME type of an attachment in mediaUrl
   children?: CanvasNode[];
   parentId?: string;
 }
 
+export type CanvasSide = 'top' | 'right' | 'bottom' | 'left';
+
+export interface CanvasEdge {
+  id: string;
+  fromNode: string;
+  toNode: string;
+  fromSide?: CanvasSide;
+  toSide?: CanvasSide;
+  label?: string;
+}
+
 export interface CanvasData {
   nodes: CanvasNode[];
-  edges: Edge[];
+  edges: CanvasEdge[];
+}
+
+export type CanvasProblemKind = 'missingId' | 'duplicateId' | 'badGeometry' | 'danglingEdge' | 'wrongParent';
+
+export interface CanvasProblem {
+  kind: CanvasProblemKind;
+  id: string;
+  message: string;
+}
+
+// ==================== VAULTS ====================
+
+export interface VaultInfo {
+  path: string;
+  name: string;
+  lastOpened?: string;
+  current: boolean; // the vault the app has open
+  available: boolean; // false if the folder is gone, e.g. an unmounted volume
+}
+
+// ==================== ATTACHMENTS ====================
+
+export interface Attachment {
+  hash: string; // SHA-256 of the contents
+  fileName: string; // name under attachments/
+  size: number;
+  uri: string; // messy://attachment/{hash}, what notes link to
+  notes: string[]; // notes that use it, trashed ones included
+}
+
+export interface AttachmentPreview {
+  hash: string;
+  width: number; // full image, the right way up
+  height: number;
+  orientation: number; // EXIF orientation it was stored with, 1 is upright
+  thumbnail: string; // data: URL
+  thumbnailWidth: number;
+  thumbnailHeight: number;
+}
+
+// ==================== IMPORT ====================
+
+export interface ObsidianImportReport {
+  dryRun: boolean;
+  name: string; // the folder everything was put in
+  folders: string[]; // paths relative to the Obsidian vault
+  notes: string[];
+  attachments: string[];
+  links: number; // [[links]] that became graph edges
+  unresolved: string[]; // links and embeds naming nothing in the vault
+  skipped: { path: string; reason: string }[];
+}
+
+// ==================== VAULT CHECK ====================
+
+export type VaultIssueKind = 'orphanedCanvas' | 'danglingFolder' | 'folderCycle' | 'duplicatePosition' | 'unparsable' | 'brokenEdge';
+
+export interface VaultIssue {
+  kind: VaultIssueKind;
+  path: string; // relative to the vault folder
+  id?: string; // the note, folder or edge concerned
+  message: string;
+}
+
+export interface VaultRepair {
+  fixed: VaultIssue[];
+  remaining: VaultIssue[];
 }
 
 // ==================== CONTEXT TYPES ====================
@@ -99,9 +288,11 @@ export interface NotesContextType {
   getNote: (noteId: string) => Note | undefined;
   updateNoteLocal: (noteId: string, updates: Partial<Note>) => void;
   updateNote: (noteId: string, updates: Partial<Note>) => Promise<void>;
+  renameNote: (noteId: string, title: string) => Promise<void>;
   createNote: (data?: Partial<Note>) => Promise<Note>;
   deleteNote: (noteId: string) => Promise<void>;
   deleteAllNotes: () => Promise<void>;
+  restoreNote: (noteId: string) => Promise<Note>;
   createFolder: (name: string, parentId?: string | null) => Promise<Folder>;
   updateFolder: (folderId: string, updates: Partial<Folder>) => Promise<void>;
   deleteFolder: (folderId: string) => Promise<void>;